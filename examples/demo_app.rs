@@ -24,13 +24,18 @@ pub fn main() {
     let context = VulkanoContext::new(VulkanoConfig::default());
     // Vulkano windows (create one)
     let mut windows = VulkanoWindows::default();
-    windows.create_window(&event_loop, &context, &WindowDescriptor::default(), |ci| {
-        ci.image_format = Some(vulkano::format::Format::B8G8R8A8_SRGB)
-    });
+    // The swapchain is in the surface's first format, which egui is drawn in whether it's sRGB or UNORM
+    windows.create_window(&event_loop, &context, &WindowDescriptor::default(), |_| {});
     // Create gui as main render pass (no overlay means it clears the image each frame)
     let mut gui = {
         let renderer = windows.get_primary_renderer_mut().unwrap();
-        Gui::new(renderer.surface(), renderer.graphics_queue(), false)
+        Gui::new_with_format(
+            renderer.surface(),
+            renderer.graphics_queue(),
+            renderer.swapchain_format(),
+            false,
+        )
+        .expect("Failed to create gui")
     };
     // Display the demo application that ships with egui.
     let mut demo_app = egui_demo_lib::DemoWindows::default();
@@ -43,7 +48,7 @@ pub fn main() {
                 if window_id == renderer.surface().window().id() =>
             {
                 // Update Egui integration so the UI works!
-                let _pass_events_to_game = !gui.update(&event);
+                let _pass_events_to_game = !gui.update(&event).consumed;
                 match event {
                     WindowEvent::Resized(_) => {
                         renderer.resize();
@@ -76,8 +81,9 @@ pub fn main() {
                 // Acquire swapchain future
                 let before_future = renderer.acquire().unwrap();
                 // Render gui
-                let after_future =
-                    gui.draw_on_image(before_future, renderer.swapchain_image_view());
+                let after_future = gui
+                    .draw_on_image(before_future, renderer.swapchain_image_view())
+                    .expect("Failed to draw gui");
                 // Present swapchain
                 renderer.present(after_future, true);
             }
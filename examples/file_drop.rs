@@ -0,0 +1,113 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use egui_winit_vulkano::Gui;
+use vulkano_util::{
+    context::{VulkanoConfig, VulkanoContext},
+    window::{VulkanoWindows, WindowDescriptor},
+};
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+};
+
+pub fn main() {
+    // Winit event loop
+    let event_loop = EventLoop::new();
+    // Vulkano context
+    let context = VulkanoContext::new(VulkanoConfig::default());
+    // Vulkano windows (create one)
+    let mut windows = VulkanoWindows::default();
+    // The swapchain is in the surface's first format, which egui is drawn in whether it's sRGB or UNORM
+    windows.create_window(&event_loop, &context, &WindowDescriptor::default(), |_| {});
+    // Create gui as main render pass (no overlay means it clears the image each frame)
+    let mut gui = {
+        let renderer = windows.get_primary_renderer_mut().unwrap();
+        Gui::new_with_format(
+            renderer.surface(),
+            renderer.graphics_queue(),
+            renderer.swapchain_format(),
+            false,
+        )
+        .expect("Failed to create gui")
+    };
+    // Files dropped on the window so far
+    let mut dropped_files: Vec<egui::DroppedFile> = vec![];
+    event_loop.run(move |event, _, control_flow| {
+        let renderer = windows.get_primary_renderer_mut().unwrap();
+        match event {
+            Event::WindowEvent { event, window_id }
+                if window_id == renderer.surface().window().id() =>
+            {
+                // Drag-and-drop events are forwarded to egui's input
+                let _pass_events_to_game = !gui.update(&event).consumed;
+                match event {
+                    WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                        renderer.resize();
+                    }
+                    WindowEvent::CloseRequested => {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    _ => (),
+                }
+            }
+            Event::RedrawRequested(_) => {
+                gui.immediate_ui(|gui| {
+                    let ctx = gui.context();
+                    dropped_files.extend(ctx.input().raw.dropped_files.iter().cloned());
+                    egui::CentralPanel::default().show(&ctx, |ui| {
+                        ui.heading("Drop files on the window");
+                        ui.separator();
+                        for file in &dropped_files {
+                            let size = file
+                                .path
+                                .as_ref()
+                                .and_then(|path| std::fs::metadata(path).ok())
+                                .map(|meta| format!("{} bytes", meta.len()))
+                                .unwrap_or_default();
+                            ui.horizontal(|ui| {
+                                ui.label(&file.name);
+                                ui.weak(size);
+                            });
+                        }
+                    });
+                    // Highlight the window while files are dragged over it
+                    let hovered_files = ctx.input().raw.hovered_files.len();
+                    if hovered_files > 0 {
+                        let painter = ctx.layer_painter(egui::LayerId::new(
+                            egui::Order::Foreground,
+                            egui::Id::new("file_drop"),
+                        ));
+                        let screen_rect = ctx.input().screen_rect();
+                        painter.rect_filled(screen_rect, 0.0, egui::Color32::from_black_alpha(192));
+                        painter.text(
+                            screen_rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            format!("Dropping {} file(s)", hovered_files),
+                            egui::TextStyle::Heading.resolve(&ctx.style()),
+                            egui::Color32::WHITE,
+                        );
+                    }
+                });
+                // Acquire swapchain future
+                let before_future = renderer.acquire().unwrap();
+                // Render gui
+                let after_future = gui
+                    .draw_on_image(before_future, renderer.swapchain_image_view())
+                    .expect("Failed to draw gui");
+                // Present swapchain
+                renderer.present(after_future, true);
+            }
+            Event::MainEventsCleared => {
+                renderer.surface().window().request_redraw();
+            }
+            _ => (),
+        }
+    });
+}
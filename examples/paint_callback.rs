@@ -0,0 +1,209 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use egui_winit_vulkano::{paint_callback, Gui};
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    device::Queue,
+    pipeline::{
+        graphics::{
+            input_assembly::InputAssemblyState, vertex_input::BuffersDefinition,
+            viewport::ViewportState,
+        },
+        GraphicsPipeline, Pipeline,
+    },
+    render_pass::Subpass,
+};
+use vulkano_util::{
+    context::{VulkanoConfig, VulkanoContext},
+    window::{VulkanoWindows, WindowDescriptor},
+};
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+};
+
+pub fn main() {
+    // Winit event loop
+    let event_loop = EventLoop::new();
+    // Vulkano context
+    let context = VulkanoContext::new(VulkanoConfig::default());
+    // Vulkano windows (create one)
+    let mut windows = VulkanoWindows::default();
+    // The swapchain is in the surface's first format, which egui is drawn in whether it's sRGB or UNORM
+    windows.create_window(&event_loop, &context, &WindowDescriptor::default(), |_| {});
+    // Create gui as main render pass (no overlay means it clears the image each frame)
+    let mut gui = {
+        let renderer = windows.get_primary_renderer_mut().unwrap();
+        Gui::new_with_format(
+            renderer.surface(),
+            renderer.graphics_queue(),
+            renderer.swapchain_format(),
+            false,
+        )
+        .expect("Failed to create gui")
+    };
+    // Our own pipeline drawn within the ui, created for the subpass egui is drawn in
+    let triangle = Arc::new(TrianglePipeline::new(context.graphics_queue(), gui.subpass()));
+    // Rotated by dragging the triangle
+    let mut angle = 0.0f32;
+    event_loop.run(move |event, _, control_flow| {
+        let renderer = windows.get_primary_renderer_mut().unwrap();
+        match event {
+            Event::WindowEvent { event, window_id }
+                if window_id == renderer.surface().window().id() =>
+            {
+                // Update Egui integration so the UI works!
+                let _pass_events_to_game = !gui.update(&event).consumed;
+                match event {
+                    WindowEvent::Resized(_) => {
+                        renderer.resize();
+                    }
+                    WindowEvent::ScaleFactorChanged { .. } => {
+                        renderer.resize();
+                    }
+                    WindowEvent::CloseRequested => {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    _ => (),
+                }
+            }
+            Event::RedrawRequested(window_id) if window_id == window_id => {
+                // Set immediate UI in redraw here
+                gui.immediate_ui(|gui| {
+                    let ctx = gui.context();
+                    egui::Window::new("Custom rendering").show(&ctx, |ui| {
+                        ui.label("Drag the triangle to rotate it");
+                        egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                            let (rect, response) = ui
+                                .allocate_exact_size(egui::vec2(256.0, 256.0), egui::Sense::drag());
+                            angle += response.drag_delta().x * 0.01;
+                            let triangle = triangle.clone();
+                            let angle = angle;
+                            // Viewport is set to `rect` & scissor to the clip rect, so the triangle is
+                            // clipped like the rest of the window
+                            ui.painter().add(egui::Shape::Callback(paint_callback(
+                                rect,
+                                move |_info, builder| {
+                                    builder
+                                        .bind_pipeline_graphics(triangle.pipeline.clone())
+                                        .push_constants(
+                                            triangle.pipeline.layout().clone(),
+                                            0,
+                                            vs::ty::PushConstants { angle },
+                                        )
+                                        .bind_vertex_buffers(0, triangle.vertex_buffer.clone())
+                                        .draw(3, 1, 0, 0)
+                                        .unwrap();
+                                },
+                            )));
+                        });
+                    });
+                });
+                // Render UI
+                // Acquire swapchain future
+                let before_future = renderer.acquire().unwrap();
+                // Render gui
+                let after_future = gui
+                    .draw_on_image(before_future, renderer.swapchain_image_view())
+                    .expect("Failed to draw gui");
+                // Present swapchain
+                renderer.present(after_future, true);
+            }
+            Event::MainEventsCleared => {
+                renderer.surface().window().request_redraw();
+            }
+            _ => (),
+        }
+    });
+}
+
+struct TrianglePipeline {
+    pipeline: Arc<GraphicsPipeline>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+}
+
+impl TrianglePipeline {
+    fn new(queue: Arc<Queue>, subpass: Subpass) -> Self {
+        let vs = vs::load(queue.device().clone()).expect("failed to create shader module");
+        let fs = fs::load(queue.device().clone()).expect("failed to create shader module");
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            // Egui sets both before calling paint callbacks
+            .viewport_state(ViewportState::viewport_dynamic_scissor_dynamic(1))
+            .render_pass(subpass)
+            .build(queue.device().clone())
+            .unwrap();
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            [
+                Vertex { position: [0.0, -0.8], color: [1.0, 0.0, 0.0, 1.0] },
+                Vertex { position: [0.7, 0.4], color: [0.0, 1.0, 0.0, 1.0] },
+                Vertex { position: [-0.7, 0.4], color: [0.0, 0.0, 1.0, 1.0] },
+            ]
+            .iter()
+            .cloned(),
+        )
+        .expect("failed to create buffer");
+        Self { pipeline, vertex_buffer }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, Zeroable, Pod)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+vulkano::impl_vertex!(Vertex, position, color);
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+
+layout(push_constant) uniform PushConstants {
+    float angle;
+} push_constants;
+
+layout(location = 0) out vec4 v_color;
+void main() {
+    float c = cos(push_constants.angle);
+    float s = sin(push_constants.angle);
+    gl_Position = vec4(mat2(c, s, -s, c) * position, 0.0, 1.0);
+    v_color = color;
+}"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = v_color;
+}"
+    }
+}
@@ -53,10 +53,8 @@ pub fn main() {
     let context = VulkanoContext::new(VulkanoConfig::default());
     // Vulkano windows (create one)
     let mut windows = VulkanoWindows::default();
-    windows.create_window(&event_loop, &context, &WindowDescriptor::default(), |ci| {
-        // If you don't use B8G8R8A8_SRGB, the egui result will be dark
-        ci.image_format = Some(vulkano::format::Format::B8G8R8A8_SRGB)
-    });
+    // The swapchain is in the surface's first format, which egui is drawn in whether it's sRGB or UNORM
+    windows.create_window(&event_loop, &context, &WindowDescriptor::default(), |_| {});
     // Create out gui pipeline
     let mut gui_pipeline = SimpleGuiPipeline::new(
         context.graphics_queue(),
@@ -67,7 +65,8 @@ pub fn main() {
         windows.get_primary_renderer_mut().unwrap().surface(),
         windows.get_primary_renderer_mut().unwrap().graphics_queue(),
         gui_pipeline.gui_pass(),
-    );
+    )
+    .expect("Failed to create gui");
     // Create gui state (pass anything your state requires)
     let mut code = CODE.to_owned();
     event_loop.run(move |event, _, control_flow| {
@@ -75,7 +74,7 @@ pub fn main() {
         match event {
             Event::WindowEvent { event, window_id } if window_id == renderer.window().id() => {
                 // Update Egui integration so the UI works!
-                let _pass_events_to_game = !gui.update(&event);
+                let _pass_events_to_game = !gui.update(&event).consumed;
                 match event {
                     WindowEvent::Resized(_) => {
                         renderer.resize();
@@ -279,7 +278,7 @@ impl SimpleGuiPipeline {
         // Move on to next subpass for gui
         builder.next_subpass(SubpassContents::SecondaryCommandBuffers).unwrap();
         // Draw gui on subpass
-        let cb = gui.draw_on_subpass_image(dimensions);
+        let cb = gui.draw_on_subpass_image(dimensions).unwrap();
         builder.execute_commands(cb).unwrap();
 
         // Last end render pass
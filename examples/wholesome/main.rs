@@ -44,10 +44,12 @@ pub struct GuiState {
 impl GuiState {
     pub fn new(gui: &mut Gui, scene_image: DeviceImageView, scene_view_size: [u32; 2]) -> GuiState {
         // tree.png asset is from https://github.com/sotrh/learn-wgpu/tree/master/docs/beginner/tutorial5-textures
-        let image_texture_id1 =
-            gui.register_user_image(include_bytes!("./assets/tree.png"), Format::R8G8B8A8_SRGB);
-        let image_texture_id2 =
-            gui.register_user_image(include_bytes!("./assets/doge2.png"), Format::R8G8B8A8_SRGB);
+        let image_texture_id1 = gui
+            .register_user_image(include_bytes!("./assets/tree.png"), Format::R8G8B8A8_SRGB)
+            .expect("Failed to register tree.png");
+        let image_texture_id2 = gui
+            .register_user_image(include_bytes!("./assets/doge2.png"), Format::R8G8B8A8_SRGB)
+            .expect("Failed to register doge2.png");
 
         GuiState {
             show_texture_window1: true,
@@ -55,7 +57,9 @@ impl GuiState {
             show_scene_window: true,
             image_texture_id1,
             image_texture_id2,
-            scene_texture_id: gui.register_user_image_view(scene_image.clone()),
+            scene_texture_id: gui
+                .register_user_image_view(scene_image.clone())
+                .expect("Failed to register scene image"),
             scene_view_size,
         }
     }
@@ -119,13 +123,18 @@ pub fn main() {
     let context = VulkanoContext::new(VulkanoConfig::default());
     // Vulkano windows (create one)
     let mut windows = VulkanoWindows::default();
-    windows.create_window(&event_loop, &context, &WindowDescriptor::default(), |ci| {
-        ci.image_format = Some(vulkano::format::Format::B8G8R8A8_SRGB)
-    });
+    // The swapchain is in the surface's first format, which egui is drawn in whether it's sRGB or UNORM
+    windows.create_window(&event_loop, &context, &WindowDescriptor::default(), |_| {});
     // Create gui as main render pass (no overlay means it clears the image each frame)
     let mut gui = {
         let renderer = windows.get_primary_renderer_mut().unwrap();
-        Gui::new(renderer.surface(), renderer.graphics_queue(), false)
+        Gui::new_with_format(
+            renderer.surface(),
+            renderer.graphics_queue(),
+            renderer.swapchain_format(),
+            false,
+        )
+        .expect("Failed to create gui")
     };
     // Create a simple image to which we'll draw the triangle scene
     let scene_image = StorageImage::general_purpose_image_view(
@@ -148,7 +157,7 @@ pub fn main() {
             Event::WindowEvent { event, window_id }
                 if window_id == renderer.surface().window().id() =>
             {
-                let _pass_events_to_game = !gui.update(&event);
+                let _pass_events_to_game = !gui.update(&event).consumed;
                 match event {
                     WindowEvent::Resized(_) => {
                         renderer.resize();
@@ -177,8 +186,9 @@ pub fn main() {
                 let after_scene_draw =
                     scene_render_pipeline.render(before_future, scene_image.clone());
                 // Render gui
-                let after_future =
-                    gui.draw_on_image(after_scene_draw, renderer.swapchain_image_view());
+                let after_future = gui
+                    .draw_on_image(after_scene_draw, renderer.swapchain_image_view())
+                    .expect("Failed to draw gui");
                 // Present swapchain
                 renderer.present(after_future, true);
 
@@ -2,3 +2,4 @@ cargo run --example wholesome --release
 cargo run --example minimal --release
 cargo run --example subpass --release
 cargo run --example demo_app --release
+cargo run --example paint_callback --release
@@ -0,0 +1,75 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::{any::Any, sync::Arc};
+
+use ahash::AHashMap;
+use egui::{PaintCallback, PaintCallbackInfo, Rect};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer};
+
+pub(crate) type CallbackRenderers = AHashMap<String, Arc<dyn CallbackRenderer>>;
+
+/// Render context paint callbacks get called with
+pub(crate) struct CallbackContext {
+    pub builder: AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+    pub renderers: Arc<CallbackRenderers>,
+}
+
+/// Renders custom content for paint callbacks created with [`keyed_paint_callback`]. Create its pipeline and other
+/// resources once & install it with [`Gui::register_callback_renderer`](crate::Gui::register_callback_renderer).
+pub trait CallbackRenderer: Send + Sync {
+    /// Records draw commands for a callback. `data` is what was given to `keyed_paint_callback`.
+    /// Viewport & scissor are set as with [`paint_callback`].
+    fn draw(
+        &self,
+        info: &PaintCallbackInfo,
+        data: &(dyn Any + Send + Sync),
+        builder: &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+    );
+}
+
+/// Creates an egui `PaintCallback` recording custom Vulkano draw commands into the command buffer egui is drawn with.
+/// Viewport is set to `rect` & scissor to the clip rect before calling `f`. Everything else (pipeline, descriptor sets
+/// etc.) must be bound by `f`. Pipelines must be created for [`Gui::subpass`](crate::Gui::subpass).
+pub fn paint_callback(
+    rect: Rect,
+    f: impl Fn(&PaintCallbackInfo, &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>)
+        + Send
+        + Sync
+        + 'static,
+) -> PaintCallback {
+    PaintCallback {
+        rect,
+        callback: Arc::new(move |info: &PaintCallbackInfo, render_ctx: &mut dyn Any| {
+            if let Some(ctx) = render_ctx.downcast_mut::<CallbackContext>() {
+                f(info, &mut ctx.builder);
+            }
+        }),
+    }
+}
+
+/// Creates an egui `PaintCallback` drawn by the [`CallbackRenderer`] registered with `key`, passing it `data`.
+/// Callbacks whose renderer isn't registered are skipped.
+pub fn keyed_paint_callback(
+    rect: Rect,
+    key: impl Into<String>,
+    data: impl Any + Send + Sync,
+) -> PaintCallback {
+    let key = key.into();
+    PaintCallback {
+        rect,
+        callback: Arc::new(move |info: &PaintCallbackInfo, render_ctx: &mut dyn Any| {
+            if let Some(ctx) = render_ctx.downcast_mut::<CallbackContext>() {
+                if let Some(renderer) = ctx.renderers.get(&key).cloned() {
+                    renderer.draw(info, &data, &mut ctx.builder);
+                }
+            }
+        }),
+    }
+}
@@ -0,0 +1,439 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::{
+    collections::VecDeque,
+    io::Write,
+    num::NonZeroU32,
+    ops::Range,
+    path::PathBuf,
+    rc::Rc,
+    sync::{mpsc, Arc},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, ImageFormat, ImageResult, RgbaImage,
+};
+use vulkano::{
+    buffer::{sys::UnsafeBuffer, BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{
+        submit::SubmitAnyBuilder, AutoCommandBufferBuilder, BufferImageCopy, CommandBufferUsage,
+        CopyImageToBufferInfo,
+    },
+    device::{Device, DeviceOwned, Queue},
+    format::Format,
+    image::{sys::UnsafeImage, ImageAccess, ImageLayout},
+    sync::{
+        AccessCheckError, AccessFlags, FenceSignalFuture, FlushError, GpuFuture, PipelineStages,
+    },
+    DeviceSize,
+};
+
+use crate::error::GuiError;
+
+/// Frame read back by a [`FrameRecorder`]
+pub struct CapturedFrame {
+    pub image: RgbaImage,
+    /// Index of the frame since recording started
+    pub index: u64,
+    /// Time since recording started
+    pub time: Duration,
+}
+
+/// Receives frames read back by a [`FrameRecorder`] on its encoding thread
+pub trait FrameSink: Send + 'static {
+    fn write_frame(&mut self, frame: CapturedFrame) -> ImageResult<()>;
+
+    /// Called once after the last frame
+    fn finish(&mut self) -> ImageResult<()> {
+        Ok(())
+    }
+}
+
+/// Encodes frames to a looping animated GIF, timed by when they were recorded
+pub struct GifSink<W: Write + Send + 'static> {
+    encoder: GifEncoder<W>,
+    /// Frame's delay is only known once the next one arrives
+    previous: Option<CapturedFrame>,
+}
+
+impl<W: Write + Send + 'static> GifSink<W> {
+    pub fn new(writer: W) -> ImageResult<GifSink<W>> {
+        // Speed of 10 trades some color quantization quality for encoding fast enough to keep up
+        let mut encoder = GifEncoder::new_with_speed(writer, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(GifSink { encoder, previous: None })
+    }
+
+    fn encode(&mut self, frame: CapturedFrame, delay: Duration) -> ImageResult<()> {
+        let delay = Delay::from_saturating_duration(delay);
+        self.encoder.encode_frame(Frame::from_parts(frame.image, 0, 0, delay))
+    }
+}
+
+impl<W: Write + Send + 'static> FrameSink for GifSink<W> {
+    fn write_frame(&mut self, frame: CapturedFrame) -> ImageResult<()> {
+        if let Some(previous) = self.previous.take() {
+            let delay = frame.time.saturating_sub(previous.time);
+            self.encode(previous, delay)?;
+        }
+        self.previous = Some(frame);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ImageResult<()> {
+        match self.previous.take() {
+            // 30 fps for the last frame
+            Some(previous) => self.encode(previous, Duration::from_millis(33)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes frames as a raw stream: for each frame its width & height as little endian `u32`s followed by tightly
+/// packed RGBA8 texels, rows top to bottom. E.g. for piping into `ffmpeg`.
+pub struct RawFrameSink<W: Write + Send + 'static> {
+    writer: W,
+}
+
+impl<W: Write + Send + 'static> RawFrameSink<W> {
+    pub fn new(writer: W) -> RawFrameSink<W> {
+        RawFrameSink { writer }
+    }
+}
+
+impl<W: Write + Send + 'static> FrameSink for RawFrameSink<W> {
+    fn write_frame(&mut self, frame: CapturedFrame) -> ImageResult<()> {
+        self.writer.write_all(&frame.image.width().to_le_bytes())?;
+        self.writer.write_all(&frame.image.height().to_le_bytes())?;
+        self.writer.write_all(frame.image.as_raw())?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ImageResult<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Saves frames as numbered PNGs (`frame_00000.png`, `frame_00001.png`...) in a directory
+pub struct PngSequenceSink {
+    directory: PathBuf,
+}
+
+impl PngSequenceSink {
+    /// Creates `directory` if it doesn't exist
+    pub fn new(directory: impl Into<PathBuf>) -> std::io::Result<PngSequenceSink> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        Ok(PngSequenceSink { directory })
+    }
+}
+
+impl FrameSink for PngSequenceSink {
+    fn write_frame(&mut self, frame: CapturedFrame) -> ImageResult<()> {
+        let path = self.directory.join(format!("frame_{:05}.png", frame.index));
+        frame.image.save_with_format(path, ImageFormat::Png)
+    }
+}
+
+/// Frame whose copy to host memory has been submitted
+struct PendingFrame {
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    fence: SharedFence,
+    size: [u32; 2],
+    bgra: bool,
+    index: u64,
+    time: Duration,
+}
+
+/// Fence of a frame's copy, shared by the recorder & the future continuing after it. Vulkano only implements
+/// `GpuFuture` for `Arc`ed fences, which the non-`Send` futures being chained would make pointless.
+#[derive(Clone)]
+struct SharedFence(Rc<FenceSignalFuture<Box<dyn GpuFuture>>>);
+
+unsafe impl DeviceOwned for SharedFence {
+    fn device(&self) -> &Arc<Device> {
+        self.0.device()
+    }
+}
+
+unsafe impl GpuFuture for SharedFence {
+    fn cleanup_finished(&mut self) {
+        // Only possible once the recorder has read the frame & dropped its clone
+        if let Some(fence) = Rc::get_mut(&mut self.0) {
+            fence.cleanup_finished();
+        }
+    }
+
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder<'_>, FlushError> {
+        // Always empty, as the fence's submission was flushed when it was created
+        self.0.build_submission()
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        self.0.flush()
+    }
+
+    unsafe fn signal_finished(&self) {
+        self.0.signal_finished()
+    }
+
+    fn queue_change_allowed(&self) -> bool {
+        self.0.queue_change_allowed()
+    }
+
+    fn queue(&self) -> Option<Arc<Queue>> {
+        self.0.queue()
+    }
+
+    fn check_buffer_access(
+        &self,
+        buffer: &UnsafeBuffer,
+        range: Range<DeviceSize>,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        self.0.check_buffer_access(buffer, range, exclusive, queue)
+    }
+
+    fn check_image_access(
+        &self,
+        image: &UnsafeImage,
+        range: Range<DeviceSize>,
+        exclusive: bool,
+        expected_layout: ImageLayout,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        self.0.check_image_access(image, range, exclusive, expected_layout, queue)
+    }
+}
+
+/// Frames in flight before recording waits for the oldest readback
+const MAX_PENDING_FRAMES: usize = 2;
+
+/// Records rendered frames, e.g. to produce documentation clips of your tools. Frames are copied to host memory
+/// after drawing and read back a frame later when the copy has finished, so recording doesn't stall the GPU.
+/// Encoding happens on a separate thread.
+pub struct FrameRecorder {
+    queue: Arc<Queue>,
+    pending: VecDeque<PendingFrame>,
+    sender: Option<mpsc::Sender<CapturedFrame>>,
+    worker: Option<JoinHandle<ImageResult<()>>>,
+    start: Instant,
+    next_index: u64,
+}
+
+impl FrameRecorder {
+    /// Creates a recorder passing frames to `sink`. `queue` is used for copying frames & should be the queue
+    /// they're rendered with.
+    pub fn new(queue: Arc<Queue>, mut sink: impl FrameSink) -> FrameRecorder {
+        let (sender, receiver) = mpsc::channel::<CapturedFrame>();
+        let worker = std::thread::spawn(move || {
+            for frame in receiver {
+                sink.write_frame(frame)?;
+            }
+            sink.finish()
+        });
+        FrameRecorder {
+            queue,
+            pending: VecDeque::new(),
+            sender: Some(sender),
+            worker: Some(worker),
+            start: Instant::now(),
+            next_index: 0,
+        }
+    }
+
+    /// Records to an animated GIF written to `writer`
+    pub fn gif(
+        queue: Arc<Queue>,
+        writer: impl Write + Send + 'static,
+    ) -> ImageResult<FrameRecorder> {
+        Ok(FrameRecorder::new(queue, GifSink::new(writer)?))
+    }
+
+    /// Records to a raw frame stream written to `writer`, see [`RawFrameSink`]
+    pub fn raw(queue: Arc<Queue>, writer: impl Write + Send + 'static) -> FrameRecorder {
+        FrameRecorder::new(queue, RawFrameSink::new(writer))
+    }
+
+    /// Copies `image` to host memory once `before_future` (e.g. returned by `Gui::draw_on_image`) has finished.
+    /// Returns the future to continue with, e.g. to present. The image must have `transfer_src` usage & a format
+    /// with 8 bits per channel RGBA or BGRA, else [`GuiError::UnreadableFormat`] is returned.
+    pub fn record<F>(
+        &mut self,
+        before_future: F,
+        image: Arc<dyn ImageAccess>,
+    ) -> Result<Box<dyn GpuFuture>, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        self.receive_finished(false);
+
+        let bgra = match image.format() {
+            Format::B8G8R8A8_SRGB | Format::B8G8R8A8_UNORM => true,
+            Format::R8G8B8A8_SRGB | Format::R8G8B8A8_UNORM => false,
+            format => return Err(GuiError::UnreadableFormat(format)),
+        };
+        let dimensions = image.dimensions();
+        let size = [dimensions.width(), dimensions.height()];
+        let buffer = unsafe {
+            CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+                self.queue.device().clone(),
+                size[0] as DeviceSize * size[1] as DeviceSize * 4,
+                BufferUsage::transfer_dst(),
+                true,
+            )
+            .map_err(GuiError::ReadbackBuffer)?
+        };
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        cbb.copy_image_to_buffer(CopyImageToBufferInfo {
+            regions: [BufferImageCopy {
+                image_subresource: image.subresource_layers(),
+                image_extent: [size[0], size[1], 1],
+                ..Default::default()
+            }]
+            .into(),
+            ..CopyImageToBufferInfo::image_buffer(image, buffer.clone())
+        })?;
+        let command_buffer = cbb.build()?;
+        let after_copy: Box<dyn GpuFuture> =
+            Box::new(before_future.then_execute(self.queue.clone(), command_buffer)?);
+        let fence = SharedFence(Rc::new(after_copy.then_signal_fence_and_flush()?));
+
+        self.pending.push_back(PendingFrame {
+            buffer,
+            fence: fence.clone(),
+            size,
+            bgra,
+            index: self.next_index,
+            time: self.start.elapsed(),
+        });
+        self.next_index += 1;
+        Ok(Box::new(fence))
+    }
+
+    /// Sends frames whose copy has finished to the encoding thread, waiting for all with `wait_all` & for the oldest
+    /// if too many are in flight
+    fn receive_finished(&mut self, wait_all: bool) {
+        while let Some(frame) = self.pending.front() {
+            let must_wait = wait_all || self.pending.len() >= MAX_PENDING_FRAMES;
+            if !must_wait && !frame.fence.0.is_signaled().unwrap_or(false) {
+                break;
+            }
+            let frame = self.pending.pop_front().unwrap();
+            // Also releases the buffer for reading
+            if frame.fence.0.wait(None).is_err() {
+                continue;
+            }
+            let mut texels = match frame.buffer.read() {
+                Ok(texels) => texels.to_vec(),
+                Err(_) => continue,
+            };
+            if frame.bgra {
+                for texel in texels.chunks_exact_mut(4) {
+                    texel.swap(0, 2);
+                }
+            }
+            let image = match RgbaImage::from_raw(frame.size[0], frame.size[1], texels) {
+                Some(image) => image,
+                None => continue,
+            };
+            if let Some(sender) = &self.sender {
+                // Encoding thread only hangs up on errors, which `finish` reports
+                let _ = sender.send(CapturedFrame { image, index: frame.index, time: frame.time });
+            }
+        }
+    }
+
+    /// Waits for frames in flight, finishes encoding & returns the first encoding error if there was one
+    pub fn finish(mut self) -> ImageResult<()> {
+        self.finish_impl()
+    }
+
+    fn finish_impl(&mut self) -> ImageResult<()> {
+        self.receive_finished(true);
+        self.sender = None;
+        match self.worker.take() {
+            Some(worker) => worker.join().expect("Frame encoding thread panicked"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for FrameRecorder {
+    fn drop(&mut self) {
+        let _ = self.finish_impl();
+    }
+}
+
+/// Saves every Nth drawn frame to numbered PNGs while enabled, e.g. for documentation screenshots or visual
+/// regression archives. Frames are read back & encoded like with [`FrameRecorder`], so dumping has little overhead.
+pub struct FrameDumper {
+    recorder: FrameRecorder,
+    every_nth: u64,
+    frame: u64,
+    enabled: bool,
+}
+
+impl FrameDumper {
+    /// Creates a dumper saving every `every_nth` frame to `directory`, see [`PngSequenceSink`].
+    /// Starts enabled.
+    pub fn new(
+        queue: Arc<Queue>,
+        directory: impl Into<PathBuf>,
+        every_nth: NonZeroU32,
+    ) -> std::io::Result<FrameDumper> {
+        Ok(FrameDumper {
+            recorder: FrameRecorder::new(queue, PngSequenceSink::new(directory)?),
+            every_nth: every_nth.get() as u64,
+            frame: 0,
+            enabled: true,
+        })
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Call for each drawn frame, see [`FrameRecorder::record`]. Frames are counted while disabled too.
+    pub fn dump<F>(
+        &mut self,
+        before_future: F,
+        image: Arc<dyn ImageAccess>,
+    ) -> Result<Box<dyn GpuFuture>, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        let frame = self.frame;
+        self.frame += 1;
+        if self.enabled && frame % self.every_nth == 0 {
+            self.recorder.record(before_future, image)
+        } else {
+            Ok(before_future.boxed())
+        }
+    }
+
+    /// Waits for frames in flight to be saved & returns the first error if there was one
+    pub fn finish(self) -> ImageResult<()> {
+        self.recorder.finish()
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+/// Text clipboard egui copies to & pastes from instead of the system one (egui-winit's `arboard`), e.g. in sandboxes
+/// without clipboard access, to work around compositor quirks, or to keep a clipboard history. Install with
+/// [`Gui::set_clipboard`](crate::Gui::set_clipboard).
+pub trait ClipboardProvider: Send {
+    /// Text to paste, called on the paste shortcut. `None` or empty text pastes nothing.
+    fn get(&mut self) -> Option<String>;
+
+    /// Called with the text egui copied or cut
+    fn set(&mut self, text: String);
+}
+
+/// Replaces the pastes egui-winit read from the system clipboard with the contents of `clipboard`. Egui-winit
+/// only emits a paste when the system clipboard has text, so every paste shortcut is looked for in the key events
+/// (which follow the paste).
+pub(crate) fn replace_pastes(events: &mut Vec<egui::Event>, clipboard: &mut dyn ClipboardProvider) {
+    events.retain(|event| !matches!(event, egui::Event::Paste(_)));
+    let mut i = 0;
+    while i < events.len() {
+        if let egui::Event::Key { key, pressed: true, modifiers } = events[i] {
+            if is_paste_command(modifiers, key) {
+                let text = clipboard.get().map(|text| text.replace("\r\n", "\n"));
+                if let Some(text) = text.filter(|text| !text.is_empty()) {
+                    events.insert(i, egui::Event::Paste(text));
+                    i += 1;
+                }
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Same shortcuts as egui-winit
+fn is_paste_command(modifiers: egui::Modifiers, key: egui::Key) -> bool {
+    (modifiers.command && key == egui::Key::V)
+        || (cfg!(target_os = "windows") && modifiers.shift && key == egui::Key::Insert)
+}
@@ -0,0 +1,181 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use ahash::AHashMap;
+use egui::{
+    epaint::{Mesh, Primitive},
+    ClippedPrimitive, Rect,
+};
+use vulkano::swapchain::{PresentRegion, RectangleLayer};
+
+/// Screen areas (in points) where the ui changed since the previous frame, see
+/// [`Gui::set_damage_tracking`](crate::Gui::set_damage_tracking).
+///
+/// Primitives are compared per clip rect (roughly egui's areas, windows & panels): an area that changed, or is now
+/// drawn above or below other areas it overlaps (e.g. a window brought to the front), is damaged where it was drawn
+/// in both frames. Paint callbacks are always damaged, as are areas drawing user images when those change (e.g. are
+/// updated or registered again under their id). Texture changes of egui (e.g. new glyphs in the font atlas) or a
+/// resize damage the whole screen.
+#[derive(Debug, Clone, Default)]
+pub struct DamageRegions {
+    pub rects: Vec<Rect>,
+    hasher: ahash::RandomState,
+    /// Content hash & bounds of each clip rect's primitives in the previous frame
+    areas: AHashMap<[u32; 4], (u64, Rect)>,
+    /// Screen rect & scale of the previous frame
+    screen: Option<(Rect, f32)>,
+    /// `Renderer::user_textures_version` of the previous frame
+    user_textures_version: u64,
+}
+
+impl DamageRegions {
+    pub(crate) fn update(
+        &mut self,
+        clipped_primitives: &[ClippedPrimitive],
+        textures_changed: bool,
+        user_textures_version: u64,
+        screen_rect: Rect,
+        pixels_per_point: f32,
+    ) {
+        let mut areas: AHashMap<[u32; 4], (ahash::AHasher, Rect)> = AHashMap::default();
+        // Clip rects in the order they're first drawn in
+        let mut order = vec![];
+        let mut callback_rects = vec![];
+        // Areas drawing user images, which look different when those change
+        let mut user_image_areas = vec![];
+        for ClippedPrimitive { clip_rect, primitive } in clipped_primitives {
+            let key = rect_bits(*clip_rect);
+            match primitive {
+                Primitive::Mesh(mesh) if !mesh.is_empty() => {
+                    let bounds = mesh.calc_bounds().intersect(*clip_rect);
+                    let (state, area) = areas.entry(key).or_insert_with(|| {
+                        order.push(key);
+                        (self.hasher.build_hasher(), Rect::NOTHING)
+                    });
+                    *area = area.union(bounds);
+                    hash_mesh(mesh, state);
+                    if matches!(mesh.texture_id, egui::TextureId::User(_)) {
+                        user_image_areas.push(key);
+                    }
+                }
+                Primitive::Mesh(_) => {}
+                Primitive::Callback(callback) => {
+                    callback_rects.push(callback.rect.intersect(*clip_rect));
+                }
+            }
+        }
+        // Overlapping areas drawn beneath are part of an area's hash, so reordering them damages both
+        let areas: AHashMap<[u32; 4], (u64, Rect)> = order
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let (state, area) = &areas[key];
+                let mut state = state.clone();
+                for below in &order[..i] {
+                    if areas[below].1.intersects(*area) {
+                        below.hash(&mut state);
+                    }
+                }
+                (*key, (state.finish(), *area))
+            })
+            .collect();
+
+        let screen = Some((screen_rect, pixels_per_point));
+        let user_textures_changed = user_textures_version != self.user_textures_version;
+        self.rects.clear();
+        if textures_changed || self.screen != screen {
+            self.rects.push(screen_rect);
+        } else {
+            for (key, (hash, area)) in &areas {
+                let user_images_changed = user_textures_changed && user_image_areas.contains(key);
+                match self.areas.get(key) {
+                    Some((previous_hash, _)) if previous_hash == hash && !user_images_changed => {}
+                    Some((_, previous_area)) => self.rects.push(previous_area.union(*area)),
+                    None => self.rects.push(*area),
+                }
+            }
+            let removed = self.areas.iter().filter(|(key, _)| !areas.contains_key(*key));
+            self.rects.extend(removed.map(|(_, (_, area))| *area));
+            self.rects.extend(callback_rects);
+            self.rects.retain(|rect| rect.is_positive());
+        }
+        self.areas = areas;
+        self.screen = screen;
+        self.user_textures_version = user_textures_version;
+    }
+
+    /// Nothing changed, e.g. when the frame was drawn from the ui cache
+    pub(crate) fn clear(&mut self) {
+        self.rects.clear();
+    }
+
+    /// Whether nothing changed since the previous frame
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Damage in pixels of a swapchain image sized `image_extent`, for vulkano's `present_incremental` (which
+    /// needs the device's `khr_incremental_present` extension, & is ignored without it). Add the damage of your own
+    /// rendering, as the hint covers the whole image.
+    pub fn present_region(&self, pixels_per_point: f32, image_extent: [u32; 2]) -> PresentRegion {
+        let rectangles = self
+            .rects
+            .iter()
+            .filter_map(|rect| {
+                let min = [
+                    (rect.min.x * pixels_per_point).floor().max(0.0) as u32,
+                    (rect.min.y * pixels_per_point).floor().max(0.0) as u32,
+                ];
+                let max = [
+                    ((rect.max.x * pixels_per_point).ceil().max(0.0) as u32).min(image_extent[0]),
+                    ((rect.max.y * pixels_per_point).ceil().max(0.0) as u32).min(image_extent[1]),
+                ];
+                if max[0] <= min[0] || max[1] <= min[1] {
+                    return None;
+                }
+                Some(RectangleLayer {
+                    offset: [min[0] as i32, min[1] as i32],
+                    extent: [max[0] - min[0], max[1] - min[1]],
+                    layer: 0,
+                })
+            })
+            .collect();
+        PresentRegion { rectangles }
+    }
+}
+
+/// Hash of what `clipped_primitives` draw, `None` with paint callbacks as what they draw can't be compared
+pub(crate) fn primitives_hash(clipped_primitives: &[ClippedPrimitive]) -> Option<u64> {
+    let mut state = ahash::AHasher::default();
+    for ClippedPrimitive { clip_rect, primitive } in clipped_primitives {
+        match primitive {
+            Primitive::Mesh(mesh) => {
+                rect_bits(*clip_rect).hash(&mut state);
+                hash_mesh(mesh, &mut state);
+            }
+            Primitive::Callback(_) => return None,
+        }
+    }
+    Some(state.finish())
+}
+
+fn hash_mesh(mesh: &Mesh, state: &mut impl Hasher) {
+    mesh.texture_id.hash(state);
+    mesh.indices.hash(state);
+    for v in &mesh.vertices {
+        [v.pos.x, v.pos.y, v.uv.x, v.uv.y].map(f32::to_bits).hash(state);
+        v.color.hash(state);
+    }
+}
+
+fn rect_bits(rect: Rect) -> [u32; 4] {
+    [rect.min.x, rect.min.y, rect.max.x, rect.max.y].map(f32::to_bits)
+}
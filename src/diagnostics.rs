@@ -0,0 +1,194 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::fmt::Write;
+
+use vulkano::{
+    device::{physical::PhysicalDevice, Device},
+    format::{Format, FormatFeatures},
+    image::SampleCount,
+    pipeline::graphics::render_pass::PipelineRenderPassType,
+};
+
+use crate::renderer::EguiVertex;
+
+/// Format egui's textures are uploaded with
+pub(crate) const TEXTURE_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+/// Features egui's textures need: sampling with linear filtering & uploads (partial ones are blitted)
+fn texture_format_problems(features: &FormatFeatures) -> Vec<&'static str> {
+    let mut problems = vec![];
+    if !features.sampled_image {
+        problems.push("sampled_image");
+    }
+    if !features.sampled_image_filter_linear {
+        problems.push("sampled_image_filter_linear");
+    }
+    if !features.transfer_dst {
+        problems.push("transfer_dst");
+    }
+    if !(features.blit_src && features.blit_dst) {
+        problems.push("blit_src & blit_dst");
+    }
+    problems
+}
+
+/// Features egui's render target needs: rendering with alpha blending
+fn target_format_problems(features: &FormatFeatures) -> Vec<&'static str> {
+    let mut problems = vec![];
+    if !features.color_attachment {
+        problems.push("color_attachment");
+    }
+    if !features.color_attachment_blend {
+        problems.push("color_attachment_blend");
+    }
+    problems
+}
+
+/// Formats of the color attachments egui's pipeline is created for
+fn color_attachment_formats(render_pass: &PipelineRenderPassType) -> Vec<Option<Format>> {
+    match render_pass {
+        PipelineRenderPassType::BeginRenderPass(subpass) => subpass
+            .subpass_desc()
+            .color_attachments
+            .iter()
+            .map(|attachment| {
+                attachment.as_ref().and_then(|attachment| {
+                    subpass.render_pass().attachments()[attachment.attachment as usize].format
+                })
+            })
+            .collect(),
+        PipelineRenderPassType::BeginRendering(rendering_info) => {
+            rendering_info.color_attachment_formats.clone()
+        }
+    }
+}
+
+/// Lists reasons why egui's pipeline can't be created for `render_pass` on `device`
+pub(crate) fn pipeline_problems(
+    device: &Device,
+    render_pass: &PipelineRenderPassType,
+) -> Vec<String> {
+    let physical_device = device.physical_device();
+    let mut problems = vec![];
+    if let PipelineRenderPassType::BeginRendering(_) = render_pass {
+        if !device.enabled_features().dynamic_rendering {
+            problems.push("dynamic_rendering feature is not enabled".to_string());
+        }
+    }
+    let formats = color_attachment_formats(render_pass);
+    if formats.is_empty() {
+        problems.push("pass has no color attachments".to_string());
+    } else if formats.len() > 1 && !device.enabled_features().independent_blend {
+        problems.push(format!(
+            "pass has {} color attachments, drawing on the first without writing the others needs \
+             the independent_blend feature",
+            formats.len()
+        ));
+    }
+    // Egui only draws on the first one
+    if let Some(Some(format)) = formats.first() {
+        let features = physical_device.format_properties(*format).optimal_tiling_features;
+        for feature in target_format_problems(&features) {
+            problems.push(format!("render target format {:?} lacks {}", format, feature));
+        }
+    }
+    // Portability subset implementations (e.g. MoltenVK) may require vertex strides to be aligned
+    if let Some(alignment) = physical_device.properties().min_vertex_input_binding_stride_alignment
+    {
+        let stride = std::mem::size_of::<EguiVertex>() as u32;
+        if alignment > 0 && stride % alignment != 0 {
+            problems.push(format!(
+                "vertex stride {} is not a multiple of min_vertex_input_binding_stride_alignment \
+                 {}",
+                stride, alignment
+            ));
+        }
+    }
+    let features = physical_device.format_properties(TEXTURE_FORMAT).optimal_tiling_features;
+    for feature in texture_format_problems(&features) {
+        problems.push(format!("texture format {:?} lacks {}", TEXTURE_FORMAT, feature));
+    }
+    problems
+}
+
+/// Panic message for a failed egui pipeline creation, describing the requested configuration
+pub(crate) fn pipeline_error_message(
+    error: impl std::fmt::Display,
+    device: &Device,
+    render_pass: &PipelineRenderPassType,
+    sample_count: SampleCount,
+) -> String {
+    let pass = match render_pass {
+        PipelineRenderPassType::BeginRenderPass(subpass) => format!("subpass {}", subpass.index()),
+        PipelineRenderPassType::BeginRendering(_) => "dynamic rendering".to_string(),
+    };
+    let mut message = format!(
+        "Failed to create egui pipeline for {} ({} color attachments, {:?} samples): {}",
+        pass,
+        color_attachment_formats(render_pass).len(),
+        sample_count,
+        error
+    );
+    for problem in pipeline_problems(device, render_pass) {
+        let _ = write!(message, "\n- {}", problem);
+    }
+    message
+}
+
+/// Human readable report of what egui needs from `physical_device`, see [`Gui::diagnose_device`](crate::Gui::diagnose_device)
+pub(crate) fn device_report(physical_device: PhysicalDevice, output_format: Format) -> String {
+    let properties = physical_device.properties();
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "Device: {} ({:?}), Vulkan {}",
+        properties.device_name,
+        properties.device_type,
+        physical_device.api_version()
+    );
+    if let Some(driver_name) = &properties.driver_name {
+        let _ = writeln!(
+            report,
+            "Driver: {} {}",
+            driver_name,
+            properties.driver_info.as_deref().unwrap_or("")
+        );
+    }
+    // Vulkano enables the extension whenever it's supported
+    let _ = writeln!(
+        report,
+        "Portability subset: {}",
+        if physical_device.supported_extensions().khr_portability_subset { "yes" } else { "no" }
+    );
+    let _ = writeln!(report, "Max 2D image dimension: {}", properties.max_image_dimension2_d);
+    let _ = writeln!(report, "Max push constants size: {}", properties.max_push_constants_size);
+    let checks = [
+        (
+            output_format,
+            target_format_problems(
+                &physical_device.format_properties(output_format).optimal_tiling_features,
+            ),
+        ),
+        (
+            TEXTURE_FORMAT,
+            texture_format_problems(
+                &physical_device.format_properties(TEXTURE_FORMAT).optimal_tiling_features,
+            ),
+        ),
+    ];
+    for (format, problems) in checks {
+        if problems.is_empty() {
+            let _ = writeln!(report, "{:?}: ok", format);
+        } else {
+            let _ = writeln!(report, "{:?}: missing {}", format, problems.join(", "));
+        }
+    }
+    report
+}
@@ -0,0 +1,386 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::{fmt, path::PathBuf};
+
+use vulkano::{
+    command_buffer::{BuildError, CommandBufferBeginError, CommandBufferExecError, CopyError},
+    descriptor_set::{pool::DescriptorPoolAllocError, DescriptorSetCreationError},
+    device::physical::SurfacePropertiesError,
+    format::Format,
+    image::{
+        immutable::ImmutableImageCreationError, view::ImageViewCreationError, ImageCreationError,
+    },
+    memory::DeviceMemoryAllocationError,
+    render_pass::RenderPassCreationError,
+    sampler::SamplerCreationError,
+    shader::ShaderCreationError,
+    sync::FlushError,
+};
+
+/// Errors of creating the gui, drawing it & registering images
+#[derive(Debug)]
+pub enum GuiError {
+    /// Querying the formats the surface supports failed
+    SurfaceFormats(SurfacePropertiesError),
+    /// The surface supports no sRGB or UNORM format, or not the format asked for
+    UnsupportedFormat(Option<Format>),
+    /// The subpass given to [`Gui::new_with_subpass`](crate::Gui::new_with_subpass) has no color attachment
+    NoColorAttachment,
+    /// [`Gui::new_with_dynamic_rendering`](crate::Gui::new_with_dynamic_rendering) needs the device's
+    /// `dynamic_rendering` feature
+    DynamicRenderingNotEnabled,
+    /// The render target's format isn't the one egui is drawn in, see [`Gui::format`](crate::Gui::format)
+    WrongRenderTargetFormat {
+        found: Option<Format>,
+        expected: Format,
+    },
+    /// Submitting the draw failed. On `FlushError::OutOfDate` (e.g. the window was resized after acquiring the
+    /// image), recreate your swapchain and draw the frame again from `begin_frame`.
+    Flush(FlushError),
+    /// Image bytes couldn't be decoded
+    ImageDecode(image::ImageError),
+    ImageCreation(ImmutableImageCreationError),
+    /// [`Gui::register_user_image_ycbcr`](crate::Gui::register_user_image_ycbcr) needs the device's
+    /// `sampler_ycbcr_conversion` feature
+    YcbcrConversionNotEnabled,
+    /// [`Gui::new_with_multiview`](crate::Gui::new_with_multiview) needs the device's `multiview` feature
+    MultiviewNotEnabled,
+    /// [`Gui::new_with_subpass`](crate::Gui::new_with_subpass) with a subpass of several color attachments needs the
+    /// device's `independent_blend` feature, to leave the other attachments unwritten
+    IndependentBlendNotEnabled,
+    /// The `view_count` of [`Gui::new_with_multiview`](crate::Gui::new_with_multiview) is 0 or exceeds the device's
+    /// `max_multiview_view_count`, which is the second value
+    InvalidViewCount(u32, u32),
+    /// [`Gui::set_bindless`](crate::Gui::set_bindless) needs a Vulkan 1.2 device with the
+    /// `runtime_descriptor_array`, `descriptor_binding_variable_descriptor_count` &
+    /// `shader_sampled_image_array_dynamic_indexing` features
+    DescriptorIndexingNotEnabled,
+    /// [`Gui::set_depth_stencil_state`](crate::Gui::set_depth_stencil_state) enabled a depth or stencil test, but
+    /// egui's subpass has no such attachment
+    NoDepthStencilAttachment,
+    /// A sampler couldn't be created, e.g. of a YUV conversion
+    SamplerCreation(SamplerCreationError),
+    /// Egui's render pass couldn't be created, e.g. as the device can't render to the format
+    RenderPassCreation(RenderPassCreationError),
+    /// The descriptor set of a texture couldn't be created
+    DescriptorSetCreation(DescriptorSetCreationError),
+    /// The view of a raw image couldn't be created, see [`Gui::register_raw_image`](crate::Gui::register_raw_image)
+    RawImageView(ash::vk::Result),
+    /// The descriptor set of a raw image couldn't be allocated
+    RawImageDescriptorSet(DescriptorPoolAllocError),
+    /// The view of a YUV image couldn't be created, e.g. as the conversion is for another format
+    ImageViewCreation(ImageViewCreationError),
+    /// The image of an [`ExternalImage`](crate::ExternalImage) couldn't be created, e.g. as the handle type isn't
+    /// supported for its format
+    ExternalImageCreation(ImageCreationError),
+    /// Importing the memory of an [`ExternalImage`](crate::ExternalImage) failed
+    ExternalMemoryImport(DeviceMemoryAllocationError),
+    /// None of the device's memory types can hold the [`ExternalImage`](crate::ExternalImage)
+    NoExternalMemoryType,
+    /// Acquiring an [`ExternalImage`](crate::ExternalImage) from its external owner failed
+    ExternalImageAcquire(String),
+    /// The device can't sample images of the format, e.g. BC or ASTC formats without the
+    /// `texture_compression_bc` or `texture_compression_astc_ldr` feature
+    UnsupportedTextureFormat(Format),
+    /// The texel data doesn't match the image's size. Block-compressed formats hold whole blocks, so e.g. a 30x30
+    /// BC1 image takes 8x8 blocks of 8 bytes.
+    WrongDataSize {
+        found: usize,
+        expected: usize,
+    },
+    /// [`XrGui::draw_into_eyes`](crate::XrGui::draw_into_eyes) was given a different number of images than eyes
+    WrongEyeImageCount {
+        found: usize,
+        expected: usize,
+    },
+    /// A KTX2 or DDS file couldn't be loaded, e.g. as its format isn't supported
+    InvalidTextureFile(&'static str),
+    /// [`Gui::read_pixels`](crate::Gui::read_pixels) was called before anything was drawn with `draw_on_image`
+    NothingDrawn,
+    /// The rect given to `Gui::copy_region_to_clipboard` covers no pixels of the image
+    EmptyRegion,
+    /// The image can't be read back as RGBA, only 8 bit per channel RGBA & BGRA formats can be
+    UnreadableFormat(Format),
+    /// The id given to [`Gui::register_user_image_view_with_id`](crate::Gui::register_user_image_view_with_id)
+    /// is already registered
+    TextureIdInUse(egui::TextureId),
+    /// User images can't be registered under an id of egui's own textures, i.e. a `TextureId::Managed`
+    NotAUserTextureId(egui::TextureId),
+    /// The id isn't of a user image registered with a vulkano image view
+    UnknownTexture(egui::TextureId),
+    /// The user image was registered from a raw Vulkan handle or as YUV image, which
+    /// [`Gui::snapshot_user_textures`](crate::Gui::snapshot_user_textures) can't re-register
+    UnsnapshottableTexture(egui::TextureId),
+    /// Copying between the image & a buffer failed, e.g. as the data doesn't cover the image or it lacks
+    /// `transfer_dst` usage for uploads or `transfer_src` usage for readbacks
+    ImageCopy(CopyError),
+    /// Executing the upload or readback failed, e.g. as the image is still used exclusively elsewhere
+    Execute(CommandBufferExecError),
+    /// The buffer an image is read back into couldn't be allocated
+    ReadbackBuffer(DeviceMemoryAllocationError),
+    /// Beginning a command buffer failed
+    CommandBufferBegin(CommandBufferBeginError),
+    /// Building a command buffer failed
+    CommandBufferBuild(BuildError),
+    /// A shader module couldn't be created, e.g. as its SPIR-V uses capabilities the device lacks
+    ShaderModule(ShaderCreationError),
+    /// A watched shader file couldn't be read or isn't a whole number of SPIR-V words, see
+    /// [`Gui::watch_custom_shaders`](crate::Gui::watch_custom_shaders)
+    ShaderFile(PathBuf, std::io::Error),
+    /// A custom shader has no `main` entry point
+    NoShaderEntryPoint,
+    /// Egui's pipeline couldn't be created, e.g. as custom shaders don't match its vertex input, push constants or
+    /// specialization constants. The message describes the pipeline's configuration.
+    PipelineCreation(String),
+    /// Reading from or writing to the system clipboard failed
+    #[cfg(feature = "clipboard_image")]
+    Clipboard(arboard::Error),
+}
+
+impl fmt::Display for GuiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuiError::SurfaceFormats(err) => write!(f, "Failed to query surface formats: {}", err),
+            GuiError::UnsupportedFormat(Some(format)) => {
+                write!(f, "Swapchain format does not support {:?}", format)
+            }
+            GuiError::UnsupportedFormat(None) => {
+                write!(f, "Swapchain supports no sRGB or UNORM format")
+            }
+            GuiError::NoColorAttachment => write!(f, "Subpass has no color attachment"),
+            GuiError::DynamicRenderingNotEnabled => {
+                write!(f, "Device doesn't have the dynamic_rendering feature enabled")
+            }
+            GuiError::WrongRenderTargetFormat { found, expected } => write!(
+                f,
+                "Render target image color format is wrong {:?}, should be {:?}",
+                found, expected
+            ),
+            GuiError::Flush(err) => write!(f, "Failed to signal fence and flush: {}", err),
+            GuiError::ImageDecode(err) => write!(f, "Failed to load image from bytes: {}", err),
+            GuiError::ImageCreation(err) => write!(f, "Failed to create image: {}", err),
+            GuiError::YcbcrConversionNotEnabled => {
+                write!(f, "Device doesn't have the sampler_ycbcr_conversion feature enabled")
+            }
+            GuiError::InvalidViewCount(view_count, max_view_count) => write!(
+                f,
+                "View count {} isn't within 1..={} of the device",
+                view_count, max_view_count
+            ),
+            GuiError::MultiviewNotEnabled => {
+                write!(f, "Device doesn't have the multiview feature enabled")
+            }
+            GuiError::IndependentBlendNotEnabled => {
+                write!(f, "Device doesn't have the independent_blend feature enabled")
+            }
+            GuiError::DescriptorIndexingNotEnabled => write!(
+                f,
+                "Device isn't Vulkan 1.2 or doesn't have the runtime_descriptor_array, \
+                 descriptor_binding_variable_descriptor_count & \
+                 shader_sampled_image_array_dynamic_indexing features enabled"
+            ),
+            GuiError::NoDepthStencilAttachment => {
+                write!(f, "Egui's subpass has no depth or stencil attachment to test against")
+            }
+            GuiError::SamplerCreation(err) => write!(f, "Failed to create sampler: {}", err),
+            GuiError::RenderPassCreation(err) => {
+                write!(f, "Failed to create render pass: {}", err)
+            }
+            GuiError::DescriptorSetCreation(err) => {
+                write!(f, "Failed to create descriptor set: {}", err)
+            }
+            GuiError::RawImageView(err) => write!(f, "Failed to create raw image view: {}", err),
+            GuiError::RawImageDescriptorSet(err) => {
+                write!(f, "Failed to allocate raw image descriptor set: {}", err)
+            }
+            GuiError::ImageViewCreation(err) => write!(f, "Failed to create image view: {}", err),
+            GuiError::ExternalImageCreation(err) => {
+                write!(f, "Failed to create external image: {}", err)
+            }
+            GuiError::ExternalMemoryImport(err) => {
+                write!(f, "Failed to import external memory: {}", err)
+            }
+            GuiError::NoExternalMemoryType => {
+                write!(f, "No memory type can hold the external image")
+            }
+            GuiError::ExternalImageAcquire(message) => {
+                write!(f, "Failed to acquire external image: {}", message)
+            }
+            GuiError::UnsupportedTextureFormat(format) => {
+                write!(f, "Device can't sample images of format {:?}", format)
+            }
+            GuiError::WrongDataSize { found, expected } => {
+                write!(f, "Texel data is {} bytes, should be {}", found, expected)
+            }
+            GuiError::InvalidTextureFile(reason) => {
+                write!(f, "Failed to load texture file: {}", reason)
+            }
+            GuiError::WrongEyeImageCount { found, expected } => {
+                write!(f, "Got {} images for {} eyes", found, expected)
+            }
+            GuiError::NothingDrawn => write!(f, "No image has been drawn on yet"),
+            GuiError::EmptyRegion => write!(f, "Region covers no pixels of the image"),
+            GuiError::UnreadableFormat(format) => {
+                write!(f, "Image format {:?} can't be read back as RGBA", format)
+            }
+            GuiError::TextureIdInUse(texture_id) => {
+                write!(f, "{:?} is already registered", texture_id)
+            }
+            GuiError::NotAUserTextureId(texture_id) => {
+                write!(f, "{:?} is not a user texture id", texture_id)
+            }
+            GuiError::UnknownTexture(texture_id) => {
+                write!(f, "{:?} is not a registered user image", texture_id)
+            }
+            GuiError::UnsnapshottableTexture(texture_id) => {
+                write!(f, "{:?} is a raw or YUV image, which can't be snapshotted", texture_id)
+            }
+            GuiError::ImageCopy(err) => write!(f, "Failed to copy image: {}", err),
+            GuiError::Execute(err) => write!(f, "Failed to execute command buffer: {}", err),
+            GuiError::ReadbackBuffer(err) => {
+                write!(f, "Failed to allocate readback buffer: {}", err)
+            }
+            GuiError::CommandBufferBegin(err) => {
+                write!(f, "Failed to begin command buffer: {}", err)
+            }
+            GuiError::CommandBufferBuild(err) => {
+                write!(f, "Failed to build command buffer: {}", err)
+            }
+            GuiError::ShaderModule(err) => write!(f, "Failed to create shader module: {}", err),
+            GuiError::ShaderFile(path, err) => {
+                write!(f, "Failed to read shader {}: {}", path.display(), err)
+            }
+            GuiError::NoShaderEntryPoint => write!(f, "Shader has no main entry point"),
+            GuiError::PipelineCreation(message) => write!(f, "{}", message),
+            #[cfg(feature = "clipboard_image")]
+            GuiError::Clipboard(err) => write!(f, "Failed to access clipboard: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for GuiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GuiError::SurfaceFormats(err) => Some(err),
+            GuiError::Flush(err) => Some(err),
+            GuiError::ImageDecode(err) => Some(err),
+            GuiError::ImageCreation(err) => Some(err),
+            GuiError::SamplerCreation(err) => Some(err),
+            GuiError::RenderPassCreation(err) => Some(err),
+            GuiError::DescriptorSetCreation(err) => Some(err),
+            GuiError::RawImageView(err) => Some(err),
+            GuiError::RawImageDescriptorSet(err) => Some(err),
+            GuiError::ImageViewCreation(err) => Some(err),
+            GuiError::ExternalImageCreation(err) => Some(err),
+            GuiError::ExternalMemoryImport(err) => Some(err),
+            GuiError::ImageCopy(err) => Some(err),
+            GuiError::Execute(err) => Some(err),
+            GuiError::ReadbackBuffer(err) => Some(err),
+            GuiError::CommandBufferBegin(err) => Some(err),
+            GuiError::CommandBufferBuild(err) => Some(err),
+            GuiError::ShaderModule(err) => Some(err),
+            GuiError::ShaderFile(_, err) => Some(err),
+            #[cfg(feature = "clipboard_image")]
+            GuiError::Clipboard(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<SurfacePropertiesError> for GuiError {
+    fn from(err: SurfacePropertiesError) -> Self {
+        GuiError::SurfaceFormats(err)
+    }
+}
+
+impl From<FlushError> for GuiError {
+    fn from(err: FlushError) -> Self {
+        GuiError::Flush(err)
+    }
+}
+
+impl From<image::ImageError> for GuiError {
+    fn from(err: image::ImageError) -> Self {
+        GuiError::ImageDecode(err)
+    }
+}
+
+impl From<ImmutableImageCreationError> for GuiError {
+    fn from(err: ImmutableImageCreationError) -> Self {
+        GuiError::ImageCreation(err)
+    }
+}
+
+impl From<CopyError> for GuiError {
+    fn from(err: CopyError) -> Self {
+        GuiError::ImageCopy(err)
+    }
+}
+
+impl From<CommandBufferExecError> for GuiError {
+    fn from(err: CommandBufferExecError) -> Self {
+        GuiError::Execute(err)
+    }
+}
+
+impl From<CommandBufferBeginError> for GuiError {
+    fn from(err: CommandBufferBeginError) -> Self {
+        GuiError::CommandBufferBegin(err)
+    }
+}
+
+impl From<BuildError> for GuiError {
+    fn from(err: BuildError) -> Self {
+        GuiError::CommandBufferBuild(err)
+    }
+}
+
+#[cfg(feature = "clipboard_image")]
+impl From<arboard::Error> for GuiError {
+    fn from(err: arboard::Error) -> Self {
+        GuiError::Clipboard(err)
+    }
+}
+
+impl From<ShaderCreationError> for GuiError {
+    fn from(err: ShaderCreationError) -> Self {
+        GuiError::ShaderModule(err)
+    }
+}
+
+impl From<SamplerCreationError> for GuiError {
+    fn from(err: SamplerCreationError) -> Self {
+        GuiError::SamplerCreation(err)
+    }
+}
+
+impl From<RenderPassCreationError> for GuiError {
+    fn from(err: RenderPassCreationError) -> Self {
+        GuiError::RenderPassCreation(err)
+    }
+}
+
+impl From<DescriptorSetCreationError> for GuiError {
+    fn from(err: DescriptorSetCreationError) -> Self {
+        GuiError::DescriptorSetCreation(err)
+    }
+}
+
+impl From<ImageViewCreationError> for GuiError {
+    fn from(err: ImageViewCreationError) -> Self {
+        GuiError::ImageViewCreation(err)
+    }
+}
+
+impl From<ImageCreationError> for GuiError {
+    fn from(err: ImageCreationError) -> Self {
+        GuiError::ExternalImageCreation(err)
+    }
+}
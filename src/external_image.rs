@@ -0,0 +1,260 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::{
+    fs::File,
+    hash::{Hash, Hasher},
+    iter,
+    sync::Arc,
+};
+
+use vulkano::{
+    command_buffer::{
+        pool::{UnsafeCommandPool, UnsafeCommandPoolCreateInfo},
+        submit::SubmitCommandBufferBuilder,
+        sys::{CommandBufferBeginInfo, UnsafeCommandBufferBuilder},
+        CommandBufferUsage,
+    },
+    device::{Device, DeviceOwned, Queue},
+    format::Format,
+    image::{
+        sys::{UnsafeImage, UnsafeImageCreateInfo},
+        ImageAccess, ImageDescriptorLayouts, ImageDimensions, ImageInner, ImageLayout, ImageTiling,
+        ImageUsage,
+    },
+    memory::{
+        DedicatedAllocation, DeviceMemory, ExternalMemoryHandleType, ExternalMemoryHandleTypes,
+        MemoryAllocateInfo, MemoryImportInfo,
+    },
+    sync::{
+        AccessFlags, DependencyInfo, Fence, FenceCreateInfo, ImageMemoryBarrier, PipelineStages,
+        QueueFamilyTransfer,
+    },
+};
+
+use crate::GuiError;
+
+/// 2D image whose memory was allocated outside of vulkano, e.g. a DMA-BUF of a hardware video decoder or an
+/// opaque fd exported by another process, see [`ExternalImage::import_fd`]. It's kept in the `General` layout, so
+/// that its owner can keep writing to it without layout transitions.
+pub struct ExternalImage {
+    image: Arc<UnsafeImage>,
+    _memory: DeviceMemory,
+}
+
+impl ExternalImage {
+    /// Imports the memory of `file` as an image of `dimensions` & `format`, to register as a user image with
+    /// [`Gui::register_user_image_view`](crate::Gui::register_user_image_view) through `ImageView::new_default`. Needs the device's
+    /// `khr_external_memory_fd` extension, & `ext_external_memory_dma_buf` for DMA-BUFs. Vulkano has no DRM
+    /// format modifiers, so DMA-BUFs must be laid out as the driver lays out `ImageTiling::Linear` images.
+    ///
+    /// The image's ownership is acquired from its external owner on `queue`, which must be of the queue family
+    /// egui is drawn with. DMA-BUFs are acquired from the foreign queue family when the device has the
+    /// `ext_queue_family_foreign` extension enabled.
+    ///
+    /// # Safety
+    ///
+    /// - `file` must be a valid fd of `handle_type` (`OpaqueFd` or `DmaBuf`) holding an image of `dimensions`,
+    ///   `format` & `tiling`. For opaque fds exported by Vulkan, the image must be defined identically to the
+    ///   original one.
+    /// - Vulkan takes ownership of `file`, so you must not use it afterwards.
+    /// - The external owner must have released the image in the `General` layout, or never used it in Vulkan for
+    ///   `ImageTiling::Linear` images, which are imported as preinitialized. Optimally tiled images released
+    ///   otherwise are acquired from `Undefined`, which drivers may treat as discarding their contents.
+    /// - Vulkano doesn't synchronize with writers outside of it. Finish writing to the image before drawing egui.
+    pub unsafe fn import_fd(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        file: File,
+        handle_type: ExternalMemoryHandleType,
+        dimensions: [u32; 2],
+        format: Format,
+        tiling: ImageTiling,
+    ) -> Result<Arc<ExternalImage>, GuiError> {
+        let image = UnsafeImage::new(device.clone(), UnsafeImageCreateInfo {
+            dimensions: ImageDimensions::Dim2d {
+                width: dimensions[0],
+                height: dimensions[1],
+                array_layers: 1,
+            },
+            format: Some(format),
+            tiling,
+            initial_layout: initial_layout(tiling),
+            usage: ImageUsage { sampled: true, transfer_src: true, ..ImageUsage::none() },
+            external_memory_handle_types: ExternalMemoryHandleTypes::from(
+                ash::vk::ExternalMemoryHandleTypeFlags::from(handle_type),
+            ),
+            ..Default::default()
+        })?;
+        let requirements = image.memory_requirements();
+        // DMA-BUFs may only be imported to some memory types
+        let fd_memory_type_bits = match handle_type {
+            // Queried with a duplicate, as the query takes ownership of the fd
+            ExternalMemoryHandleType::DmaBuf => file
+                .try_clone()
+                .ok()
+                .and_then(|file| device.memory_fd_properties(handle_type, file).ok())
+                .map_or(u32::MAX, |properties| properties.memory_type_bits),
+            _ => u32::MAX,
+        };
+        let memory_type_bits = requirements.memory_type_bits & fd_memory_type_bits;
+        let memory_types = || {
+            device
+                .physical_device()
+                .memory_types()
+                .filter(|memory_type| memory_type_bits & (1 << memory_type.id()) != 0)
+        };
+        let memory_type_index = memory_types()
+            .find(|memory_type| memory_type.is_device_local())
+            .or_else(|| memory_types().next())
+            .ok_or(GuiError::NoExternalMemoryType)?
+            .id();
+        let memory = DeviceMemory::import(
+            device.clone(),
+            MemoryAllocateInfo {
+                allocation_size: requirements.size,
+                memory_type_index,
+                dedicated_allocation: Some(DedicatedAllocation::Image(&image)),
+                ..Default::default()
+            },
+            MemoryImportInfo::Fd { handle_type, file },
+        )
+        .map_err(GuiError::ExternalMemoryImport)?;
+        image.bind_memory(&memory, 0).map_err(|err| GuiError::ExternalMemoryImport(err.into()))?;
+        let source_queue_family = match handle_type {
+            ExternalMemoryHandleType::DmaBuf
+                if device.enabled_extensions().ext_queue_family_foreign =>
+            {
+                ash::vk::QUEUE_FAMILY_FOREIGN_EXT
+            }
+            _ => ash::vk::QUEUE_FAMILY_EXTERNAL,
+        };
+        acquire_image(&queue, &image, source_queue_family, initial_layout(tiling))
+            .map_err(GuiError::ExternalImageAcquire)?;
+        Ok(Arc::new(ExternalImage { image, _memory: memory }))
+    }
+}
+
+/// Layout the image is created in. Only linearly tiled images keep their contents when preinitialized
+fn initial_layout(tiling: ImageTiling) -> ImageLayout {
+    match tiling {
+        ImageTiling::Linear => ImageLayout::Preinitialized,
+        _ => ImageLayout::Undefined,
+    }
+}
+
+/// Transfers the ownership of `image` from `source_queue_family` to the family of `queue` & transitions it to the
+/// `General` layout, waiting for it to finish, so vulkano can treat the image as its own afterwards
+unsafe fn acquire_image(
+    queue: &Arc<Queue>,
+    image: &Arc<UnsafeImage>,
+    source_queue_family: u32,
+    old_layout: ImageLayout,
+) -> Result<(), String> {
+    let device = queue.device().clone();
+    let pool = UnsafeCommandPool::new(device.clone(), UnsafeCommandPoolCreateInfo {
+        queue_family_index: queue.family().id(),
+        transient: true,
+        ..Default::default()
+    })
+    .map_err(|err| err.to_string())?;
+    let pool_alloc = pool
+        .allocate_command_buffers(Default::default())
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("no command buffer allocated")?;
+    let mut builder = UnsafeCommandBufferBuilder::new(&pool_alloc, CommandBufferBeginInfo {
+        usage: CommandBufferUsage::OneTimeSubmit,
+        ..Default::default()
+    })
+    .map_err(|err| err.to_string())?;
+    builder.pipeline_barrier(&DependencyInfo {
+        image_memory_barriers: iter::once(ImageMemoryBarrier {
+            destination_stages: PipelineStages { all_commands: true, ..PipelineStages::none() },
+            destination_access: AccessFlags {
+                shader_read: true,
+                transfer_read: true,
+                ..AccessFlags::none()
+            },
+            old_layout,
+            new_layout: ImageLayout::General,
+            queue_family_transfer: Some(QueueFamilyTransfer {
+                source_index: source_queue_family,
+                destination_index: queue.family().id(),
+            }),
+            subresource_range: image.subresource_range(),
+            ..ImageMemoryBarrier::image(image.clone())
+        })
+        .collect(),
+        ..Default::default()
+    });
+    let command_buffer = builder.build().map_err(|err| err.to_string())?;
+    let fence = Fence::new(device, FenceCreateInfo::default()).map_err(|err| err.to_string())?;
+    let mut submit = SubmitCommandBufferBuilder::new();
+    submit.add_command_buffer(&command_buffer);
+    submit.set_fence_signal(&fence);
+    submit.submit(queue).map_err(|err| err.to_string())?;
+    fence.wait(None).map_err(|err| err.to_string())
+}
+
+unsafe impl ImageAccess for ExternalImage {
+    fn inner(&self) -> ImageInner<'_> {
+        ImageInner {
+            image: &self.image,
+            first_layer: 0,
+            num_layers: 1,
+            first_mipmap_level: 0,
+            num_mipmap_levels: 1,
+        }
+    }
+
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        Some(ImageDescriptorLayouts {
+            storage_image: ImageLayout::General,
+            combined_image_sampler: ImageLayout::General,
+            sampled_image: ImageLayout::General,
+            input_attachment: ImageLayout::General,
+        })
+    }
+
+    // Acquired in the `General` layout on import
+    unsafe fn layout_initialized(&self) {}
+
+    fn is_layout_initialized(&self) -> bool {
+        true
+    }
+}
+
+unsafe impl DeviceOwned for ExternalImage {
+    fn device(&self) -> &Arc<Device> {
+        self.image.device()
+    }
+}
+
+impl PartialEq for ExternalImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl Eq for ExternalImage {}
+
+impl Hash for ExternalImage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+
+use egui::{epaint::ClippedShape, Rect, TexturesDelta};
+use vulkano::{
+    device::Queue,
+    format::Format,
+    image::{view::ImageView, AttachmentImage, ImageAccess, ImageUsage, ImageViewAbstract},
+    sync::GpuFuture,
+};
+
+use crate::{error::GuiError, renderer::Renderer};
+
+/// View of the texture the ui was drawn on & the future of the draw, see [`HeadlessGui::draw_to_texture`]
+pub type DrawnTexture = (Arc<ImageView<AttachmentImage>>, Box<dyn GpuFuture>);
+
+/// Egui rendered to offscreen images without a window, e.g. in CI or a server-side thumbnailer. There's no
+/// `egui_winit::State`, so input is given as `egui::RawInput` to [`HeadlessGui::run`].
+pub struct HeadlessGui {
+    egui_ctx: egui::Context,
+    renderer: Renderer,
+    dimensions: [u32; 2],
+    pixels_per_point: f32,
+    shapes: Vec<ClippedShape>,
+    textures_delta: TexturesDelta,
+    /// Image drawn on by `draw_to_texture`
+    texture: Option<Arc<ImageView<AttachmentImage>>>,
+}
+
+impl HeadlessGui {
+    /// Creates a gui drawing on images of `format` (e.g. a `StorageImage` with color attachment usage) sized
+    /// `dimensions` in pixels. Images are cleared before egui is drawn on them.
+    pub fn new(
+        gfx_queue: Arc<Queue>,
+        format: Format,
+        dimensions: [u32; 2],
+    ) -> Result<HeadlessGui, GuiError> {
+        Ok(HeadlessGui {
+            egui_ctx: Default::default(),
+            renderer: Renderer::new_with_render_pass(gfx_queue, format, false)?,
+            dimensions,
+            pixels_per_point: 1.0,
+            shapes: vec![],
+            textures_delta: Default::default(),
+            texture: None,
+        })
+    }
+
+    pub fn context(&self) -> egui::Context {
+        self.egui_ctx.clone()
+    }
+
+    /// Size of the images drawn on, in pixels
+    pub fn set_dimensions(&mut self, dimensions: [u32; 2]) {
+        self.dimensions = dimensions;
+    }
+
+    /// Scale the ui is laid out with, 1 by default. Overridden by `pixels_per_point` of the input given to `run`.
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: f32) {
+        self.pixels_per_point = pixels_per_point;
+    }
+
+    /// Runs a frame with `raw_input`, whose screen rect, scale & max texture side default to the gui's. Returns
+    /// what egui wants done outside of drawing (e.g. copied text), which is otherwise dropped.
+    pub fn run(
+        &mut self,
+        mut raw_input: egui::RawInput,
+        run_ui: impl FnOnce(&egui::Context),
+    ) -> egui::PlatformOutput {
+        let pixels_per_point = *raw_input.pixels_per_point.get_or_insert(self.pixels_per_point);
+        raw_input.screen_rect.get_or_insert_with(|| {
+            Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(self.dimensions[0] as f32, self.dimensions[1] as f32) / pixels_per_point,
+            )
+        });
+        raw_input.max_texture_side.get_or_insert(
+            self.renderer.queue().device().physical_device().properties().max_image_dimension2_d
+                as usize,
+        );
+        self.pixels_per_point = pixels_per_point;
+        let output = self.egui_ctx.run(raw_input, run_ui);
+        self.shapes = output.shapes;
+        self.textures_delta.append(output.textures_delta);
+        output.platform_output
+    }
+
+    /// Draws the last frame run on `final_image`, which must have the gui's format & dimensions
+    pub fn draw_on_image<F>(
+        &mut self,
+        before_future: F,
+        final_image: Arc<dyn ImageViewAbstract + 'static>,
+    ) -> Result<Box<dyn GpuFuture>, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        self.renderer.check_render_target_format(&final_image)?;
+        let clipped_meshes = self.egui_ctx.tessellate(std::mem::take(&mut self.shapes));
+        let textures_delta = std::mem::take(&mut self.textures_delta);
+        self.renderer.draw_on_image(
+            &clipped_meshes,
+            &textures_delta,
+            self.pixels_per_point,
+            before_future,
+            final_image,
+        )
+    }
+
+    /// Draws the last frame run on an image the gui owns, e.g. to map the ui onto a quad or curved mesh in your 3D
+    /// scene, see [`WorldQuad`](crate::WorldQuad). Returns its view, which can be sampled & read back, & the future
+    /// of the draw. The image is reused while the dimensions stay the same, so chain the future into the commands
+    /// sampling it, & draw the next frame after those.
+    pub fn draw_to_texture<F>(&mut self, before_future: F) -> Result<DrawnTexture, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        let texture = match &self.texture {
+            Some(texture) if texture.image().dimensions().width_height() == self.dimensions => {
+                texture.clone()
+            }
+            _ => {
+                let image = AttachmentImage::with_usage(
+                    self.renderer.queue().device().clone(),
+                    self.dimensions,
+                    self.format(),
+                    ImageUsage {
+                        sampled: true,
+                        color_attachment: true,
+                        transfer_src: true,
+                        ..ImageUsage::none()
+                    },
+                )
+                .map_err(|err| GuiError::ImageCreation(err.into()))?;
+                let texture = ImageView::new_default(image)?;
+                self.texture = Some(texture.clone());
+                texture
+            }
+        };
+        let after_future = self.draw_on_image(before_future, texture.clone())?;
+        Ok((texture, after_future))
+    }
+
+    /// Position in egui's points of texture coordinates on the drawn ui, e.g. from [`WorldQuad::ray_uv`]. Give it
+    /// to [`HeadlessGui::run`] as an `egui::Event::PointerMoved`, followed by `PointerButton` events for clicks.
+    ///
+    /// [`WorldQuad::ray_uv`]: crate::WorldQuad::ray_uv
+    pub fn pos_from_uv(&self, uv: [f32; 2]) -> egui::Pos2 {
+        egui::pos2(
+            uv[0] * self.dimensions[0] as f32 / self.pixels_per_point,
+            uv[1] * self.dimensions[1] as f32 / self.pixels_per_point,
+        )
+    }
+
+    /// Reads back `image` (e.g. the one last drawn on) as RGBA after `before_future` (e.g. the future of the draw),
+    /// waiting for the copy to finish. The image must have `transfer_src` usage & an 8 bit per channel RGBA or BGRA
+    /// format.
+    pub fn read_pixels<F>(
+        &self,
+        before_future: F,
+        image: Arc<dyn ImageViewAbstract + 'static>,
+    ) -> Result<image::RgbaImage, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        let image = image.image().clone();
+        let [width, height] = image.dimensions().width_height();
+        let bytes =
+            self.renderer.read_rgba_region(before_future, image, [0, 0], [width, height])?;
+        Ok(image::RgbaImage::from_raw(width, height, bytes).unwrap())
+    }
+
+    pub fn register_user_image_view(
+        &mut self,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.renderer.register_image(image)
+    }
+
+    pub fn unregister_user_image(&mut self, texture_id: egui::TextureId) {
+        self.renderer.unregister_image(texture_id);
+    }
+
+    pub fn format(&self) -> Format {
+        self.renderer.format()
+    }
+
+    #[cfg(feature = "xr")]
+    pub(crate) fn queue(&self) -> Arc<Queue> {
+        self.renderer.queue()
+    }
+
+    /// Image last drawn on by `draw_to_texture`
+    #[cfg(feature = "xr")]
+    pub(crate) fn texture(&self) -> Option<Arc<ImageView<AttachmentImage>>> {
+        self.texture.clone()
+    }
+}
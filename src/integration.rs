@@ -6,20 +6,237 @@
 // at your option. All files in the project carrying such
 // notice may not be copied, modified, or distributed except
 // according to those terms.
-use std::sync::Arc;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use egui::{ClippedPrimitive, TexturesDelta};
+use ahash::AHashMap;
+use egui::{ClippedPrimitive, LayerId, PaintCallback, PaintCallbackInfo, TexturesDelta};
 use vulkano::{
-    command_buffer::SecondaryAutoCommandBuffer, device::Queue, image::ImageViewAbstract,
-    render_pass::Subpass, swapchain::Surface, sync::GpuFuture,
+    command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer},
+    device::Queue,
+    format::{Format, NumericType},
+    image::{ImageAccess, ImageViewAbstract, SampleCount},
+    pipeline::{
+        cache::PipelineCache,
+        graphics::{
+            color_blend::ColorComponents, depth_stencil::DepthStencilState,
+            render_pass::PipelineRenderPassType,
+        },
+    },
+    render_pass::Subpass,
+    sampler::ycbcr::SamplerYcbcrConversion,
+    swapchain::Surface,
+    sync::GpuFuture,
+};
+use winit::{
+    event_loop::{ControlFlow, EventLoopProxy},
+    window::{Window, WindowId},
 };
-use winit::window::Window;
 
+#[cfg(feature = "remote")]
+use crate::remote::RemoteMirror;
+#[cfg(feature = "shader_hot_reload")]
+use crate::shader_reload::ShaderWatcher;
 use crate::{
-    renderer::Renderer,
-    utils::{immutable_texture_from_bytes, immutable_texture_from_file},
+    callback::{paint_callback, CallbackRenderer},
+    clipboard::{replace_pastes, ClipboardProvider},
+    damage::{primitives_hash, DamageRegions},
+    error::GuiError,
+    layers::LayerMarkers,
+    occlusion::UiOcclusion,
+    raw_image::RawImageRelease,
+    renderer::{
+        AllocationStats, BlendMode, ColorVisionDeficiency, CustomShaders, DescriptorAllocator,
+        OutputTransfer, Renderer, SamplerOptions, SubpassInheritance,
+    },
+    repaint::take_repaint_deadline,
+    secondary::SecondaryContext,
+    shadows::{add_panel_shadows, PanelShadowRenderer, PanelShadows, PANEL_SHADOW_KEY},
+    shaping::{GlyphAtlas, TextDirection, TextShaper},
+    text_effect::{apply_text_effect, TextEffect},
+    utils::{
+        check_texture_data, immutable_texture_from_bytes, immutable_texture_from_bytes_async,
+        immutable_texture_from_bytes_with_mipmaps, immutable_texture_from_file,
+        immutable_texture_from_file_with_mipmaps,
+    },
+    window_command::apply_window_commands,
 };
 
+/// An image pasted from the system clipboard, already registered as a user texture.
+/// Unregister it with [`Gui::unregister_user_image`] once it's no longer needed.
+#[cfg(feature = "clipboard_image")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PastedImage {
+    pub texture_id: egui::TextureId,
+    pub size: [u32; 2],
+}
+
+/// Bounding rectangles (in points) of what egui drew in a frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct UiBounds {
+    /// Union of everything drawn, `Rect::NOTHING` if nothing was drawn
+    pub union: egui::Rect,
+    /// Bounds of what was drawn grouped by clip rect, which roughly corresponds to egui's areas, windows & panels
+    pub areas: Vec<egui::Rect>,
+}
+
+impl Default for UiBounds {
+    fn default() -> Self {
+        UiBounds { union: egui::Rect::NOTHING, areas: vec![] }
+    }
+}
+
+impl UiBounds {
+    fn from_primitives(clipped_primitives: &[ClippedPrimitive]) -> UiBounds {
+        let mut areas: Vec<(egui::Rect, egui::Rect)> = vec![];
+        for ClippedPrimitive { clip_rect, primitive } in clipped_primitives {
+            let bounds = match primitive {
+                egui::epaint::Primitive::Mesh(mesh) if !mesh.is_empty() => mesh.calc_bounds(),
+                egui::epaint::Primitive::Mesh(_) => continue,
+                egui::epaint::Primitive::Callback(callback) => callback.rect,
+            }
+            .intersect(*clip_rect);
+            if !bounds.is_positive() {
+                continue;
+            }
+            match areas.iter_mut().find(|(area_clip_rect, _)| area_clip_rect == clip_rect) {
+                Some((_, area)) => *area = area.union(bounds),
+                None => areas.push((*clip_rect, bounds)),
+            }
+        }
+        let union = areas.iter().fold(egui::Rect::NOTHING, |union, (_, area)| union.union(*area));
+        UiBounds { union, areas: areas.into_iter().map(|(_, area)| area).collect() }
+    }
+}
+
+/// Size of the screen egui's draw data is meant for
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "remote", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScreenDescriptor {
+    pub size_in_pixels: [u32; 2],
+    pub pixels_per_point: f32,
+}
+
+/// Everything needed to draw a finished egui frame with your own renderer, see [`Gui::get_draw_data`]
+#[derive(Debug, Clone)]
+pub struct DrawData {
+    pub clipped_primitives: Vec<ClippedPrimitive>,
+    pub textures_delta: TexturesDelta,
+    pub screen_descriptor: ScreenDescriptor,
+}
+
+/// How often the ui should be drawn, see [`Gui::set_render_mode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Draw every frame, game-style
+    Continuous,
+    /// Draw only when input arrives or egui requests a repaint (e.g. while animating), tool-style
+    Reactive,
+}
+
+impl Default for RenderMode {
+    fn default() -> Self {
+        RenderMode::Continuous
+    }
+}
+
+/// How edges of shapes are anti-aliased, see [`Gui::set_anti_aliasing`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntiAliasing {
+    /// Egui's feathering: alpha faded out over a pixel wide band of extra vertices
+    Feathering,
+    /// Edge coverage computed in the fragment shader from egui's feathering, keeping edges a pixel wide & smooth
+    /// also when the ui is scaled, e.g. with [`Gui::set_projection`]. No MSAA attachments needed.
+    Analytic,
+}
+
+impl Default for AntiAliasing {
+    fn default() -> Self {
+        AntiAliasing::Feathering
+    }
+}
+
+/// Kind of a window event given to [`Gui::update`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    /// Cursor, mouse buttons, scrolling & touches
+    Pointer,
+    /// Keys, text & modifiers
+    Keyboard,
+    /// Everything else, e.g. resizes, focus changes & dropped files
+    Window,
+}
+
+impl InputKind {
+    fn of(event: &winit::event::WindowEvent<'_>) -> InputKind {
+        use winit::event::WindowEvent;
+        match event {
+            WindowEvent::CursorMoved { .. }
+            | WindowEvent::CursorEntered { .. }
+            | WindowEvent::CursorLeft { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::Touch(_)
+            | WindowEvent::TouchpadPressure { .. } => InputKind::Pointer,
+            WindowEvent::KeyboardInput { .. }
+            | WindowEvent::ReceivedCharacter(_)
+            | WindowEvent::ModifiersChanged(_) => InputKind::Keyboard,
+            _ => InputKind::Window,
+        }
+    }
+}
+
+/// What [`Gui::update`] did with a window event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventResponse {
+    /// Egui wants exclusive use of the event (e.g. a click on an egui window or text typed into a text field), so
+    /// don't pass it on to e.g. your game
+    pub consumed: bool,
+    /// The event may change the ui, so a frame should be drawn
+    pub repaint: bool,
+    pub kind: InputKind,
+}
+
+/// Glyph of text laid out by egui, see [`Gui::font_glyphs`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontGlyph {
+    pub chr: char,
+    /// Quad of the glyph in points, relative to the top left corner of the text
+    pub rect: egui::Rect,
+    /// Normalized uv rect of the glyph in [`Gui::font_image`]
+    pub uv: egui::Rect,
+}
+
+/// User texture in a [`UserTextureSnapshot`]
+#[derive(Clone)]
+pub struct UserTextureEntry {
+    pub texture_id: egui::TextureId,
+    pub size: [u32; 2],
+    pub image: Arc<dyn ImageViewAbstract + Send + Sync + 'static>,
+    pub sampler_options: SamplerOptions,
+    /// Region of the image registered with [`Gui::register_user_image_view_region`]
+    pub uv_rect: Option<egui::Rect>,
+    /// Set with [`Gui::set_user_image_blend_mode`]
+    pub blend_mode: Option<BlendMode>,
+}
+
+/// Registered user textures, see [`Gui::snapshot_user_textures`]
+#[derive(Clone, Default)]
+pub struct UserTextureSnapshot {
+    pub entries: Vec<UserTextureEntry>,
+}
+
+/// Side of the texture glyphs of a [`TextShaper`] are cached in
+const GLYPH_ATLAS_SIDE: usize = 1024;
+
+/// Callback speaking what changed in a frame, see [`Gui::set_announcer`]
+type Announcer = Box<dyn FnMut(&str) + Send>;
+
+/// Callback opening clicked links, see [`Gui::set_url_handler`]
+type UrlHandler = Box<dyn FnMut(&egui::output::OpenUrl) -> bool + Send>;
+
 pub struct Gui {
     pub egui_ctx: egui::Context,
     pub egui_winit: egui_winit::State,
@@ -28,6 +245,57 @@ pub struct Gui {
 
     shapes: Vec<egui::epaint::ClippedShape>,
     textures_delta: egui::TexturesDelta,
+    layer_markers: LayerMarkers,
+    layer_callbacks: AHashMap<LayerId, PaintCallback>,
+    ui_bounds: UiBounds,
+    occlusion: Option<UiOcclusion>,
+    damage: Option<DamageRegions>,
+    render_mode: RenderMode,
+    repaint_requested: bool,
+    last_activity: Instant,
+    skip_idle_frames: bool,
+    pixels_per_point_override: Option<f32>,
+    zoom_factor: f32,
+    /// Feathering & text rounding options of egui before enabling pixel snapping
+    snapped_tessellation: Option<(bool, bool)>,
+    /// Scale egui-winit maps input with: the window's at first & after scale changes, then egui's of the last frame
+    /// (which includes the render scale) as egui-winit follows it
+    input_pixels_per_point: f32,
+    frame_needed: bool,
+    last_drawn_image: Option<Arc<dyn ImageViewAbstract + 'static>>,
+    /// Hash of the primitives, scale & user textures version drawn on `last_drawn_image`, see [`primitives_hash`]
+    last_drawn_hash: Option<u64>,
+    frame_interval: Option<Duration>,
+    /// Earliest repaint requested with `request_repaint_after` last frame
+    repaint_deadline: Option<Instant>,
+    pending_fonts: Option<std::sync::mpsc::Receiver<egui::FontDefinitions>>,
+    /// Characters to rasterize per text style at the start of the next frame
+    pending_prewarm: Vec<(egui::TextStyle, String)>,
+    glyph_atlas: Option<GlyphAtlas>,
+    text_direction: TextDirection,
+    last_frame_start: Instant,
+    text_effect: Option<TextEffect>,
+    /// Shadow settings & markers of the window layers shadowed this frame
+    panel_shadows: Option<(PanelShadows, LayerMarkers)>,
+    announcer: Option<Announcer>,
+    url_handler: Option<UrlHandler>,
+    /// Link clicked in a secondary context, opened with the main frame's output
+    secondary_open_url: Option<egui::output::OpenUrl>,
+    clipboard: Option<Box<dyn ClipboardProvider>>,
+    /// Output of secondary contexts run this frame, drawn on top of the main ui
+    secondary_frames: Vec<(Vec<ClippedPrimitive>, TexturesDelta)>,
+    #[cfg(feature = "remote")]
+    remote_mirror: Option<RemoteMirror>,
+
+    #[cfg(feature = "clipboard_image")]
+    pasted_images: Vec<Result<PastedImage, GuiError>>,
+    #[cfg(feature = "clipboard_image")]
+    last_frame_image: Option<Arc<dyn ImageViewAbstract + 'static>>,
+    #[cfg(feature = "shader_hot_reload")]
+    shader_watcher: Option<ShaderWatcher>,
+    #[cfg(feature = "shader_hot_reload")]
+    shader_reload_result: Option<Result<(), GuiError>>,
+    follow_system_theme: bool,
 }
 
 impl Gui {
@@ -39,79 +307,334 @@ impl Gui {
     /// - `gfx_queue`: Vulkano's [`Queue`]
     /// - `is_overlay`: If true, you should be responsible for clearing the image before `draw_on_image`, else it gets cleared
     ///
-    /// Note that your swapchain images should be created with `vulkano::format::Format::B8G8R8A8_SRGB`
-    pub fn new(surface: Arc<Surface<Window>>, gfx_queue: Arc<Queue>, is_overlay: bool) -> Gui {
-        let format = vulkano::format::Format::B8G8R8A8_SRGB;
-        let formats = gfx_queue
-            .device()
-            .physical_device()
-            .surface_formats(&surface, Default::default())
-            .unwrap();
-        assert!(
-            formats.iter().find(|f| f.0 == format).is_some(),
-            "Swapchain format does not support {:?}",
-            format
-        );
-        let max_texture_side =
-            gfx_queue.device().physical_device().properties().max_image_array_layers as usize;
-        let renderer = Renderer::new_with_render_pass(gfx_queue, format, is_overlay);
-        Gui {
-            egui_ctx: Default::default(),
-            egui_winit: egui_winit::State::new(max_texture_side, surface.window()),
-            renderer,
-            surface,
-            shapes: vec![],
-            textures_delta: Default::default(),
+    /// Egui is drawn in the format of [`Gui::preferred_format`], create your swapchain images with it. Use
+    /// [`Gui::new_with_format`] to pick the format yourself.
+    ///
+    /// On macOS (MoltenVK), create your instance with `enumerate_portability: true` so the device is found.
+    /// The renderer only uses state available on portability subset devices, see [`Gui::is_portability_subset`].
+    ///
+    /// Egui's pipeline is compiled on a background thread, the first draw or image registration waits for it.
+    pub fn new(
+        surface: Arc<Surface<Window>>,
+        gfx_queue: Arc<Queue>,
+        is_overlay: bool,
+    ) -> Result<Gui, GuiError> {
+        let format = Self::preferred_format(&surface, &gfx_queue)?;
+        Self::new_with_format(surface, gfx_queue, format, is_overlay)
+    }
+
+    /// Swapchain format egui is drawn in by [`Gui::new`]: `B8G8R8A8_SRGB` if the surface supports it, otherwise
+    /// another 8 bit sRGB format it supports. Surfaces of some drivers (e.g. on Android & older Intel GPUs) offer
+    /// no sRGB format, a UNORM format is then picked, which egui's shaders encode to sRGB for, see
+    /// [`Gui::set_shader_srgb_encoding`].
+    pub fn preferred_format(
+        surface: &Arc<Surface<Window>>,
+        gfx_queue: &Arc<Queue>,
+    ) -> Result<Format, GuiError> {
+        let formats =
+            gfx_queue.device().physical_device().surface_formats(surface, Default::default())?;
+        let supported = |format: Format| formats.iter().any(|f| f.0 == format);
+        [Format::B8G8R8A8_SRGB, Format::R8G8B8A8_SRGB, Format::A8B8G8R8_SRGB_PACK32]
+            .iter()
+            .copied()
+            .find(|&format| supported(format))
+            .or_else(|| {
+                formats
+                    .iter()
+                    .map(|f| f.0)
+                    .find(|format| format.type_color() == Some(NumericType::SRGB))
+            })
+            .or_else(|| {
+                [Format::B8G8R8A8_UNORM, Format::R8G8B8A8_UNORM, Format::A8B8G8R8_UNORM_PACK32]
+                    .iter()
+                    .copied()
+                    .find(|&format| supported(format))
+            })
+            .or_else(|| {
+                formats
+                    .iter()
+                    .map(|f| f.0)
+                    .find(|format| format.type_color() == Some(NumericType::UNORM))
+            })
+            .ok_or(GuiError::UnsupportedFormat(None))
+    }
+
+    /// Same as `new` but egui is drawn in `format`, which must be supported by the surface. With a UNORM format,
+    /// egui's shaders encode their output to sRGB, see [`Gui::set_shader_srgb_encoding`].
+    pub fn new_with_format(
+        surface: Arc<Surface<Window>>,
+        gfx_queue: Arc<Queue>,
+        format: Format,
+        is_overlay: bool,
+    ) -> Result<Gui, GuiError> {
+        let formats =
+            gfx_queue.device().physical_device().surface_formats(&surface, Default::default())?;
+        if !formats.iter().any(|f| f.0 == format) {
+            return Err(GuiError::UnsupportedFormat(Some(format)));
         }
+        let renderer = Renderer::new_with_render_pass(gfx_queue, format, is_overlay)?;
+        Ok(Self::from_renderer(surface, renderer))
     }
 
     /// Same as `new` but instead of integration owning a render pass, egui renders on your subpass
     ///
-    /// Note that your swapchain images should be created with `vulkano::format::Format::B8G8R8A8_SRGB`
+    /// Egui is drawn in the format of the subpass' first color attachment, which should be sRGB or UNORM.
+    /// The subpass may be any of the render pass, e.g. the lighting or composition subpass of a deferred renderer.
+    /// Other color attachments of it aren't written, which needs the device's `independent_blend` feature, else
+    /// [`GuiError::IndependentBlendNotEnabled`] is returned.
+    /// The subpass may be multisampled (e.g. a 4x MSAA scene pass), egui's pipeline then takes its sample count
+    /// from the subpass' attachments, see [`Gui::sample_count`].
     pub fn new_with_subpass(
         surface: Arc<Surface<Window>>,
         gfx_queue: Arc<Queue>,
         subpass: Subpass,
-    ) -> Gui {
-        let format = vulkano::format::Format::B8G8R8A8_SRGB;
-        let formats = gfx_queue
+    ) -> Result<Gui, GuiError> {
+        let format = subpass
+            .subpass_desc()
+            .color_attachments
+            .first()
+            .and_then(|reference| reference.as_ref())
+            .and_then(|reference| {
+                subpass.render_pass().attachments()[reference.attachment as usize].format
+            })
+            .ok_or(GuiError::NoColorAttachment)?;
+        let renderer = Renderer::new_with_subpass(gfx_queue, format, subpass)?;
+        Ok(Self::from_renderer(surface, renderer))
+    }
+
+    /// Same as `new` but egui is drawn with dynamic rendering (`VK_KHR_dynamic_rendering`) instead of a render pass,
+    /// for engines that have no render pass objects. Egui begins rendering on the image itself in `draw_on_image`,
+    /// or its commands can be executed within your own `begin_rendering` with `draw_on_subpass_image`. The
+    /// device must have the `dynamic_rendering` feature enabled.
+    /// - `format`: format of the images egui is drawn on, which should be an sRGB or UNORM format
+    /// - `sample_count`: sample count of the images, must be 1 for using `draw_on_image` on swapchain images
+    /// - `is_overlay`: If true, you should be responsible for clearing the image before `draw_on_image`, else it gets cleared
+    pub fn new_with_dynamic_rendering(
+        surface: Arc<Surface<Window>>,
+        gfx_queue: Arc<Queue>,
+        format: Format,
+        sample_count: SampleCount,
+        is_overlay: bool,
+    ) -> Result<Gui, GuiError> {
+        if !gfx_queue.device().enabled_features().dynamic_rendering {
+            return Err(GuiError::DynamicRenderingNotEnabled);
+        }
+        let renderer =
+            Renderer::new_with_dynamic_rendering(gfx_queue, format, sample_count, is_overlay)?;
+        Ok(Self::from_renderer(surface, renderer))
+    }
+
+    /// Same as `new_with_format` but egui is drawn into each of the first `view_count` array layers of the image
+    /// with multiview (`VK_KHR_multiview`), e.g. into both eyes of a VR headset's layered image with one draw,
+    /// instead of recording everything twice. Draw with `draw_on_image` on a 2D array view of the image's layers
+    /// (`ImageViewType::Dim2dArray`). The device must have the `multiview` feature enabled, & `view_count` must be
+    /// within 1..=`max_multiview_view_count` of it.
+    pub fn new_with_multiview(
+        surface: Arc<Surface<Window>>,
+        gfx_queue: Arc<Queue>,
+        format: Format,
+        view_count: u32,
+        is_overlay: bool,
+    ) -> Result<Gui, GuiError> {
+        if !gfx_queue.device().enabled_features().multiview {
+            return Err(GuiError::MultiviewNotEnabled);
+        }
+        let max_view_count = gfx_queue
             .device()
             .physical_device()
-            .surface_formats(&surface, Default::default())
-            .unwrap();
-        assert!(
-            formats.iter().find(|f| f.0 == format).is_some(),
-            "Swapchain format does not support {:?}",
-            format
-        );
+            .properties()
+            .max_multiview_view_count
+            .unwrap_or(0)
+            .min(32);
+        if view_count == 0 || view_count > max_view_count {
+            return Err(GuiError::InvalidViewCount(view_count, max_view_count));
+        }
+        let renderer = Renderer::new_with_multiview(gfx_queue, format, view_count, is_overlay)?;
+        Ok(Self::from_renderer(surface, renderer))
+    }
+
+    fn from_renderer(surface: Arc<Surface<Window>>, renderer: Renderer) -> Gui {
         let max_texture_side =
-            gfx_queue.device().physical_device().properties().max_image_array_layers as usize;
-        let renderer = Renderer::new_with_subpass(gfx_queue, format, subpass);
-        Gui {
+            renderer.queue().device().physical_device().properties().max_image_dimension2_d
+                as usize;
+        let native_pixels_per_point = egui_winit::native_pixels_per_point(surface.window());
+        let gui = Gui {
             egui_ctx: Default::default(),
             egui_winit: egui_winit::State::new(max_texture_side, surface.window()),
             renderer,
             surface,
             shapes: vec![],
             textures_delta: Default::default(),
+            layer_markers: Default::default(),
+            layer_callbacks: AHashMap::default(),
+            ui_bounds: Default::default(),
+            occlusion: None,
+            damage: None,
+            render_mode: RenderMode::default(),
+            repaint_requested: true,
+            last_activity: Instant::now(),
+            skip_idle_frames: false,
+            input_pixels_per_point: native_pixels_per_point,
+            pixels_per_point_override: None,
+            zoom_factor: 1.0,
+            snapped_tessellation: None,
+            frame_needed: true,
+            last_drawn_image: None,
+            last_drawn_hash: None,
+            frame_interval: None,
+            repaint_deadline: None,
+            pending_fonts: None,
+            pending_prewarm: vec![],
+            glyph_atlas: None,
+            text_direction: TextDirection::default(),
+            last_frame_start: Instant::now(),
+            text_effect: None,
+            panel_shadows: None,
+            announcer: None,
+            url_handler: None,
+            secondary_open_url: None,
+            clipboard: None,
+            secondary_frames: vec![],
+            #[cfg(feature = "remote")]
+            remote_mirror: None,
+            #[cfg(feature = "clipboard_image")]
+            pasted_images: vec![],
+            #[cfg(feature = "clipboard_image")]
+            last_frame_image: None,
+            #[cfg(feature = "shader_hot_reload")]
+            shader_watcher: None,
+            #[cfg(feature = "shader_hot_reload")]
+            shader_reload_result: None,
+            follow_system_theme: true,
+        };
+        // Winit only reports the initial theme on Windows, elsewhere egui's dark default stays until it changes
+        #[cfg(target_os = "windows")]
+        {
+            use winit::platform::windows::WindowExtWindows;
+            gui.apply_theme(gui.surface.window().theme());
         }
+        gui
+    }
+
+    /// Applies egui's visuals of the system theme
+    fn apply_theme(&self, theme: winit::window::Theme) {
+        self.egui_ctx.set_visuals(match theme {
+            winit::window::Theme::Dark => egui::Visuals::dark(),
+            winit::window::Theme::Light => egui::Visuals::light(),
+        });
+    }
+
+    /// Creates a gui for another window on the same queue, e.g. an inspector window of a tools app. User images
+    /// are shared between the guis, so an image registered with either can be shown in both windows with the same
+    /// `TextureId`. Each gui has its own egui context, input & fonts. Pass each window's events to its gui by
+    /// comparing the event's window id with [`Gui::window_id`].
+    pub fn new_for_window(
+        &self,
+        surface: Arc<Surface<Window>>,
+        is_overlay: bool,
+    ) -> Result<Gui, GuiError> {
+        let mut gui = Self::new(surface, self.renderer.queue(), is_overlay)?;
+        gui.renderer.share_user_textures(&self.renderer);
+        Ok(gui)
+    }
+
+    /// Id of the window egui is drawn in
+    pub fn window_id(&self) -> WindowId {
+        self.surface.window().id()
     }
 
     /// Updates context state by winit window event.
-    /// Returns `true` if egui wants exclusive use of this event
+    /// `consumed` of the response is `true` if egui wants exclusive use of this event
     /// (e.g. a mouse click on an egui window, or entering text into a text field).
     /// For instance, if you use egui for a game, you want to first call this
-    /// and only when this returns `false` pass on the events to your game.
+    /// and only when it's `false` pass on the events to your game.
+    /// With [`RenderMode::Reactive`], draw a frame when `repaint` is `true`.
+    ///
+    /// Note that egui uses `tab` to move focus between elements, so tabs are always consumed.
     ///
-    /// Note that egui uses `tab` to move focus between elements, so this will always return `true` for tabs.
-    pub fn update(&mut self, winit_event: &winit::event::WindowEvent<'_>) -> bool {
-        self.egui_winit.on_event(&self.egui_ctx, winit_event)
+    /// File drag-and-drop events (`HoveredFile`, `HoveredFileCancelled` and `DroppedFile`) are forwarded
+    /// to egui's `RawInput::hovered_files` & `RawInput::dropped_files` and never consumed.
+    ///
+    /// `ThemeChanged` applies egui's visuals of the new theme, see [`Gui::set_follow_system_theme`].
+    ///
+    /// Text committed by an input method arrives as `ReceivedCharacter` & is typed as usual. The IME's candidate
+    /// window is placed at the text cursor of the focused text edit each frame. Winit 0.26 doesn't report the
+    /// preedit text, so it's shown by the IME itself rather than inline in the text edit.
+    ///
+    /// With `clipboard_image` feature, pasting an image uploads it as a user texture, see [`Gui::take_pasted_images`].
+    pub fn update(&mut self, winit_event: &winit::event::WindowEvent<'_>) -> EventResponse {
+        use winit::event::WindowEvent;
+        // Any input egui reads may change the ui
+        let repaint = !matches!(
+            winit_event,
+            WindowEvent::Moved(_)
+                | WindowEvent::Destroyed
+                | WindowEvent::AxisMotion { .. }
+                | WindowEvent::TouchpadPressure { .. }
+                | WindowEvent::ThemeChanged(_)
+        ) || matches!(winit_event, WindowEvent::ThemeChanged(_))
+            && self.follow_system_theme;
+        if repaint {
+            self.repaint_requested = true;
+            self.last_activity = Instant::now();
+        }
+        if let (WindowEvent::ThemeChanged(theme), true) = (winit_event, self.follow_system_theme) {
+            self.apply_theme(*theme);
+        }
+        if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = winit_event {
+            self.input_pixels_per_point = *scale_factor as f32;
+        }
+        #[cfg(feature = "clipboard_image")]
+        if let winit::event::WindowEvent::KeyboardInput { input, .. } = winit_event {
+            if input.state == winit::event::ElementState::Pressed
+                && input.virtual_keycode == Some(winit::event::VirtualKeyCode::V)
+                && self.egui_winit.egui_input().modifiers.command
+            {
+                self.paste_clipboard_image();
+            }
+        }
+        let consumed = self.egui_winit.on_event(&self.egui_ctx, winit_event);
+        EventResponse { consumed, repaint, kind: InputKind::of(winit_event) }
+    }
+
+    /// Uploads clipboard image (if there is one) as a user texture
+    #[cfg(feature = "clipboard_image")]
+    fn paste_clipboard_image(&mut self) {
+        let image = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_image())
+        {
+            Ok(image) => image,
+            // No image on clipboard, egui handles text pasting
+            Err(arboard::Error::ContentNotAvailable) => return,
+            Err(err) => {
+                self.pasted_images.push(Err(err.into()));
+                return;
+            }
+        };
+        let size = [image.width as u32, image.height as u32];
+        let pasted = self
+            .register_user_image_from_bytes(
+                &image.bytes,
+                size,
+                vulkano::format::Format::R8G8B8A8_SRGB,
+            )
+            .map(|texture_id| PastedImage { texture_id, size });
+        self.pasted_images.push(pasted);
+        // Shown by the next frame's layout, even if egui ignores the key press
+        self.repaint_requested = true;
+        self.egui_ctx.request_repaint();
+    }
+
+    /// Returns images pasted since the last call as registered user textures, or why pasting them failed, e.g. as
+    /// the clipboard couldn't be read or the image couldn't be uploaded. Egui has no event for pasted images, so
+    /// call this e.g. within your layout to react to pastes. A repaint is requested for each paste.
+    #[cfg(feature = "clipboard_image")]
+    pub fn take_pasted_images(&mut self) -> Vec<Result<PastedImage, GuiError>> {
+        std::mem::take(&mut self.pasted_images)
     }
 
     /// Begins Egui frame & determines what will be drawn later. This must be called before draw, and after `update` (winit event).
     pub fn immediate_ui(&mut self, layout_function: impl FnOnce(&mut Self)) {
-        let raw_input = self.egui_winit.take_egui_input(self.surface.window());
-        self.egui_ctx.begin_frame(raw_input);
+        self.begin_frame();
         // Render Egui
         layout_function(self);
     }
@@ -119,47 +642,346 @@ impl Gui {
     /// If you wish to better control when to begin frame, do so by calling this function
     /// (Finish by drawing)
     pub fn begin_frame(&mut self) {
-        let raw_input = self.egui_winit.take_egui_input(self.surface.window());
+        self.frame_needed = self.repaint_requested;
+        self.last_frame_start = Instant::now();
+        self.swap_in_pending_fonts();
+        #[cfg(feature = "shader_hot_reload")]
+        self.reload_custom_shaders();
+        let raw_input = self.take_egui_input();
         self.egui_ctx.begin_frame(raw_input);
+        if let Some(glyph_atlas) = &mut self.glyph_atlas {
+            glyph_atlas.begin_frame();
+        }
+        self.prewarm_pending_text();
+        self.layer_markers.begin_frame(&self.egui_ctx);
+        if let Some((_, markers)) = &mut self.panel_shadows {
+            let windows: Vec<LayerId> = self
+                .egui_ctx
+                .memory()
+                .layer_ids()
+                .filter(|layer_id| layer_id.order == egui::Order::Middle)
+                .collect();
+            *markers = LayerMarkers::new(windows);
+            markers.begin_frame(&self.egui_ctx);
+        }
+        let screen_rect = self.egui_ctx.input().screen_rect();
+        for (layer_id, callback) in &self.layer_callbacks {
+            // Being the first shape of the layer, the callback is drawn right before the layer
+            self.egui_ctx
+                .layer_painter(*layer_id)
+                .add(PaintCallback { rect: screen_rect, callback: callback.callback.clone() });
+        }
+    }
+
+    #[cfg(feature = "shader_hot_reload")]
+    fn reload_custom_shaders(&mut self) {
+        let watcher = match &mut self.shader_watcher {
+            Some(watcher) => watcher,
+            None => return,
+        };
+        let device = self.renderer.queue().device().clone();
+        // Safety: guaranteed by the caller of `watch_custom_shaders`
+        if let Some(reloaded) = unsafe { watcher.poll(&device) } {
+            let result = reloaded.and_then(|shaders| self.renderer.set_custom_shaders(shaders));
+            self.shader_reload_result = Some(result);
+        }
+    }
+
+    fn swap_in_pending_fonts(&mut self) {
+        let receiver = match &self.pending_fonts {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        match receiver.try_recv() {
+            Ok(definitions) => {
+                self.egui_ctx.set_fonts(definitions);
+                self.pending_fonts = None;
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            // Loading panicked, keep the old fonts
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => self.pending_fonts = None,
+        }
+    }
+
+    /// Rasterizes the glyphs of `strings` in each of `styles` at the start of the next frame, to be uploaded
+    /// along with it. Call it e.g. during a loading screen, so that a large table or CJK text doesn't stall the
+    /// frame it first appears in. Glyphs are rasterized for the current fonts & scale.
+    pub fn prewarm_text(&mut self, styles: &[egui::TextStyle], strings: &[&str]) {
+        let mut chars: Vec<char> = strings.iter().flat_map(|string| string.chars()).collect();
+        chars.sort_unstable();
+        chars.dedup();
+        let chars: String = chars.into_iter().filter(|c| !c.is_control()).collect();
+        for style in styles {
+            self.pending_prewarm.push((style.clone(), chars.clone()));
+        }
+        self.repaint_requested = true;
+    }
+
+    fn prewarm_pending_text(&mut self) {
+        if self.pending_prewarm.is_empty() {
+            return;
+        }
+        let style = self.egui_ctx.style();
+        let fonts = self.egui_ctx.fonts();
+        for (text_style, chars) in self.pending_prewarm.drain(..) {
+            if let Some(font_id) = style.text_styles.get(&text_style) {
+                fonts.layout_no_wrap(chars, font_id.clone(), egui::Color32::WHITE);
+            }
+        }
+    }
+
+    /// Installs an external text shaper for [`Gui::shaped_text`]. Its glyphs are cached in atlas textures managed
+    /// by egui, which replace the previous shaper's atlas.
+    pub fn set_text_shaper(&mut self, shaper: impl TextShaper + 'static) {
+        let side = self.egui_ctx.input().max_texture_side.min(GLYPH_ATLAS_SIDE);
+        self.glyph_atlas = Some(GlyphAtlas::new(Arc::new(shaper), side));
+    }
+
+    /// Paragraph direction of [`Gui::shaped_text`], defaults to [`TextDirection::Auto`] detecting it from the text.
+    /// Egui's own text layout is always left-to-right, so use shaped text for right-to-left & complex scripts.
+    pub fn set_text_direction(&mut self, direction: TextDirection) {
+        self.text_direction = direction;
+    }
+
+    /// Number of atlas textures glyphs of the text shaper are cached in, pages are added as scripts
+    /// with many glyphs fill them
+    pub fn glyph_atlas_pages(&self) -> usize {
+        self.glyph_atlas.as_ref().map_or(0, |glyph_atlas| glyph_atlas.num_pages())
+    }
+
+    pub fn remove_text_shaper(&mut self) {
+        self.glyph_atlas = None;
+    }
+
+    /// Shapes `text` of `font_size` points with the shaper set with [`Gui::set_text_shaper`] & returns a shape
+    /// drawing it with the start of its baseline at `pos` (the right end for right-to-left text, see
+    /// [`Gui::set_text_direction`]), e.g. to add with `ui.painter().add(..)`. Returns `Shape::Noop` without a shaper.
+    pub fn shaped_text(
+        &mut self,
+        pos: egui::Pos2,
+        text: &str,
+        font_size: f32,
+        color: egui::Color32,
+    ) -> egui::Shape {
+        match &mut self.glyph_atlas {
+            Some(glyph_atlas) => glyph_atlas.shape_text(
+                &self.egui_ctx,
+                pos,
+                text,
+                font_size,
+                self.text_direction,
+                color,
+            ),
+            None => egui::Shape::Noop,
+        }
+    }
+
+    /// Loads font definitions with `load` on a worker thread (e.g. reading a font file the user picked from disk or
+    /// the network), then sets them at the beginning of a frame. The current fonts stay in use in the meantime.
+    /// Only the loading is moved off the main thread: egui 0.18 has no way to install prebuilt `Fonts`, so the
+    /// frame the fonts are set on still parses them & rebuilds the whole atlas, as `egui::Context::set_fonts` does.
+    /// A repaint is requested once the fonts are loaded, see [`Gui::set_repaint_proxy`].
+    /// If loading panics, the current fonts are kept. Calling this again replaces a pending load.
+    pub fn set_fonts_async(
+        &mut self,
+        load: impl FnOnce() -> egui::FontDefinitions + Send + 'static,
+    ) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let ctx = self.egui_ctx.clone();
+        std::thread::spawn(move || {
+            if sender.send(load()).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+        self.pending_fonts = Some(receiver);
+    }
+
+    /// Takes accumulated input from winit state and fills in file information winit doesn't give us
+    fn take_egui_input(&mut self) -> egui::RawInput {
+        let mut raw_input = self.egui_winit.take_egui_input(self.surface.window());
+        let pixels_per_point = self.pixels_per_point();
+        if pixels_per_point != self.input_pixels_per_point {
+            Self::remap_input(&mut raw_input, self.input_pixels_per_point / pixels_per_point);
+        }
+        for file in raw_input.dropped_files.iter_mut() {
+            if let Some(path) = &file.path {
+                if file.name.is_empty() {
+                    if let Some(name) = path.file_name() {
+                        file.name = name.to_string_lossy().into_owned();
+                    }
+                }
+                if file.last_modified.is_none() {
+                    file.last_modified =
+                        std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+                }
+            }
+        }
+        if let Some(clipboard) = &mut self.clipboard {
+            replace_pastes(&mut raw_input.events, clipboard.as_mut());
+        }
+        #[cfg(feature = "remote")]
+        if let Some(remote_mirror) = &self.remote_mirror {
+            raw_input.events.extend(remote_mirror.take_events());
+        }
+        // Glyphs are rasterized for the drawn resolution, layout in points stays the same
+        raw_input.pixels_per_point = Some(pixels_per_point * self.renderer.render_scale());
+        raw_input
+    }
+
+    /// Scales the points of input egui-winit mapped with another scale than the gui's, as it follows egui's
+    /// `pixels_per_point` which includes the render scale
+    fn remap_input(raw_input: &mut egui::RawInput, scale: f32) {
+        if let Some(screen_rect) = &mut raw_input.screen_rect {
+            *screen_rect = egui::Rect::from_min_max(
+                (screen_rect.min.to_vec2() * scale).to_pos2(),
+                (screen_rect.max.to_vec2() * scale).to_pos2(),
+            );
+        }
+        for event in &mut raw_input.events {
+            match event {
+                egui::Event::PointerMoved(pos)
+                | egui::Event::PointerButton { pos, .. }
+                | egui::Event::Touch { pos, .. } => *pos = (pos.to_vec2() * scale).to_pos2(),
+                egui::Event::Scroll(delta) => *delta *= scale,
+                _ => {}
+            }
+        }
+    }
+
+    /// Physical pixels per point the ui is laid out & drawn with, & its input mapped with: the one set with
+    /// [`Gui::set_pixels_per_point`] or the window's scale factor, times the [`Gui::set_zoom_factor`]
+    pub fn pixels_per_point(&self) -> f32 {
+        let pixels_per_point = self
+            .pixels_per_point_override
+            .unwrap_or_else(|| egui_winit::native_pixels_per_point(self.surface.window()));
+        pixels_per_point * self.zoom_factor
+    }
+
+    /// Overrides the window's scale factor, for both input & drawing, from the next frame. `None` restores it.
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: Option<f32>) {
+        if let Some(pixels_per_point) = pixels_per_point {
+            assert!(
+                pixels_per_point > 0.0,
+                "Pixels per point must be positive, got {}",
+                pixels_per_point
+            );
+        }
+        self.pixels_per_point_override = pixels_per_point;
+        self.repaint_requested = true;
+    }
+
+    /// Scales the ui from the next frame, e.g. to zoom in & out on Ctrl+= & Ctrl+- like browsers do. Multiplies the
+    /// window's scale factor or the override of [`Gui::set_pixels_per_point`], so zoom is kept across monitors.
+    /// 1 by default.
+    pub fn set_zoom_factor(&mut self, zoom_factor: f32) {
+        assert!(zoom_factor > 0.0, "Zoom factor must be positive, got {}", zoom_factor);
+        self.zoom_factor = zoom_factor;
+        self.repaint_requested = true;
+    }
+
+    pub fn zoom_factor(&self) -> f32 {
+        self.zoom_factor
+    }
+
+    /// Whether egui's dark or light visuals are applied when the system theme changes (reported by winit on
+    /// Windows), replacing visuals you've set. Enabled by default, disable it to keep your own visuals.
+    pub fn set_follow_system_theme(&mut self, enabled: bool) {
+        self.follow_system_theme = enabled;
+    }
+
+    pub fn follow_system_theme(&self) -> bool {
+        self.follow_system_theme
+    }
+
+    /// Streams each frame's meshes & texture updates to a [`RemoteViewer`](crate::RemoteViewer), whose input events
+    /// are added to the ui's own. Set it before the first frame, the viewer only gets textures created or changed
+    /// after this. User images & paint callbacks aren't mirrored.
+    #[cfg(feature = "remote")]
+    pub fn set_remote_mirror(&mut self, remote_mirror: Option<RemoteMirror>) {
+        self.remote_mirror = remote_mirror;
+    }
+
+    /// Whether a remote viewer is connected
+    #[cfg(feature = "remote")]
+    pub fn is_remote_connected(&self) -> bool {
+        self.remote_mirror.as_ref().map_or(false, |remote_mirror| remote_mirror.is_connected())
     }
 
     /// Renders ui on `final_image` & Updates cursor icon
     /// Finishes Egui frame
     /// - `before_future` = Vulkano's GpuFuture
     /// - `final_image` = Vulkano's image (render target)
+    ///
+    /// On `GuiError::Flush(FlushError::OutOfDate)` (e.g. the window was resized after acquiring the image), recreate
+    /// your swapchain and draw the frame again from `begin_frame`. Texture updates of the frame have been applied, so
+    /// nothing is lost. A suboptimal swapchain still draws fine and is reported by acquiring & presenting, not here.
     pub fn draw_on_image<F>(
         &mut self,
         before_future: F,
         final_image: Arc<dyn ImageViewAbstract + 'static>,
-    ) -> Box<dyn GpuFuture>
+    ) -> Result<Box<dyn GpuFuture>, GuiError>
     where
         F: GpuFuture + 'static,
     {
-        if !self.renderer.has_renderpass() {
+        if !self.renderer.has_renderpass() && !self.renderer.is_dynamic_rendering() {
             panic!(
                 "Gui integration has been created with subpass, use `draw_on_subpass_image` \
                  instead"
             )
         }
 
-        let format = final_image.format();
-        if format != Some(vulkano::format::Format::B8G8R8A8_SRGB) {
-            panic!(
-                "Render target image color format is wrong {:?}, should be {:?}",
-                format,
-                Some(vulkano::format::Format::B8G8R8A8_SRGB)
-            );
+        self.check_render_target_format(&final_image)?;
+
+        let framebuffer_dimensions = final_image.image().dimensions().width_height();
+        let (clipped_meshes, textures_delta) =
+            self.extract_cached_draw_data(framebuffer_dimensions)?;
+
+        // User images may have changed under the same primitives, e.g. updated or registered again under their id
+        let user_textures_version = self.renderer.user_textures_version();
+        let frame_hash = primitives_hash(&clipped_meshes).map(|hash| {
+            hash ^ u64::from(self.pixels_per_point().to_bits()).rotate_left(32)
+                ^ user_textures_version.rotate_left(16)
+        });
+        if self.can_skip_draw(&final_image, &textures_delta, frame_hash) {
+            return Ok(before_future.boxed());
         }
+        // Not drawn if submitting fails
+        self.last_drawn_image = None;
+        self.last_drawn_hash = None;
 
-        let (clipped_meshes, textures_delta) = self.extract_draw_data_at_frame_end();
+        #[cfg(feature = "clipboard_image")]
+        {
+            self.last_frame_image = Some(final_image.clone());
+        }
 
-        self.renderer.draw_on_image(
+        let after_future = self.renderer.draw_on_image(
             &clipped_meshes,
             &textures_delta,
-            self.egui_winit.pixels_per_point(),
+            self.pixels_per_point(),
             before_future,
-            final_image,
-        )
+            final_image.clone(),
+        )?;
+        self.last_drawn_image = Some(final_image);
+        self.last_drawn_hash = frame_hash;
+        Ok(after_future)
+    }
+
+    /// A frame can be skipped if the image still contains the ui drawn last time & this frame draws the same
+    fn can_skip_draw(
+        &self,
+        final_image: &Arc<dyn ImageViewAbstract + 'static>,
+        textures_delta: &TexturesDelta,
+        frame_hash: Option<u64>,
+    ) -> bool {
+        self.skip_idle_frames
+            && frame_hash.is_some()
+            && frame_hash == self.last_drawn_hash
+            && !self.renderer.is_overlay()
+            && textures_delta.set.is_empty()
+            && textures_delta.free.is_empty()
+            && self.last_drawn_image.as_ref().map_or(false, |last| {
+                Arc::as_ptr(last) as *const () == Arc::as_ptr(final_image) as *const ()
+            })
     }
 
     /// Creates commands for rendering ui on subpass' image and returns the command buffer for execution on your side
@@ -168,7 +990,7 @@ impl Gui {
     pub fn draw_on_subpass_image(
         &mut self,
         image_dimensions: [u32; 2],
-    ) -> SecondaryAutoCommandBuffer {
+    ) -> Result<SecondaryAutoCommandBuffer, GuiError> {
         if self.renderer.has_renderpass() {
             panic!(
                 "Gui integration has been created with its own render pass, use `draw_on_image` \
@@ -176,77 +998,1271 @@ impl Gui {
             )
         }
 
-        let (clipped_meshes, textures_delta) = self.extract_draw_data_at_frame_end();
+        let (clipped_meshes, textures_delta) = self.extract_cached_draw_data(image_dimensions)?;
 
         self.renderer.draw_on_subpass_image(
             &clipped_meshes,
             &textures_delta,
-            self.egui_winit.pixels_per_point(),
+            self.pixels_per_point(),
             image_dimensions,
         )
     }
 
-    fn extract_draw_data_at_frame_end(&mut self) -> (Vec<ClippedPrimitive>, TexturesDelta) {
-        self.end_frame();
-        let shapes = std::mem::take(&mut self.shapes);
-        let textures_delta = std::mem::take(&mut self.textures_delta);
-        let clipped_meshes = self.egui_ctx.tessellate(shapes);
-        (clipped_meshes, textures_delta)
+    /// Sets how many frames may be drawn before the GPU has finished the first, 2 by default. The geometry & staging
+    /// rings have room for as many frames & device-local geometry is kept per frame in flight. Match it to your
+    /// swapchain (e.g. 3 for triple buffering), or set 1 if you wait for each frame to finish. Panics if `frames_in_flight` is 0.
+    pub fn set_frames_in_flight(&mut self, frames_in_flight: u32) {
+        self.renderer.set_frames_in_flight(frames_in_flight);
     }
 
-    fn end_frame(&mut self) {
-        let egui::FullOutput { platform_output, needs_repaint: _n, textures_delta, shapes } =
-            self.egui_ctx.end_frame();
+    /// Draws textures from one descriptor array indexed by a push constant, so that meshes of different textures
+    /// don't each bind a descriptor set, e.g. for texture-heavy UIs. Needs a Vulkan 1.2 device (descriptor indexing
+    /// of Vulkan 1.1's extension isn't enough, the shader is SPIR-V 1.5) with the `runtime_descriptor_array`,
+    /// `descriptor_binding_variable_descriptor_count` & `shader_sampled_image_array_dynamic_indexing` features. The
+    /// array is rewritten when textures are registered or freed, & each draw uses all of them, so don't update user
+    /// images while a frame is in flight. YUV and raw images, & textures beyond the device's descriptor limits are
+    /// drawn as before. Disabled by default.
+    pub fn set_bindless(&mut self, enabled: bool) -> Result<(), GuiError> {
+        self.renderer.set_bindless(enabled)
+    }
 
-        self.egui_winit.handle_platform_output(
-            self.surface.window(),
-            &self.egui_ctx,
-            platform_output,
+    /// Draws meshes from device-local vertex & index buffers, which egui's geometry is copied to before each draw.
+    /// Speeds up very heavy UIs (e.g. node graphs of tens of thousands of primitives) on discrete GPUs, at the cost
+    /// of the copy & of twice the buffer memory. With `draw_on_subpass_image` the copy is waited for, as you execute
+    /// the command buffer. Disabled by default.
+    pub fn set_device_local_geometry(&mut self, enabled: bool) {
+        self.renderer.set_device_local_geometry(enabled);
+    }
+
+    /// Snaps vertices of untextured shapes (widget rects, separators, borders) to physical pixels & disables
+    /// feathering, so that 1px lines stay crisp at any DPI. Text is rounded to pixels by egui. Clip rects are
+    /// always snapped to pixels. Disabling restores the tessellation options enabling changed. Disabled by default.
+    pub fn set_pixel_snapping(&mut self, enabled: bool) {
+        self.renderer.set_pixel_snapping(enabled);
+        let mut options = self.egui_ctx.tessellation_options();
+        match (enabled, self.snapped_tessellation.take()) {
+            (true, None) => {
+                self.snapped_tessellation =
+                    Some((options.feathering, options.round_text_to_pixels));
+                options.feathering = false;
+                options.round_text_to_pixels = true;
+            }
+            (true, snapped) => self.snapped_tessellation = snapped,
+            (false, Some((feathering, round_text_to_pixels))) => {
+                options.feathering = feathering;
+                options.round_text_to_pixels = round_text_to_pixels;
+            }
+            (false, None) => {}
+        }
+    }
+
+    /// Runs a frame of a secondary context, which is drawn on top of the main ui in the same pass by the next draw
+    /// call. Call it between beginning the main frame & drawing.
+    pub fn run_secondary(
+        &mut self,
+        secondary: &mut SecondaryContext,
+        run_ui: impl FnOnce(&egui::Context),
+    ) {
+        let size = self.surface.window().inner_size();
+        let (primitives, textures_delta, open_url) = secondary.run(
+            [size.width, size.height],
+            self.pixels_per_point(),
+            self.egui_ctx.input().max_texture_side,
+            run_ui,
         );
-        self.shapes = shapes;
-        self.textures_delta = textures_delta;
+        self.secondary_frames.push((primitives, textures_delta));
+        if open_url.is_some() {
+            self.secondary_open_url = open_url;
+        }
     }
 
-    /// Registers a user image from Vulkano image view to be used by egui
-    pub fn register_user_image_view(
+    /// Frees the textures of a secondary context that's no longer run, with the next draw call
+    pub fn remove_secondary(&mut self, mut secondary: SecondaryContext) {
+        self.secondary_frames.push((vec![], secondary.free_textures()));
+    }
+
+    /// Draws soft shadows beneath windows, blurred in the fragment shader. They look better than egui's tessellated
+    /// shadows, which you may want to disable with `style.visuals.window_shadow = Shadow::default()`. `None`
+    /// disables them, which is the default. Returns errors of creating their pipeline when enabling them.
+    pub fn set_panel_shadows(
         &mut self,
-        image: Arc<dyn ImageViewAbstract + Send + Sync>,
-    ) -> egui::TextureId {
-        self.renderer.register_image(image)
+        panel_shadows: Option<PanelShadows>,
+    ) -> Result<(), GuiError> {
+        match panel_shadows {
+            Some(panel_shadows) => {
+                if self.panel_shadows.is_none() {
+                    let renderer = PanelShadowRenderer::new(
+                        self.renderer.queue(),
+                        self.pipeline_render_pass(),
+                        self.sample_count(),
+                    )?;
+                    self.renderer.register_callback_renderer(PANEL_SHADOW_KEY, Arc::new(renderer));
+                }
+                self.panel_shadows = Some((panel_shadows, LayerMarkers::default()));
+            }
+            None => {
+                self.renderer.unregister_callback_renderer(PANEL_SHADOW_KEY);
+                self.panel_shadows = None;
+            }
+        }
+        Ok(())
     }
 
-    /// Registers a user image to be used by egui
-    /// - `image_file_bytes`: e.g. include_bytes!("./assets/tree.png")
-    /// - `format`: e.g. vulkano::format::Format::R8G8B8A8Unorm
-    pub fn register_user_image(
+    /// Calls `announcer` at the end of frames in which a widget got focus, was clicked or changed its value, with
+    /// egui's description of it (e.g. "checkbox: Show grid, checked"). Hook a text-to-speech system to it to
+    /// announce the ui to visually impaired users.
+    pub fn set_announcer(&mut self, announcer: impl FnMut(&str) + Send + 'static) {
+        self.announcer = Some(Box::new(announcer));
+    }
+
+    /// Removes the announcer set with [`Gui::set_announcer`]
+    pub fn remove_announcer(&mut self) {
+        self.announcer = None;
+    }
+
+    /// Lets `handler` open hyperlinks clicked in the ui (also in secondary contexts), e.g. in an in-app browser or a
+    /// sandbox friendly way. Links the handler returns `false` for are opened in the system browser as usual, so
+    /// return `true` to block a link (e.g. in kiosk mode) & `false` to only log it.
+    pub fn set_url_handler(
         &mut self,
-        image_file_bytes: &[u8],
-        format: vulkano::format::Format,
-    ) -> egui::TextureId {
-        let image = immutable_texture_from_file(self.renderer.queue(), image_file_bytes, format)
-            .expect("Failed to create image");
-        self.renderer.register_image(image)
+        handler: impl FnMut(&egui::output::OpenUrl) -> bool + Send + 'static,
+    ) {
+        self.url_handler = Some(Box::new(handler));
     }
 
-    pub fn register_user_image_from_bytes(
+    /// Removes the handler set with [`Gui::set_url_handler`]
+    pub fn remove_url_handler(&mut self) {
+        self.url_handler = None;
+    }
+
+    /// Copies & pastes text with `clipboard` instead of the system clipboard, from the next frame. `None` restores
+    /// the system clipboard. Images pasted with the `clipboard_image` feature still come from the system clipboard.
+    pub fn set_clipboard(&mut self, clipboard: Option<Box<dyn ClipboardProvider>>) {
+        self.clipboard = clipboard;
+    }
+
+    /// Draws a shadow or outline beneath all text in egui's fonts (not [`Gui::shaped_text`]), e.g. to keep a HUD
+    /// readable over bright 3D scenes. `None` disables it, which is the default.
+    pub fn set_text_effect(&mut self, text_effect: Option<TextEffect>) {
+        self.text_effect = text_effect;
+    }
+
+    /// Filters egui's output to simulate how it looks with a color vision deficiency, to audit the ui's color
+    /// accessibility. Paint callbacks aren't filtered. `None` disables it, which is the default. The pipelines are
+    /// recompiled with the matrix, which custom fragment shaders read from the float specialization constants 4 to
+    /// 12 (column-major).
+    pub fn set_color_vision_simulation(
         &mut self,
-        image_byte_data: &[u8],
-        dimensions: [u32; 2],
-        format: vulkano::format::Format,
-    ) -> egui::TextureId {
-        let image = immutable_texture_from_bytes(
-            self.renderer.queue(),
-            image_byte_data,
-            dimensions,
-            format,
-        )
-        .expect("Failed to create image");
-        self.renderer.register_image(image)
+        deficiency: Option<ColorVisionDeficiency>,
+    ) -> Result<(), GuiError> {
+        self.renderer.set_color_vision_simulation(deficiency)
     }
 
-    /// Unregisters a user image
-    pub fn unregister_user_image(&mut self, texture_id: egui::TextureId) {
-        self.renderer.unregister_image(texture_id);
+    /// Sets which components of the target image egui writes, all of them by default. E.g. write only alpha to
+    /// draw the ui's coverage into an existing image, or only RGB to keep an alpha mask the image holds. Paint
+    /// callbacks are drawn with their own pipelines & aren't masked.
+    pub fn set_color_write_mask(
+        &mut self,
+        color_write_mask: ColorComponents,
+    ) -> Result<(), GuiError> {
+        self.renderer.set_color_write_mask(color_write_mask)
+    }
+
+    /// Creates egui's pipelines with `pipeline_cache`, e.g. one seeded with `PipelineCache::with_data` from the
+    /// data of the previous run, so that they compile faster on startup. Call it right after creating the gui, as
+    /// the pipelines are recompiled with it. Save [`Gui::pipeline_cache`]'s `get_data` before exiting to reuse it.
+    /// The cache can be shared with your own pipelines. `None` creates them without a cache, which is the default.
+    pub fn set_pipeline_cache(
+        &mut self,
+        pipeline_cache: Option<Arc<PipelineCache>>,
+    ) -> Result<(), GuiError> {
+        self.renderer.set_pipeline_cache(pipeline_cache)
+    }
+
+    /// Cache egui's pipelines are created with, see [`Gui::set_pipeline_cache`]
+    pub fn pipeline_cache(&self) -> Option<Arc<PipelineCache>> {
+        self.renderer.pipeline_cache()
+    }
+
+    /// Encodes the linear output of egui's fragment shader to sRGB, for drawing into UNORM images (e.g. UNORM
+    /// swapchains or storage images) which don't encode it themselves, so that colors don't look too dark. Enabled
+    /// by default when egui is drawn in a UNORM format. Blending then happens on the encoded colors, as egui's
+    /// colors are specified in. Custom fragment shaders opt in by declaring the bool specialization constant 0,
+    /// see [`Gui::set_output_transfer`].
+    pub fn set_shader_srgb_encoding(&mut self, enabled: bool) -> Result<(), GuiError> {
+        let output_transfer = if enabled { OutputTransfer::Srgb } else { OutputTransfer::Linear };
+        self.renderer.set_output_transfer(output_transfer)
+    }
+
+    /// Whether egui's shaders encode their output to sRGB, see [`Gui::set_shader_srgb_encoding`]
+    pub fn shader_srgb_encoding(&self) -> bool {
+        self.renderer.output_transfer() == OutputTransfer::Srgb
+    }
+
+    /// Selects how egui's fragment shader encodes its output, e.g. for HDR swapchains. Create the swapchain in the
+    /// color space matching it, e.g. `R16G16B16A16_SFLOAT` with `ExtendedSrgbLinear` for
+    /// [`OutputTransfer::ScRgb`], & egui with [`Gui::new_with_format`]. `sdr_white_nits` sets the ui's brightness,
+    /// e.g. to the system's SDR white level, as HDR displays would otherwise show it at 80 nits or dimmer. Images
+    /// are sampled as SDR content. Defaults to [`OutputTransfer::Srgb`] on UNORM targets, else
+    /// [`OutputTransfer::Linear`]. Custom fragment shaders read sRGB encoding from the bool specialization constant
+    /// 0, the HDR transfer from the uint constant 3 (0: none, 1: scRGB, 2: PQ) & its scale from the float constant 1
+    /// (ui white relative to 80 nits for scRGB, 10000 for PQ), & whether to output straight alpha from the bool
+    /// constant 2, see [`Gui::set_blend_mode`].
+    pub fn set_output_transfer(&mut self, output_transfer: OutputTransfer) -> Result<(), GuiError> {
+        self.renderer.set_output_transfer(output_transfer)
+    }
+
+    /// How egui's fragment shader encodes its output, see [`Gui::set_output_transfer`]
+    pub fn output_transfer(&self) -> OutputTransfer {
+        self.renderer.output_transfer()
+    }
+
+    /// Sets how egui's output is blended onto the target, [`BlendMode::Premultiplied`] by default. Compositing the
+    /// ui over some scenes needs other blending, e.g. straight alpha for a compositor expecting it. The pipelines
+    /// are recompiled with it. Paint callbacks are drawn with their own pipelines & keep their blending.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) -> Result<(), GuiError> {
+        self.renderer.set_blend_mode(blend_mode)
+    }
+
+    /// How egui's output is blended onto the target, see [`Gui::set_blend_mode`]
+    pub fn blend_mode(&self) -> BlendMode {
+        self.renderer.blend_mode()
+    }
+
+    /// Sets the depth & stencil state of egui's pipelines when drawn on your subpass, e.g. to test the ui against
+    /// the depth of your scene with `DepthStencilState::simple_depth_test`. Egui's vertices have a depth of 0, or
+    /// whatever your [`Gui::set_projection`] gives them. Disabled by default, which is valid in subpasses with or
+    /// without depth attachments. Enabling a test needs the subpass to have the depth or stencil attachment, else
+    /// [`GuiError::NoDepthStencilAttachment`] is returned & the current state is kept, as it is when the pipeline
+    /// can't be created with it.
+    pub fn set_depth_stencil_state(
+        &mut self,
+        depth_stencil: DepthStencilState,
+    ) -> Result<(), GuiError> {
+        self.renderer.set_depth_stencil_state(depth_stencil)
+    }
+
+    /// Depth & stencil state of egui's pipelines, see [`Gui::set_depth_stencil_state`]
+    pub fn depth_stencil_state(&self) -> &DepthStencilState {
+        self.renderer.depth_stencil_state()
+    }
+
+    /// Replaces egui's vertex and/or fragment shader, e.g. to tint the ui or add a CRT effect. The pipelines are
+    /// recompiled with them. Start from egui's vertex shader in `src/renderer.rs` & its fragment shader in
+    /// `src/shaders/egui.frag`: the vertex shader must take the same inputs, both stages must declare the same push
+    /// constants, & the fragment shader samples the texture bound to set 0, binding 0. The bindless pipeline keeps
+    /// egui's fragment shader, & the static cache renders with egui's shaders, applying custom ones when drawing its
+    /// textures. E.g. `CustomShaders::default()` restores egui's shaders.
+    ///
+    /// Egui's pipeline is created right away to check the shaders, shaders that don't fit it (e.g. without a `main`
+    /// entry point or with other push constants) return an error & keep the current shaders.
+    pub fn set_custom_shaders(&mut self, shaders: CustomShaders) -> Result<(), GuiError> {
+        self.renderer.set_custom_shaders(shaders)
+    }
+
+    /// Shaders set with [`Gui::set_custom_shaders`]
+    pub fn custom_shaders(&self) -> &CustomShaders {
+        self.renderer.custom_shaders()
+    }
+
+    /// Loads custom shaders from SPIR-V files, e.g. output by `glslc`, & reloads them whenever the files change,
+    /// checked at the start of each frame. Shaders that fail to load or don't fit egui's pipeline keep the previous
+    /// ones in use, see [`Gui::take_shader_reload_result`]. `None` paths use egui's shader for the stage. Replaces
+    /// the previously watched files & restores egui's shaders until they're loaded.
+    ///
+    /// # Safety
+    ///
+    /// The files must hold valid SPIR-V whenever they're loaded, see `ShaderModule::from_bytes`.
+    #[cfg(feature = "shader_hot_reload")]
+    pub unsafe fn watch_custom_shaders(
+        &mut self,
+        vertex: Option<std::path::PathBuf>,
+        fragment: Option<std::path::PathBuf>,
+    ) -> Result<(), GuiError> {
+        self.renderer.set_custom_shaders(CustomShaders::default())?;
+        self.shader_watcher = Some(ShaderWatcher::new(vertex, fragment));
+        self.shader_reload_result = None;
+        Ok(())
+    }
+
+    /// Outcome of the last reload of the shaders watched with [`Gui::watch_custom_shaders`] since the last call,
+    /// e.g. to show compile errors in the ui. `None` if they haven't been reloaded since.
+    #[cfg(feature = "shader_hot_reload")]
+    pub fn take_shader_reload_result(&mut self) -> Option<Result<(), GuiError>> {
+        self.shader_reload_result.take()
+    }
+
+    /// Selects how shape edges are anti-aliased, [`AntiAliasing::Feathering`] by default. Analytic anti-aliasing
+    /// relies on egui's feathering, so it re-enables feathering disabled by [`Gui::set_pixel_snapping`].
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) {
+        let analytic = anti_aliasing == AntiAliasing::Analytic;
+        self.renderer.set_analytic_anti_aliasing(analytic);
+        if analytic {
+            self.egui_ctx.tessellation_options().feathering = true;
+        }
+    }
+
+    /// Allocates the descriptor sets of textures registered from now on from `descriptor_pool`, e.g. one shared
+    /// with your engine so that its allocations are tracked in one place. Set it right after creating the gui to
+    /// cover the font atlas too. `None` (the default) allocates from the device's standard pool.
+    ///
+    /// Vulkano 0.30 doesn't take memory or command buffer allocators, so buffers, images & command buffers are
+    /// still allocated from the device's standard pools. Images registered with
+    /// [`Gui::register_raw_image_view`] keep their own descriptor pools.
+    pub fn set_descriptor_pool(
+        &mut self,
+        descriptor_pool: Option<Arc<Mutex<dyn DescriptorAllocator>>>,
+    ) {
+        self.renderer.set_descriptor_pool(descriptor_pool);
+    }
+
+    /// Overrides the projection (a column major matrix from points to clip space) egui is drawn with, e.g. to tilt
+    /// the HUD slightly in 3D or flip Y. `None` restores the default [`orthographic_projection`](crate::orthographic_projection),
+    /// which can be used as a base for your own. Clip rects stay axis aligned in screen space.
+    pub fn set_projection(&mut self, projection: Option<[[f32; 4]; 4]>) {
+        self.renderer.set_projection(projection);
+    }
+
+    /// Configures inheritance of the secondary command buffers returned by `draw_on_subpass_image`, e.g. to execute
+    /// them in a compatible subpass, within an occlusion query or while pipeline statistics are queried.
+    pub fn set_subpass_inheritance(&mut self, subpass_inheritance: SubpassInheritance) {
+        self.renderer.set_subpass_inheritance(subpass_inheritance);
+    }
+
+    /// Sets egui layers (e.g. tooltips & popups) to be rendered separately from the rest of the ui with
+    /// [`Gui::draw_on_images`] or [`Gui::draw_on_subpass_images`], so you can draw e.g. 3D gizmos between the
+    /// background ui and the popups. Layers can be found with `egui::Memory::layer_ids`, e.g. by their `egui::Order`.
+    ///
+    /// Selected layers are always drawn separately, even if an unselected layer is on top of them.
+    /// Call this outside of a frame, i.e. before `begin_frame` (or `immediate_ui`) or after drawing.
+    pub fn set_separate_layers(&mut self, layer_ids: impl IntoIterator<Item = LayerId>) {
+        self.layer_markers = LayerMarkers::new(layer_ids);
+    }
+
+    /// Layers currently set to be rendered separately
+    pub fn separate_layers(&self) -> Vec<LayerId> {
+        self.layer_markers.layer_ids().collect()
+    }
+
+    /// Sets a callback recording custom draw commands right before `layer_id` is drawn, e.g. to render 3D overlays
+    /// above panels but below tooltips. The callback's viewport covers the whole screen.
+    /// Pipelines used in the callback must be created for [`Gui::subpass`].
+    pub fn set_layer_callback(
+        &mut self,
+        layer_id: LayerId,
+        callback: impl Fn(&PaintCallbackInfo, &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>)
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.layer_callbacks.insert(layer_id, paint_callback(egui::Rect::NOTHING, callback));
+    }
+
+    /// Installs a renderer for paint callbacks created with [`keyed_paint_callback`](crate::keyed_paint_callback)
+    /// using `key`. Replaces a renderer previously registered with the same key.
+    pub fn register_callback_renderer(
+        &mut self,
+        key: impl Into<String>,
+        renderer: impl CallbackRenderer + 'static,
+    ) {
+        self.renderer.register_callback_renderer(key, Arc::new(renderer));
+    }
+
+    /// Removes a renderer registered with [`Gui::register_callback_renderer`]
+    pub fn unregister_callback_renderer(&mut self, key: &str) {
+        self.renderer.unregister_callback_renderer(key);
+    }
+
+    /// Removes a callback set with [`Gui::set_layer_callback`]
+    pub fn remove_layer_callback(&mut self, layer_id: LayerId) {
+        self.layer_callbacks.remove(&layer_id);
+    }
+
+    /// The subpass egui is drawn in, for creating pipelines used in paint callbacks
+    ///
+    /// Panics if the gui was created with [`Gui::new_with_dynamic_rendering`], use [`Gui::pipeline_render_pass`].
+    pub fn subpass(&self) -> Subpass {
+        self.renderer.subpass()
+    }
+
+    /// The subpass or dynamic rendering attachments egui is drawn in, to pass to `GraphicsPipelineBuilder::render_pass`
+    /// for pipelines used in paint callbacks
+    pub fn pipeline_render_pass(&self) -> PipelineRenderPassType {
+        self.renderer.pipeline_render_pass()
+    }
+
+    /// Sets what `draw_on_image` does with the image's contents before drawing egui: clear them to `clear_color`, or
+    /// load them with `None`, like creating the gui with `is_overlay`. E.g. clear to your background color instead
+    /// of transparent black, or switch to drawing over your scene without creating another gui. Has no effect when
+    /// egui is drawn on your subpass.
+    pub fn set_clear_color(&mut self, clear_color: Option<[f32; 4]>) -> Result<(), GuiError> {
+        self.renderer.set_clear_color(clear_color)
+    }
+
+    /// Color the image is cleared to before drawing, `None` if its contents are loaded, see [`Gui::set_clear_color`]
+    pub fn clear_color(&self) -> Option<[f32; 4]> {
+        self.renderer.clear_color()
+    }
+
+    /// Samples per pixel egui's pipeline rasterizes with, the sample count of the pass it's drawn in. Pipelines
+    /// used in paint callbacks need the same multisample state.
+    pub fn sample_count(&self) -> SampleCount {
+        self.renderer.sample_count()
+    }
+
+    /// Same as `draw_on_image`, but layers set with [`Gui::set_separate_layers`] are rendered on `separate_image`
+    /// after the rest of the ui is rendered on `final_image`. These frames are always drawn, neither skipped (see
+    /// [`Gui::set_skip_idle_frames`]) nor drawn from the ui cache ([`Gui::set_ui_cache`]). The render scale applies
+    /// to both images, & [`Gui::damage`] covers the changes of both.
+    pub fn draw_on_images<F>(
+        &mut self,
+        before_future: F,
+        final_image: Arc<dyn ImageViewAbstract + 'static>,
+        separate_image: Arc<dyn ImageViewAbstract + 'static>,
+    ) -> Result<Box<dyn GpuFuture>, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        if !self.renderer.has_renderpass() && !self.renderer.is_dynamic_rendering() {
+            panic!(
+                "Gui integration has been created with subpass, use `draw_on_subpass_images` \
+                 instead"
+            )
+        }
+
+        self.check_render_target_format(&final_image)?;
+        self.check_render_target_format(&separate_image)?;
+
+        self.forget_drawn_frame();
+        let (clipped_meshes, textures_delta) = self.extract_draw_data_at_frame_end();
+        let (clipped_meshes, separate_meshes) = self.layer_markers.split(clipped_meshes);
+        let (set_delta, free_delta) = Self::split_textures_delta(textures_delta);
+        let scale_factor = self.pixels_per_point();
+
+        let after_future = self.renderer.draw_on_image(
+            &clipped_meshes,
+            &set_delta,
+            scale_factor,
+            before_future,
+            final_image,
+        )?;
+        self.renderer.draw_on_image(
+            &separate_meshes,
+            &free_delta,
+            scale_factor,
+            after_future,
+            separate_image,
+        )
+    }
+
+    /// Same as `draw_on_subpass_image`, but layers set with [`Gui::set_separate_layers`] are recorded in the second
+    /// returned command buffer. Both command buffers are compatible with the subpass given at creation. Like
+    /// `draw_on_images`, these frames aren't drawn from the ui cache, & [`Gui::damage`] covers both command buffers.
+    pub fn draw_on_subpass_images(
+        &mut self,
+        image_dimensions: [u32; 2],
+    ) -> Result<(SecondaryAutoCommandBuffer, SecondaryAutoCommandBuffer), GuiError> {
+        if self.renderer.has_renderpass() {
+            panic!(
+                "Gui integration has been created with its own render pass, use `draw_on_images` \
+                 instead"
+            )
+        }
+
+        self.forget_drawn_frame();
+        let (clipped_meshes, textures_delta) = self.extract_draw_data_at_frame_end();
+        let (clipped_meshes, separate_meshes) = self.layer_markers.split(clipped_meshes);
+        let (set_delta, free_delta) = Self::split_textures_delta(textures_delta);
+        let scale_factor = self.pixels_per_point();
+
+        let command_buffer = self.renderer.draw_on_subpass_image(
+            &clipped_meshes,
+            &set_delta,
+            scale_factor,
+            image_dimensions,
+        )?;
+        let separate_command_buffer = self.renderer.draw_on_subpass_image(
+            &separate_meshes,
+            &free_delta,
+            scale_factor,
+            image_dimensions,
+        )?;
+        Ok((command_buffer, separate_command_buffer))
+    }
+
+    /// Frames drawn in layers aren't known to `draw_on_image`, so it neither skips its next frame nor draws it from
+    /// the ui cached before
+    fn forget_drawn_frame(&mut self) {
+        self.last_drawn_image = None;
+        self.last_drawn_hash = None;
+        self.renderer.invalidate_ui_cache();
+    }
+
+    /// Finishes Egui frame & returns its tessellated primitives, texture changes and screen descriptor without drawing,
+    /// so they can be fed to your own renderer. Textures registered with this integration are not usable there.
+    ///
+    /// Primitives may contain paint callbacks, e.g. those set with [`Gui::set_layer_callback`], which only work with
+    /// this crate's renderer.
+    pub fn get_draw_data(&mut self) -> DrawData {
+        let (clipped_meshes, textures_delta) = self.extract_draw_data_at_frame_end();
+        let clipped_primitives = self.remove_layer_markers(clipped_meshes);
+        let size = self.surface.window().inner_size();
+        DrawData {
+            clipped_primitives,
+            textures_delta,
+            screen_descriptor: ScreenDescriptor {
+                size_in_pixels: [size.width, size.height],
+                pixels_per_point: self.pixels_per_point(),
+            },
+        }
+    }
+
+    fn check_render_target_format(
+        &self,
+        image: &Arc<dyn ImageViewAbstract + 'static>,
+    ) -> Result<(), GuiError> {
+        self.renderer.check_render_target_format(image)
+    }
+
+    /// Format egui is drawn in, which the render target images must have
+    pub fn format(&self) -> Format {
+        self.renderer.format()
+    }
+
+    /// Splits textures delta so that textures get uploaded before the first draw & freed after the last
+    fn split_textures_delta(textures_delta: TexturesDelta) -> (TexturesDelta, TexturesDelta) {
+        let TexturesDelta { set, free } = textures_delta;
+        (TexturesDelta { set, free: vec![] }, TexturesDelta { set: Default::default(), free })
+    }
+
+    fn remove_layer_markers(
+        &self,
+        mut clipped_meshes: Vec<ClippedPrimitive>,
+    ) -> Vec<ClippedPrimitive> {
+        clipped_meshes.retain(|clipped| self.layer_markers.boundary(&clipped.primitive).is_none());
+        clipped_meshes
+    }
+
+    fn extract_draw_data_at_frame_end(&mut self) -> (Vec<ClippedPrimitive>, TexturesDelta) {
+        self.end_frame();
+        self.tessellate_frame()
+    }
+
+    /// Draw data of the frame without layer markers. With the ui cache, an idle frame is drawn from the cached ui
+    /// without being tessellated, & the first idle frame after changes is cached.
+    fn extract_cached_draw_data(
+        &mut self,
+        framebuffer_dimensions: [u32; 2],
+    ) -> Result<(Vec<ClippedPrimitive>, TexturesDelta), GuiError> {
+        if !self.renderer.has_ui_cache() {
+            let (clipped_meshes, textures_delta) = self.extract_draw_data_at_frame_end();
+            return Ok((self.remove_layer_markers(clipped_meshes), textures_delta));
+        }
+        self.end_frame();
+        let scale_factor = self.pixels_per_point();
+        let idle = !self.frame_needed
+            && self.textures_delta.is_empty()
+            && self.secondary_frames.is_empty();
+        if idle {
+            if let Some(cached) = self.renderer.cached_ui(scale_factor, framebuffer_dimensions) {
+                self.shapes.clear();
+                if let Some(damage) = &mut self.damage {
+                    damage.clear();
+                }
+                return Ok((cached, TexturesDelta::default()));
+            }
+        }
+        let (clipped_meshes, textures_delta) = self.tessellate_frame();
+        let clipped_meshes = self.remove_layer_markers(clipped_meshes);
+        if !idle {
+            self.renderer.invalidate_ui_cache();
+            return Ok((clipped_meshes, textures_delta));
+        }
+        match self.renderer.cache_ui(&clipped_meshes, scale_factor, framebuffer_dimensions) {
+            Ok(Some(cached)) => Ok((cached, textures_delta)),
+            Ok(None) => Ok((clipped_meshes, textures_delta)),
+            Err(err) => {
+                // Uploaded with the next frame that's drawn
+                self.textures_delta = textures_delta;
+                Err(err)
+            }
+        }
+    }
+
+    /// Tessellates the shapes of the ended frame & adds the primitives of secondary contexts
+    fn tessellate_frame(&mut self) -> (Vec<ClippedPrimitive>, TexturesDelta) {
+        let shapes = std::mem::take(&mut self.shapes);
+        let mut textures_delta = std::mem::take(&mut self.textures_delta);
+        let mut clipped_meshes = self.egui_ctx.tessellate(shapes);
+        if let Some(text_effect) = self.text_effect {
+            apply_text_effect(&mut clipped_meshes, text_effect);
+        }
+        for (primitives, secondary_delta) in self.secondary_frames.drain(..) {
+            clipped_meshes.extend(primitives);
+            textures_delta.append(secondary_delta);
+        }
+        self.ui_bounds = UiBounds::from_primitives(&clipped_meshes);
+        let pixels_per_point = self.pixels_per_point();
+        if let Some(damage) = &mut self.damage {
+            damage.update(
+                &clipped_meshes,
+                !textures_delta.set.is_empty(),
+                self.renderer.user_textures_version(),
+                self.egui_ctx.input().screen_rect(),
+                pixels_per_point,
+            );
+        }
+        #[cfg(feature = "remote")]
+        if let Some(remote_mirror) = &self.remote_mirror {
+            let size = self.surface.window().inner_size();
+            remote_mirror.send_frame(&clipped_meshes, &textures_delta, ScreenDescriptor {
+                size_in_pixels: [size.width, size.height],
+                pixels_per_point: self.pixels_per_point(),
+            });
+        }
+        (clipped_meshes, textures_delta)
+    }
+
+    fn end_frame(&mut self) {
+        if let Some(glyph_atlas) = &mut self.glyph_atlas {
+            glyph_atlas.flush();
+        }
+        self.layer_markers.end_frame(&self.egui_ctx);
+        let egui::FullOutput { mut platform_output, needs_repaint, textures_delta, mut shapes } =
+            self.egui_ctx.end_frame();
+        if let Some((panel_shadows, markers)) = &self.panel_shadows {
+            let screen_rect = self.egui_ctx.input().screen_rect();
+            add_panel_shadows(&mut shapes, markers, *panel_shadows, screen_rect);
+        }
+        self.repaint_requested = needs_repaint;
+        self.repaint_deadline = take_repaint_deadline(&self.egui_ctx);
+        if needs_repaint {
+            // Animating
+            self.last_activity = Instant::now();
+        }
+
+        if let Some(announcer) = &mut self.announcer {
+            let description = platform_output.events_description();
+            if !description.is_empty() {
+                announcer(&description);
+            }
+        }
+
+        if platform_output.open_url.is_none() {
+            platform_output.open_url = self.secondary_open_url.take();
+        }
+        if let (Some(url_handler), Some(open_url)) =
+            (&mut self.url_handler, &platform_output.open_url)
+        {
+            if url_handler(open_url) {
+                platform_output.open_url = None;
+            }
+        }
+
+        if let Some(clipboard) = &mut self.clipboard {
+            let copied_text = std::mem::take(&mut platform_output.copied_text);
+            if !copied_text.is_empty() {
+                clipboard.set(copied_text);
+            }
+        }
+
+        // Egui-winit places the IME in logical pixels, which are points only without a scale override or zoom
+        let text_cursor_pos = platform_output.text_cursor_pos.take();
+        self.egui_winit.handle_platform_output(
+            self.surface.window(),
+            &self.egui_ctx,
+            platform_output,
+        );
+        self.input_pixels_per_point = self.egui_ctx.pixels_per_point();
+        if let Some(pos) = text_cursor_pos {
+            let pixels_per_point = self.pixels_per_point();
+            self.surface.window().set_ime_position(winit::dpi::PhysicalPosition::new(
+                pos.x * pixels_per_point,
+                pos.y * pixels_per_point,
+            ));
+        }
+        apply_window_commands(&self.egui_ctx, self.surface.window(), self.pixels_per_point());
+        if let Some(occlusion) = &mut self.occlusion {
+            *occlusion = UiOcclusion::from_shapes(&shapes);
+        }
+        self.shapes = shapes;
+        self.textures_delta = textures_delta;
+    }
+
+    /// Registers a user image from Vulkano image view to be used by egui
+    pub fn register_user_image_view(
+        &mut self,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.renderer.register_image(image)
+    }
+
+    /// Same as `register_user_image_view`, but the image is sampled with `sampler_options`, e.g.
+    /// [`SamplerOptions::nearest`] for pixel art or [`SamplerOptions::repeat`] for tiled previews (with uvs
+    /// beyond 0..1 on your mesh or `egui::Image::uv`). [`SamplerOptions::srgb`] & [`SamplerOptions::linear`]
+    /// override the color space the view's format implies, e.g. for sRGB screenshots stored in a UNORM image.
+    pub fn register_user_image_view_with_sampler(
+        &mut self,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+        sampler_options: SamplerOptions,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.renderer.register_image_with_sampler(image, sampler_options)
+    }
+
+    /// Draws a user image with `blend_mode` instead of the one set with [`Gui::set_blend_mode`], e.g. to add a glow
+    /// texture onto the scene. `None` restores it. Not applied to YUV images.
+    pub fn set_user_image_blend_mode(
+        &mut self,
+        texture_id: egui::TextureId,
+        blend_mode: Option<BlendMode>,
+    ) -> Result<(), GuiError> {
+        self.renderer.set_image_blend_mode(texture_id, blend_mode)
+    }
+
+    /// Changes the sampler of a user image registered with a vulkano image view, e.g. to switch a zoomed in preview
+    /// to [`SamplerOptions::nearest`]. Descriptor sets are created once per texture, so this recreates just the
+    /// image's, without re-registering it.
+    pub fn set_user_image_sampler(
+        &mut self,
+        texture_id: egui::TextureId,
+        sampler_options: SamplerOptions,
+    ) -> Result<(), GuiError> {
+        self.renderer.set_image_sampler(texture_id, sampler_options)
+    }
+
+    /// Registers the region `uv_rect` (in 0..1 uvs) of `image` as a user texture of its own, e.g. a sprite of an
+    /// atlas. Uvs of its draws are relative to the region, so e.g. `egui::Image` shows just the region, without
+    /// duplicating the image. Sampling filters across the region's edges, so leave padding between sprites, and
+    /// [`SamplerOptions::repeat`] doesn't repeat the region.
+    pub fn register_user_image_view_region(
+        &mut self,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+        uv_rect: egui::Rect,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.renderer.register_image_region(image, uv_rect)
+    }
+
+    /// Registers a multi-planar YUV image, e.g. an NV12 (`G8_B8R8_2PLANE_420_UNORM`) video frame, to be shown without
+    /// converting it on the CPU. It's sampled through `conversion`, created for the image's format with
+    /// `SamplerYcbcrConversion::new`, which needs the device's `sampler_ycbcr_conversion` feature. Images are drawn
+    /// with a pipeline per conversion, so share one conversion between frames, or keep the `TextureId` & decode into
+    /// the same image. Unlike other user images, it's only known to this gui, not to the ones created with
+    /// [`Gui::new_for_window`].
+    pub fn register_user_image_ycbcr(
+        &mut self,
+        image: Arc<dyn ImageAccess>,
+        conversion: Arc<SamplerYcbcrConversion>,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.renderer.register_ycbcr_image(image, conversion)
+    }
+
+    /// Registers an image view created outside of vulkano (e.g. with ash or by a C engine) as a user texture.
+    /// Vulkano doesn't know about the image, so it's up to you to keep it in `image_layout` & finish writing to it
+    /// before egui is drawn. `release` is called once egui no longer uses the image, i.e. after it's unregistered &
+    /// the frames drawing it have finished, e.g. to destroy it. Otherwise keep it alive until then yourself.
+    /// `release` is also called when registering fails.
+    ///
+    /// # Safety
+    ///
+    /// `image_view` must be a valid 2D color image view with a float (e.g. UNORM or SRGB) format, created with
+    /// the same device as the gui & sampled usage.
+    pub unsafe fn register_raw_image_view(
+        &mut self,
+        image_view: ash::vk::ImageView,
+        image_layout: vulkano::image::ImageLayout,
+        release: Option<RawImageRelease>,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.renderer.register_raw_image(image_view, image_layout, release)
+    }
+
+    /// Same as `register_raw_image_view`, but for an image created outside of vulkano without a view. Egui creates a
+    /// 2D view of its first mip level & layer in `format` & destroys it before calling `release`.
+    ///
+    /// # Safety
+    ///
+    /// `image` must be a valid 2D color image with a float (e.g. UNORM or SRGB) `format`, created with the same
+    /// device as the gui & sampled usage. See `register_raw_image_view` for keeping it in `image_layout`.
+    pub unsafe fn register_raw_image(
+        &mut self,
+        image: ash::vk::Image,
+        format: Format,
+        image_layout: vulkano::image::ImageLayout,
+        release: Option<RawImageRelease>,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.renderer.register_raw_vk_image(image, format, image_layout, release)
+    }
+
+    /// Registers a user image under an id of your choosing, e.g. a deterministic one from your asset system that
+    /// survives ui reloads & serialization. Ids given by the other `register_user_image*` functions won't collide
+    /// with it, but they may have taken the id already, so allocate your ids before or from a range far above.
+    ///
+    /// Fails with [`GuiError::NotAUserTextureId`] if `texture_id` isn't a `TextureId::User`.
+    pub fn register_user_image_view_with_id(
+        &mut self,
+        texture_id: egui::TextureId,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+    ) -> Result<(), GuiError> {
+        // Managed ids are rejected by the renderer, rather than being in use by egui
+        if matches!(texture_id, egui::TextureId::User(_))
+            && self.renderer.is_image_registered(texture_id)
+        {
+            return Err(GuiError::TextureIdInUse(texture_id));
+        }
+        self.renderer.register_image_with_id(texture_id, image, SamplerOptions::default())
+    }
+
+    /// Registers a user image to be used by egui
+    /// - `image_file_bytes`: e.g. include_bytes!("./assets/tree.png")
+    /// - `format`: e.g. vulkano::format::Format::R8G8B8A8Unorm
+    pub fn register_user_image(
+        &mut self,
+        image_file_bytes: &[u8],
+        format: vulkano::format::Format,
+    ) -> Result<egui::TextureId, GuiError> {
+        let image = immutable_texture_from_file(self.renderer.queue(), image_file_bytes, format)?;
+        self.renderer.register_image(image)
+    }
+
+    /// Registers a user image from its texel data in `format`, e.g. R8G8B8A8_SRGB. Block-compressed formats (BC1-BC7,
+    /// ASTC, ETC2) take the blocks as is, so hundreds of thumbnails fit in a fraction of the memory, if the device
+    /// supports them.
+    pub fn register_user_image_from_bytes(
+        &mut self,
+        image_byte_data: &[u8],
+        dimensions: [u32; 2],
+        format: vulkano::format::Format,
+    ) -> Result<egui::TextureId, GuiError> {
+        check_texture_data(&self.renderer.queue(), image_byte_data, dimensions, format)?;
+        let image = immutable_texture_from_bytes(
+            self.renderer.queue(),
+            image_byte_data,
+            dimensions,
+            format,
+        )?;
+        self.renderer.register_image(image)
+    }
+
+    /// Same as `register_user_image`, but with a full mip chain generated on registration & sampled
+    /// trilinearly, so that e.g. large photos don't shimmer when shown small
+    pub fn register_user_image_with_mipmaps(
+        &mut self,
+        image_file_bytes: &[u8],
+        format: vulkano::format::Format,
+    ) -> Result<egui::TextureId, GuiError> {
+        let image = immutable_texture_from_file_with_mipmaps(
+            self.renderer.queue(),
+            image_file_bytes,
+            format,
+        )?;
+        self.renderer.register_image(image)
+    }
+
+    /// Same as `register_user_image_from_bytes`, but with a full mip chain, see
+    /// [`Gui::register_user_image_with_mipmaps`]. Block-compressed formats get a single level, as they can't be
+    /// blitted to.
+    pub fn register_user_image_from_bytes_with_mipmaps(
+        &mut self,
+        image_byte_data: &[u8],
+        dimensions: [u32; 2],
+        format: vulkano::format::Format,
+    ) -> Result<egui::TextureId, GuiError> {
+        check_texture_data(&self.renderer.queue(), image_byte_data, dimensions, format)?;
+        let image = immutable_texture_from_bytes_with_mipmaps(
+            self.renderer.queue(),
+            image_byte_data,
+            dimensions,
+            format,
+        )?;
+        self.renderer.register_image(image)
+    }
+
+    /// Re-uploads the texel data of a user image registered with a vulkano image view (including those registered
+    /// from bytes or files), e.g. for a visualization that changes every frame, keeping its `TextureId`. `byte_data`
+    /// must cover the image's first mip level in its format. The upload is waited for, so if a frame drawing the
+    /// image is still in flight it may fail with [`GuiError::Execute`]; cleanup your previous frame's future first.
+    pub fn update_user_image(
+        &mut self,
+        texture_id: egui::TextureId,
+        byte_data: &[u8],
+    ) -> Result<(), GuiError> {
+        self.renderer.invalidate_ui_cache();
+        self.renderer.update_image(texture_id, byte_data)
+    }
+
+    /// Same as `update_user_image`, but only re-uploads the region at `offset` of `extent` texels, e.g. the tiles
+    /// of a terminal that changed. `byte_data` holds the region's tightly packed rows.
+    pub fn update_user_image_region(
+        &mut self,
+        texture_id: egui::TextureId,
+        offset: [u32; 2],
+        extent: [u32; 2],
+        byte_data: &[u8],
+    ) -> Result<(), GuiError> {
+        self.renderer.invalidate_ui_cache();
+        self.renderer.update_image_region(texture_id, offset, extent, byte_data)
+    }
+
+    /// Same as `register_user_image_from_bytes`, but the upload is submitted to `transfer_queue` (e.g. of a dedicated
+    /// transfer queue family) without waiting for it, so it doesn't block the frame. The id can be used right away,
+    /// its images are left out until the upload has finished, see [`Gui::is_user_image_ready`].
+    pub fn register_user_image_from_bytes_async(
+        &mut self,
+        image_byte_data: &[u8],
+        dimensions: [u32; 2],
+        format: vulkano::format::Format,
+        transfer_queue: Arc<Queue>,
+    ) -> Result<egui::TextureId, GuiError> {
+        check_texture_data(&transfer_queue, image_byte_data, dimensions, format)?;
+        let (image, upload) = immutable_texture_from_bytes_async(
+            transfer_queue,
+            image_byte_data,
+            dimensions,
+            format,
+        )?;
+        self.renderer.register_image_uploading(image, upload)
+    }
+
+    /// Whether a user image registered with `register_user_image_from_bytes_async` has finished uploading. Other
+    /// registered images are always ready.
+    pub fn is_user_image_ready(&self, texture_id: egui::TextureId) -> bool {
+        !self.renderer.is_upload_pending(texture_id)
+    }
+
+    /// Unregisters a user image
+    pub fn unregister_user_image(&mut self, texture_id: egui::TextureId) {
+        self.renderer.invalidate_ui_cache();
+        self.renderer.unregister_image(texture_id);
+    }
+
+    /// Egui's font atlas, e.g. to draw world-space labels with the same fonts in your own renderer using uvs from
+    /// [`Gui::font_glyphs`]. `None` until the first frame has been drawn. The image is replaced when egui recreates the
+    /// atlas (e.g. when fonts or scale change), so fetch it each frame.
+    pub fn font_image(&self) -> Option<Arc<dyn ImageViewAbstract + Send + Sync + 'static>> {
+        self.renderer.texture_image(egui::TextureId::default())
+    }
+
+    /// Lays out `text` with egui's fonts & returns its glyphs with uvs in [`Gui::font_image`]. Glyphs new to the atlas
+    /// are uploaded when the next frame is drawn, so call this before drawing the frame you use them in.
+    pub fn font_glyphs(&self, text: &str, font_id: egui::FontId) -> Vec<FontGlyph> {
+        let fonts = self.egui_ctx.fonts();
+        let galley = fonts.layout_no_wrap(text.to_owned(), font_id, egui::Color32::WHITE);
+        let [width, height] = fonts.font_image_size();
+        let uv = |[x, y]: [u16; 2]| egui::pos2(x as f32 / width as f32, y as f32 / height as f32);
+        galley
+            .rows
+            .iter()
+            .flat_map(|row| row.glyphs.iter())
+            .filter(|glyph| !glyph.uv_rect.is_nothing())
+            .map(|glyph| FontGlyph {
+                chr: glyph.chr,
+                rect: egui::Rect::from_min_size(
+                    glyph.pos + glyph.uv_rect.offset,
+                    glyph.uv_rect.size,
+                ),
+                uv: egui::Rect::from_min_max(uv(glyph.uv_rect.min), uv(glyph.uv_rect.max)),
+            })
+            .collect()
+    }
+
+    /// Snapshots registered user textures (ids, sizes, images & the sampler options, regions & blend modes they're
+    /// drawn with), e.g. before hot-reloading your code so that the reloaded code can reconstruct its `TextureId`
+    /// mapping with [`Gui::restore_user_textures`]. Images stay alive as long as the snapshot does. Waits for the
+    /// uploads of images registered with `register_user_image_from_bytes_async`. Fails with
+    /// [`GuiError::UnsnapshottableTexture`] if raw or YUV images are registered, unregister them first.
+    pub fn snapshot_user_textures(&self) -> Result<UserTextureSnapshot, GuiError> {
+        Ok(UserTextureSnapshot { entries: self.renderer.snapshot_user_images()? })
+    }
+
+    /// Replaces registered user textures with those of `snapshot`, keeping their ids & options. `reload` is called
+    /// for each entry and may return a new image (e.g. reloaded from disk) to register instead of the snapshotted
+    /// one. Ids registered after restoring don't collide with the snapshot's.
+    pub fn restore_user_textures(
+        &mut self,
+        snapshot: UserTextureSnapshot,
+        mut reload: impl FnMut(&UserTextureEntry) -> Option<Arc<dyn ImageViewAbstract + Send + Sync>>,
+    ) -> Result<(), GuiError> {
+        for (texture_id, _) in self.renderer.user_images() {
+            self.renderer.unregister_image(texture_id);
+        }
+        for entry in snapshot.entries {
+            let image = reload(&entry).unwrap_or_else(|| entry.image.clone());
+            self.renderer.restore_user_image(&entry, image)?;
+        }
+        Ok(())
+    }
+
+    /// Copies `rect` (in points) of the image last drawn with `draw_on_image` to the clipboard as an image. Give
+    /// it the future returned by the draw, before presenting the image, & present with the returned future.
+    /// Waits for the readback to finish, so don't call this every frame.
+    /// The render target must have `transfer_src` usage & a format with 8 bits per channel RGBA or BGRA.
+    #[cfg(feature = "clipboard_image")]
+    pub fn copy_region_to_clipboard<F>(
+        &mut self,
+        before_future: F,
+        rect: egui::Rect,
+    ) -> Result<Box<dyn GpuFuture>, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        let image = self.last_frame_image.as_ref().ok_or(GuiError::NothingDrawn)?.image().clone();
+        let [width, height] = image.dimensions().width_height();
+        let scale_factor = self.pixels_per_point();
+        let to_pixels =
+            |value: f32, max: u32| (value * scale_factor).round().clamp(0.0, max as f32) as u32;
+        let min = [to_pixels(rect.min.x, width), to_pixels(rect.min.y, height)];
+        let max = [to_pixels(rect.max.x, width), to_pixels(rect.max.y, height)];
+        if max[0] <= min[0] || max[1] <= min[1] {
+            return Err(GuiError::EmptyRegion);
+        }
+        let extent = [max[0] - min[0], max[1] - min[1]];
+        let bytes = self.renderer.read_rgba_region(before_future, image, min, extent)?;
+        arboard::Clipboard::new()?.set_image(arboard::ImageData {
+            width: extent[0] as usize,
+            height: extent[1] as usize,
+            bytes: bytes.into(),
+        })?;
+        Ok(vulkano::sync::now(self.renderer.queue().device().clone()).boxed())
+    }
+
+    /// Reads back the image last drawn with `draw_on_image` as RGBA, e.g. to save a screenshot or compare the ui
+    /// against a reference image in tests. Give it the future returned by the draw, before presenting the image, &
+    /// present with the returned future. Waits for the copy to finish. The image must have `transfer_src` usage &
+    /// an 8 bit per channel RGBA or BGRA format.
+    pub fn read_pixels<F>(
+        &self,
+        before_future: F,
+    ) -> Result<(image::RgbaImage, Box<dyn GpuFuture>), GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        let image = self.last_drawn_image.as_ref().ok_or(GuiError::NothingDrawn)?.image().clone();
+        let [width, height] = image.dimensions().width_height();
+        let bytes =
+            self.renderer.read_rgba_region(before_future, image, [0, 0], [width, height])?;
+        let pixels = image::RgbaImage::from_raw(width, height, bytes).unwrap();
+        Ok((pixels, vulkano::sync::now(self.renderer.queue().device().clone()).boxed()))
+    }
+
+    /// Caches runs of egui primitives (e.g. panels or windows) that stay unchanged between frames into textures
+    /// & draws each of them as a single textured quad. Useful for dashboards where only a small part of the UI animates.
+    /// Only primitives drawn with egui's own textures are cached, because user images may change under the hood.
+    pub fn set_static_cache(&mut self, enabled: bool) -> Result<(), GuiError> {
+        self.renderer.set_static_cache(enabled)
+    }
+
+    /// Caches the whole ui into a texture once egui needs no repaint, & draws idle frames (see
+    /// [`Gui::should_render_this_frame`]) with that texture instead of tessellating & drawing the ui again, e.g. for
+    /// mostly static tool UIs at high refresh rates. Unlike [`Gui::set_skip_idle_frames`], it works with swapchain
+    /// images & overlays. Layers drawn separately with `draw_on_images` aren't cached, nor are frames with paint
+    /// callbacks, YUV images or user images with their own blend mode. Disabled by default.
+    ///
+    /// Changes egui doesn't know about aren't seen, so call `request_repaint` on the context when the shown state
+    /// changes without input, or [`Gui::invalidate_ui_cache`] when an image you draw into yourself does.
+    pub fn set_ui_cache(&mut self, enabled: bool) -> Result<(), GuiError> {
+        self.renderer.set_ui_cache(enabled)
+    }
+
+    /// Draws the ui at `render_scale` times the resolution of the image given to `draw_on_image` & scales it onto the
+    /// image, e.g. 2 for crisper text on low-DPI monitors (glyphs are rasterized at the higher resolution) or 0.5 to
+    /// cut fill rate on weak GPUs. The layout in points doesn't change. Frames with paint callbacks, YUV images or user
+    /// images with their own blend mode are drawn directly, as are draws on subpasses. Takes effect from the next
+    /// frame, panics if `render_scale` isn't positive. 1 by default.
+    pub fn set_render_scale(&mut self, render_scale: f32) -> Result<(), GuiError> {
+        self.renderer.set_render_scale(render_scale)
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.renderer.render_scale()
+    }
+
+    /// Drops the cached ui texture, so that the next frame is drawn anew
+    pub fn invalidate_ui_cache(&mut self) {
+        self.renderer.invalidate_ui_cache();
+    }
+
+    /// Bounds of what egui drew in the last drawn frame (in points, multiply by [`Gui::pixels_per_point`] for
+    /// pixels), e.g. for damage aware presentation or to skip re-rendering 3D content under unchanged ui
+    pub fn ui_bounds(&self) -> &UiBounds {
+        &self.ui_bounds
+    }
+
+    /// Enables or disables tracking of screen areas covered by opaque ui each frame, see [`Gui::occlusion`]
+    pub fn set_occlusion_tracking(&mut self, enabled: bool) {
+        self.occlusion =
+            if enabled { Some(self.occlusion.take().unwrap_or_default()) } else { None };
+    }
+
+    /// Screen areas covered by opaque ui in the last finished frame, if tracking is enabled with
+    /// [`Gui::set_occlusion_tracking`]. Use e.g. to stencil out or skip expensive shading work under full screen menus.
+    pub fn occlusion(&self) -> Option<&UiOcclusion> {
+        self.occlusion.as_ref()
+    }
+
+    /// Enables or disables tracking of screen areas where the ui changed each frame, see [`Gui::damage`]
+    pub fn set_damage_tracking(&mut self, enabled: bool) {
+        self.damage = if enabled { Some(self.damage.take().unwrap_or_default()) } else { None };
+    }
+
+    /// Screen areas where the ui changed in the last finished frame, if tracking is enabled with
+    /// [`Gui::set_damage_tracking`]. Use e.g. to redraw only those pixels on low-power targets, or give
+    /// [`DamageRegions::present_region`](crate::DamageRegions::present_region) to `present_incremental`.
+    pub fn damage(&self) -> Option<&DamageRegions> {
+        self.damage.as_ref()
+    }
+
+    /// Sets how often the ui should be drawn. Can be changed at runtime, e.g. to switch to
+    /// [`RenderMode::Reactive`] when your app has nothing else animating. Defaults to [`RenderMode::Continuous`].
+    ///
+    /// The integration doesn't drive your event loop, use [`Gui::needs_repaint`] & [`Gui::control_flow`] to do so:
+    /// request a redraw on `MainEventsCleared` if `needs_repaint` & set `control_flow` after handling each event.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Whether the ui should be drawn. Always `true` in [`RenderMode::Continuous`], otherwise `true` if input has
+    /// arrived since the last frame or egui requested a repaint (e.g. animations). With [`Gui::set_max_fps`],
+    /// `false` until the next frame is due.
+    pub fn needs_repaint(&self) -> bool {
+        self.wants_repaint()
+            && self.next_frame_deadline().map_or(true, |deadline| Instant::now() >= deadline)
+    }
+
+    fn wants_repaint(&self) -> bool {
+        match self.render_mode {
+            RenderMode::Continuous => true,
+            RenderMode::Reactive => self.should_render_this_frame(),
+        }
+    }
+
+    /// Caps how often the ui is drawn, e.g. for tools that shouldn't run at monitor refresh rate when only a blinking
+    /// cursor is animating. Respected by [`Gui::needs_repaint`], [`Gui::control_flow`] & [`Gui::wait_for_next_frame`].
+    /// `None` (default) removes the cap.
+    pub fn set_max_fps(&mut self, max_fps: Option<f32>) {
+        self.frame_interval = max_fps.map(|fps| {
+            assert!(fps > 0.0, "Max fps must be positive, got {}", fps);
+            Duration::from_secs_f32(1.0 / fps)
+        });
+    }
+
+    /// When the next frame is due with [`Gui::set_max_fps`]
+    pub fn next_frame_deadline(&self) -> Option<Instant> {
+        self.frame_interval.map(|interval| self.last_frame_start + interval)
+    }
+
+    /// Sleeps until the next frame is due with [`Gui::set_max_fps`], for loops not using [`Gui::control_flow`]
+    pub fn wait_for_next_frame(&self) {
+        if let Some(deadline) = self.next_frame_deadline() {
+            let now = Instant::now();
+            if deadline > now {
+                std::thread::sleep(deadline - now);
+            }
+        }
+    }
+
+    /// Whether input or animations have been active since the last frame, or a repaint requested with
+    /// [`request_repaint_after`](crate::request_repaint_after) is due, regardless of render mode.
+    /// Utility apps can check this to skip their frame (and rendering of the rest of their scene) when untouched.
+    pub fn should_render_this_frame(&self) -> bool {
+        self.repaint_requested
+            || self.repaint_deadline.map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
+    /// Time since the last input or animated frame
+    pub fn idle_duration(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    /// When enabled, `draw_on_image` skips drawing frames that draw the same as last time (compared by their
+    /// tessellated primitives, so changes without input are drawn) if the target image is the one drawn last time &
+    /// still has its contents, i.e. the integration owns a non-overlay render pass and the image isn't e.g. a
+    /// swapchain image that has been presented since. Frames with paint callbacks are always drawn, as are frames
+    /// after user images were registered, unregistered or updated. Images you draw into yourself aren't seen, so
+    /// disable it while they change. Disabled by default.
+    pub fn set_skip_idle_frames(&mut self, enabled: bool) {
+        self.skip_idle_frames = enabled;
+    }
+
+    /// Control flow for your event loop with the current render mode: `Poll` while repaints are needed, otherwise
+    /// `Wait` until next input or a repaint requested with [`request_repaint_after`](crate::request_repaint_after).
+    /// Repaints requested from other threads need [`Gui::set_repaint_proxy`] to wake up the loop.
+    /// With [`Gui::set_max_fps`], waits until the next frame is due instead of polling.
+    pub fn control_flow(&self) -> ControlFlow {
+        if !self.wants_repaint() {
+            return match self.repaint_deadline {
+                Some(deadline) => ControlFlow::WaitUntil(deadline),
+                None => ControlFlow::Wait,
+            };
+        }
+        match self.next_frame_deadline() {
+            Some(deadline) if deadline > Instant::now() => ControlFlow::WaitUntil(deadline),
+            _ => ControlFlow::Poll,
+        }
+    }
+
+    /// Releases resources tied to the surface & swapchain (references to your swapchain images) and transient GPU
+    /// memory (mesh buffers, cache & render scale textures), e.g. when a laptop goes to sleep or on Android's `Suspended`
+    /// event, after which the surface must be dropped. Egui's state and all textures, including user textures, are
+    /// kept. Don't draw until [`Gui::resume`].
+    pub fn suspend(&mut self) {
+        self.renderer.suspend();
+        self.last_drawn_image = None;
+        #[cfg(feature = "clipboard_image")]
+        {
+            self.last_frame_image = None;
+        }
+    }
+
+    /// Restores what [`Gui::suspend`] released. `surface` replaces the one given at creation, as the window's surface
+    /// is recreated on resume on Android. The swapchain format requirements of [`Gui::new`] still apply.
+    pub fn resume(&mut self, surface: Arc<Surface<Window>>) {
+        // Released buffers & textures are created again by the next draw
+        // Scale factor may differ on the new window
+        let max_texture_side =
+            self.renderer.queue().device().physical_device().properties().max_image_dimension2_d
+                as usize;
+        self.egui_winit = egui_winit::State::new(max_texture_side, surface.window());
+        self.input_pixels_per_point = egui_winit::native_pixels_per_point(surface.window());
+        self.surface = surface;
+        self.repaint_requested = true;
+    }
+
+    /// Whether the device is a `VK_KHR_portability_subset` implementation (e.g. MoltenVK on macOS), which vulkano
+    /// enables automatically. Check `supported_features()` of the physical device for what the subset lacks
+    /// if you draw your own commands e.g. with paint callbacks.
+    pub fn is_portability_subset(&self) -> bool {
+        self.renderer.queue().device().enabled_extensions().khr_portability_subset
+    }
+
+    /// Describes the device & whether it supports what egui needs (formats of the render target & textures),
+    /// e.g. to include in bug reports when pipeline creation fails
+    pub fn diagnose_device(&self) -> String {
+        crate::diagnostics::device_report(
+            self.renderer.queue().device().physical_device(),
+            self.renderer.format(),
+        )
+    }
+
+    /// Reports the renderer's own allocations (buffers, textures, descriptor sets & samplers), e.g. to see where the
+    /// integration's memory footprint goes
+    pub fn allocation_stats(&self) -> AllocationStats {
+        self.renderer.allocation_stats()
+    }
+
+    /// Makes egui's repaint requests (e.g. `ctx.request_repaint()` called from a background thread) wake up the event
+    /// loop by sending `event` through `proxy`, so that they aren't missed with `ControlFlow::Wait`.
+    /// Request a redraw of the window when you receive `Event::UserEvent(event)`.
+    pub fn set_repaint_proxy<T: Clone + Send + 'static>(&self, proxy: EventLoopProxy<T>, event: T) {
+        // Proxy isn't `Sync` on all platforms
+        let proxy = Mutex::new((proxy, event));
+        self.egui_ctx.set_request_repaint_callback(move || {
+            let (proxy, event) = &*proxy.lock().unwrap();
+            // Event loop may have been closed already, nothing to wake up then
+            let _ = proxy.send_event(event.clone());
+        });
     }
 
     /// Access egui's context (which can be used to e.g. set fonts, visuals etc)
@@ -0,0 +1,113 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::{any::Any, sync::Arc};
+
+use egui::{epaint::Primitive, ClippedPrimitive, LayerId, PaintCallback, PaintCallbackInfo};
+
+/// Egui flattens its layers into one list of shapes at the end of the frame. To still know which primitives
+/// belong to which layer, we paint an empty callback at the beginning & at the end of each layer we're interested in.
+#[derive(Default)]
+pub(crate) struct LayerMarkers {
+    markers: Vec<LayerMarker>,
+}
+
+struct LayerMarker {
+    layer_id: LayerId,
+    start: PaintCallback,
+    end: PaintCallback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LayerBoundary {
+    Start(LayerId),
+    End(LayerId),
+}
+
+fn marker_callback() -> PaintCallback {
+    // Each `Arc` is a separate allocation, so the pointer identifies the marker
+    PaintCallback {
+        rect: egui::Rect::NOTHING,
+        callback: Arc::new(|_: &PaintCallbackInfo, _: &mut dyn Any| {}),
+    }
+}
+
+impl LayerMarkers {
+    pub fn new(layer_ids: impl IntoIterator<Item = LayerId>) -> LayerMarkers {
+        LayerMarkers {
+            markers: layer_ids
+                .into_iter()
+                .map(|layer_id| LayerMarker {
+                    layer_id,
+                    start: marker_callback(),
+                    end: marker_callback(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn layer_ids(&self) -> impl Iterator<Item = LayerId> + '_ {
+        self.markers.iter().map(|marker| marker.layer_id)
+    }
+
+    /// Must be called right after egui's frame has begun, while the layers are still empty
+    pub fn begin_frame(&self, ctx: &egui::Context) {
+        for marker in &self.markers {
+            ctx.layer_painter(marker.layer_id).add(marker.start.clone());
+        }
+    }
+
+    /// Must be called right before ending egui's frame
+    pub fn end_frame(&self, ctx: &egui::Context) {
+        for marker in &self.markers {
+            ctx.layer_painter(marker.layer_id).add(marker.end.clone());
+        }
+    }
+
+    /// Returns which boundary the primitive marks, if it's one of our markers
+    pub fn boundary(&self, primitive: &Primitive) -> Option<LayerBoundary> {
+        let callback = match primitive {
+            Primitive::Callback(callback) => callback,
+            Primitive::Mesh(_) => return None,
+        };
+        self.markers.iter().find_map(|marker| {
+            if Arc::ptr_eq(&marker.start.callback, &callback.callback) {
+                Some(LayerBoundary::Start(marker.layer_id))
+            } else if Arc::ptr_eq(&marker.end.callback, &callback.callback) {
+                Some(LayerBoundary::End(marker.layer_id))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Whether the callback is the marker at the start of a layer
+    pub fn starts_layer(&self, callback: &PaintCallback) -> bool {
+        self.markers.iter().any(|marker| Arc::ptr_eq(&marker.start.callback, &callback.callback))
+    }
+
+    /// Splits primitives into those outside & those inside marked layers, removing the markers
+    pub fn split(
+        &self,
+        clipped_primitives: Vec<ClippedPrimitive>,
+    ) -> (Vec<ClippedPrimitive>, Vec<ClippedPrimitive>) {
+        let mut outside = vec![];
+        let mut inside = vec![];
+        let mut open_layers = 0usize;
+        for clipped in clipped_primitives {
+            match self.boundary(&clipped.primitive) {
+                Some(LayerBoundary::Start(_)) => open_layers += 1,
+                Some(LayerBoundary::End(_)) => open_layers = open_layers.saturating_sub(1),
+                None if open_layers > 0 => inside.push(clipped),
+                None => outside.push(clipped),
+            }
+        }
+        (outside, inside)
+    }
+}
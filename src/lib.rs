@@ -7,10 +7,74 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+mod callback;
+#[cfg(feature = "frame_capture")]
+mod capture;
+mod clipboard;
+mod damage;
+mod diagnostics;
+mod error;
+#[cfg(unix)]
+mod external_image;
+mod headless;
 mod integration;
+mod layers;
+mod occlusion;
+mod overlay;
+mod raw_image;
+#[cfg(feature = "remote")]
+mod remote;
 mod renderer;
+mod repaint;
+mod secondary;
+#[cfg(feature = "shader_hot_reload")]
+mod shader_reload;
+mod shadows;
+mod shaping;
+#[cfg(feature = "system_fonts")]
+mod system_fonts;
+mod text_effect;
 mod utils;
+mod window_command;
+mod world_space;
+#[cfg(feature = "xr")]
+mod xr;
 
+pub use callback::{keyed_paint_callback, paint_callback, CallbackRenderer};
+#[cfg(feature = "frame_capture")]
+pub use capture::{
+    CapturedFrame, FrameDumper, FrameRecorder, FrameSink, GifSink, PngSequenceSink, RawFrameSink,
+};
+pub use clipboard::ClipboardProvider;
+pub use damage::DamageRegions;
 pub use egui;
+pub use error::GuiError;
+#[cfg(unix)]
+pub use external_image::ExternalImage;
+pub use headless::{DrawnTexture, HeadlessGui};
 pub use integration::*;
-pub use utils::{immutable_texture_from_bytes, immutable_texture_from_file};
+pub use occlusion::UiOcclusion;
+pub use overlay::{anchor_window, overlay_window_builder};
+pub use raw_image::RawImageRelease;
+#[cfg(feature = "remote")]
+pub use remote::{RemoteFrame, RemoteMesh, RemoteMirror, RemoteViewer};
+pub use renderer::{
+    orthographic_projection, AllocationStats, BlendMode, ColorSpace, ColorVisionDeficiency,
+    CustomShaders, DescriptorAllocator, OutputTransfer, SamplerOptions, SubpassInheritance,
+};
+pub use repaint::request_repaint_after;
+pub use secondary::SecondaryContext;
+pub use shadows::PanelShadows;
+pub use shaping::{ShapedGlyph, TextDirection, TextShaper};
+#[cfg(feature = "system_fonts")]
+pub use system_fonts::SystemFonts;
+pub use text_effect::TextEffect;
+pub use utils::{
+    immutable_texture_from_bytes, immutable_texture_from_bytes_with_mipmaps,
+    immutable_texture_from_dds, immutable_texture_from_file,
+    immutable_texture_from_file_with_mipmaps, immutable_texture_from_ktx2,
+};
+pub use window_command::{send_window_command, WindowCommand};
+pub use world_space::WorldQuad;
+#[cfg(feature = "xr")]
+pub use xr::{XrEye, XrGui, XrQuadLayer};
@@ -0,0 +1,94 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use egui::{
+    epaint::{CircleShape, ClippedShape, RectShape},
+    Rect, Shape, Vec2,
+};
+
+/// Screen areas (in points) covered by opaque ui in a frame, see [`Gui::set_occlusion_tracking`](crate::Gui::set_occlusion_tracking).
+///
+/// Occlusion is conservative: only opaque filled rectangles & circles (e.g. window & panel backgrounds) are considered,
+/// with rounded corners & anti-aliased edges left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UiOcclusion {
+    pub rects: Vec<Rect>,
+}
+
+/// Edges are feathered by egui's anti-aliasing, so they are not opaque
+const FEATHERING_MARGIN: f32 = 1.0;
+
+impl UiOcclusion {
+    pub(crate) fn from_shapes(shapes: &[ClippedShape]) -> UiOcclusion {
+        let mut rects = vec![];
+        for ClippedShape(clip_rect, shape) in shapes {
+            Self::add_shape(&mut rects, *clip_rect, shape);
+        }
+        UiOcclusion { rects }
+    }
+
+    fn add_shape(rects: &mut Vec<Rect>, clip_rect: Rect, shape: &Shape) {
+        let mut add_rect = |rect: Rect| {
+            let rect = rect.intersect(clip_rect);
+            if rect.is_positive() {
+                rects.push(rect);
+            }
+        };
+        match shape {
+            Shape::Vec(shapes) => {
+                for shape in shapes {
+                    Self::add_shape(rects, clip_rect, shape);
+                }
+            }
+            Shape::Rect(RectShape { rect, rounding, fill, .. }) if fill.a() == 255 => {
+                let rect = rect.shrink(FEATHERING_MARGIN);
+                let radius = rounding.nw.max(rounding.ne).max(rounding.sw).max(rounding.se);
+                if radius > 0.0 {
+                    // Leave out the rounded corners
+                    add_rect(rect.shrink2(Vec2::new(radius, 0.0)));
+                    add_rect(rect.shrink2(Vec2::new(0.0, radius)));
+                } else {
+                    add_rect(rect);
+                }
+            }
+            Shape::Circle(CircleShape { center, radius, fill, .. }) if fill.a() == 255 => {
+                // Square inscribed in the circle
+                let half_side = (radius - FEATHERING_MARGIN) * std::f32::consts::FRAC_1_SQRT_2;
+                if half_side > 0.0 {
+                    add_rect(Rect::from_center_size(*center, Vec2::splat(2.0 * half_side)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether `rect` (in points) is fully covered by opaque ui
+    pub fn covers(&self, rect: Rect) -> bool {
+        self.rects.iter().any(|occluder| occluder.contains_rect(rect))
+    }
+
+    /// Rasterizes occlusion into a `width` x `height` single channel mask stretched over a screen of `screen_size`
+    /// points. Texels fully covered by opaque ui are 255, others 0. Rows are top to bottom.
+    pub fn mask(&self, screen_size: Vec2, width: u32, height: u32) -> Vec<u8> {
+        let mut mask = vec![0; width as usize * height as usize];
+        let texel_size = Vec2::new(screen_size.x / width as f32, screen_size.y / height as f32);
+        for y in 0..height {
+            for x in 0..width {
+                let texel = Rect::from_min_size(
+                    egui::pos2(x as f32 * texel_size.x, y as f32 * texel_size.y),
+                    texel_size,
+                );
+                if self.covers(texel) {
+                    mask[(y * width + x) as usize] = 255;
+                }
+            }
+        }
+        mask
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use egui::Align2;
+use winit::{
+    dpi::PhysicalPosition,
+    window::{Window, WindowBuilder},
+};
+
+/// Window builder for HUD overlays & status bars: undecorated, transparent & above normal windows. Create the gui
+/// for it with `is_overlay = true` & clear the image to transparent, and present with a composite alpha mode the
+/// surface supports other than `Opaque`.
+///
+/// Winit has no wlr-layer-shell support, so on Wayland the compositor treats it as a normal window: it may not
+/// stay on top & can't be anchored.
+pub fn overlay_window_builder(title: &str) -> WindowBuilder {
+    WindowBuilder::new()
+        .with_title(title)
+        .with_decorations(false)
+        .with_transparent(true)
+        .with_always_on_top(true)
+        .with_resizable(false)
+}
+
+/// Moves the window to the `anchor` edge or corner of its monitor, `margin` physical pixels away from it. Does
+/// nothing on Wayland, where windows can't position themselves.
+pub fn anchor_window(window: &Window, anchor: Align2, margin: u32) {
+    let monitor = match window.current_monitor() {
+        Some(monitor) => monitor,
+        None => return,
+    };
+    let monitor_pos = monitor.position();
+    let monitor_size = monitor.size();
+    let window_size = window.outer_size();
+    let offset = |align: egui::Align, monitor: u32, window: u32| -> i32 {
+        let free = monitor.saturating_sub(window) as i32;
+        let margin = (margin as i32).min(free / 2);
+        match align {
+            egui::Align::Min => margin,
+            egui::Align::Center => free / 2,
+            egui::Align::Max => free - margin,
+        }
+    };
+    window.set_outer_position(PhysicalPosition::new(
+        monitor_pos.x + offset(anchor.x(), monitor_size.width, window_size.width),
+        monitor_pos.y + offset(anchor.y(), monitor_size.height, window_size.height),
+    ));
+}
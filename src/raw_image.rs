@@ -0,0 +1,197 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+
+use vulkano::{
+    descriptor_set::{
+        layout::{DescriptorSetLayout, DescriptorType},
+        pool::{
+            DescriptorPoolAllocError, DescriptorSetAllocateInfo, UnsafeDescriptorPool,
+            UnsafeDescriptorPoolCreateInfo,
+        },
+        sys::UnsafeDescriptorSet,
+        DescriptorSet, DescriptorSetResources, WriteDescriptorSet,
+    },
+    device::{Device, DeviceOwned, Queue},
+    format::Format,
+    image::{ImageLayout, ImageViewAbstract},
+    sampler::Sampler,
+    OomError, VulkanObject,
+};
+
+use crate::{utils::immutable_texture_from_bytes, GuiError};
+
+/// Called once egui no longer uses a raw image, see [`Gui::register_raw_image_view`](crate::Gui::register_raw_image_view)
+pub type RawImageRelease = Box<dyn FnOnce() + Send + Sync>;
+
+/// Creates a view of the whole first mip level & layer of a 2D color image created outside of vulkano
+///
+/// # Safety
+///
+/// `image` must be a valid 2D color image of `device` with a float `format` & sampled usage.
+pub(crate) unsafe fn create_raw_image_view(
+    device: &Arc<Device>,
+    image: ash::vk::Image,
+    format: Format,
+) -> Result<ash::vk::ImageView, GuiError> {
+    let create_info = ash::vk::ImageViewCreateInfo {
+        image,
+        view_type: ash::vk::ImageViewType::TYPE_2D,
+        format: format.into(),
+        subresource_range: ash::vk::ImageSubresourceRange {
+            aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+        ..Default::default()
+    };
+    let mut image_view = ash::vk::ImageView::null();
+    (device.fns().v1_0.create_image_view)(
+        device.internal_object(),
+        &create_info,
+        std::ptr::null(),
+        &mut image_view,
+    )
+    .result()
+    .map_err(GuiError::RawImageView)?;
+    Ok(image_view)
+}
+
+/// # Safety
+///
+/// `image_view` must have been created with `create_raw_image_view` & no longer be in use
+pub(crate) unsafe fn destroy_raw_image_view(device: &Arc<Device>, image_view: ash::vk::ImageView) {
+    (device.fns().v1_0.destroy_image_view)(device.internal_object(), image_view, std::ptr::null());
+}
+
+/// Descriptor set sampling an image view created outside of vulkano. Vulkano only knows about a placeholder
+/// image, which keeps its validation happy, so synchronizing the actual image is up to its owner.
+pub(crate) struct RawImageDescriptorSet {
+    // Destroying the pool frees the set
+    _pool: UnsafeDescriptorPool,
+    inner: UnsafeDescriptorSet,
+    layout: Arc<DescriptorSetLayout>,
+    resources: DescriptorSetResources,
+    release: Option<RawImageRelease>,
+}
+
+impl RawImageDescriptorSet {
+    /// # Safety
+    ///
+    /// `image_view` must be a valid 2D color image view of the queue's device with a float format, sampled in
+    /// `image_layout`. `release` is called right away on errors, as egui won't use the image.
+    pub unsafe fn new(
+        queue: Arc<Queue>,
+        layout: Arc<DescriptorSetLayout>,
+        sampler: Arc<Sampler>,
+        image_view: ash::vk::ImageView,
+        image_layout: ImageLayout,
+        release: Option<RawImageRelease>,
+    ) -> Result<RawImageDescriptorSet, GuiError> {
+        match Self::allocate(queue, &layout, sampler, image_view, image_layout) {
+            Ok((pool, inner, resources)) => {
+                Ok(RawImageDescriptorSet { _pool: pool, inner, layout, resources, release })
+            }
+            Err(err) => {
+                if let Some(release) = release {
+                    release();
+                }
+                Err(err)
+            }
+        }
+    }
+
+    unsafe fn allocate(
+        queue: Arc<Queue>,
+        layout: &Arc<DescriptorSetLayout>,
+        sampler: Arc<Sampler>,
+        image_view: ash::vk::ImageView,
+        image_layout: ImageLayout,
+    ) -> Result<(UnsafeDescriptorPool, UnsafeDescriptorSet, DescriptorSetResources), GuiError> {
+        let device = queue.device().clone();
+        let mut pool = UnsafeDescriptorPool::new(device.clone(), UnsafeDescriptorPoolCreateInfo {
+            max_sets: 1,
+            pool_sizes: std::iter::once((DescriptorType::CombinedImageSampler, 1)).collect(),
+            ..Default::default()
+        })
+        .map_err(|err| {
+            GuiError::RawImageDescriptorSet(match err {
+                OomError::OutOfHostMemory => DescriptorPoolAllocError::OutOfHostMemory,
+                OomError::OutOfDeviceMemory => DescriptorPoolAllocError::OutOfDeviceMemory,
+            })
+        })?;
+        let inner = pool
+            .allocate_descriptor_sets([DescriptorSetAllocateInfo {
+                layout,
+                variable_descriptor_count: 0,
+            }])
+            .map_err(GuiError::RawImageDescriptorSet)?
+            .next()
+            .unwrap();
+
+        let image_info = ash::vk::DescriptorImageInfo {
+            sampler: sampler.internal_object(),
+            image_view,
+            image_layout: image_layout.into(),
+        };
+        let write = ash::vk::WriteDescriptorSet {
+            dst_set: inner.internal_object(),
+            dst_binding: 0,
+            descriptor_count: 1,
+            descriptor_type: ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            p_image_info: &image_info,
+            ..Default::default()
+        };
+        (device.fns().v1_0.update_descriptor_sets)(
+            device.internal_object(),
+            1,
+            &write,
+            0,
+            std::ptr::null(),
+        );
+
+        let placeholder: Arc<dyn ImageViewAbstract> =
+            immutable_texture_from_bytes(queue, &[0; 4], [1, 1], Format::R8G8B8A8_UNORM)?;
+        let mut resources = DescriptorSetResources::new(layout, 0);
+        resources.update(&WriteDescriptorSet::image_view_sampler(0, placeholder, sampler));
+        Ok((pool, inner, resources))
+    }
+}
+
+unsafe impl DescriptorSet for RawImageDescriptorSet {
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        &self.inner
+    }
+
+    fn layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.layout
+    }
+
+    fn resources(&self) -> &DescriptorSetResources {
+        &self.resources
+    }
+}
+
+unsafe impl DeviceOwned for RawImageDescriptorSet {
+    fn device(&self) -> &Arc<Device> {
+        self.layout.device()
+    }
+}
+
+impl Drop for RawImageDescriptorSet {
+    fn drop(&mut self) {
+        // Command buffers keep the set alive until they're done
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+};
+
+use egui::{epaint::Primitive, ClippedPrimitive, Mesh, Rect, TexturesDelta};
+use serde::{Deserialize, Serialize};
+
+use crate::ScreenDescriptor;
+
+/// Mesh of a mirrored frame with the clip rect it's drawn with
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RemoteMesh {
+    pub clip_rect: Rect,
+    pub mesh: Mesh,
+}
+
+/// Draw data of a frame sent to a [`RemoteViewer`]. Paint callbacks aren't mirrored.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RemoteFrame {
+    pub meshes: Vec<RemoteMesh>,
+    pub textures_delta: TexturesDelta,
+    pub screen_descriptor: ScreenDescriptor,
+}
+
+/// Shared by both ends: messages are newline delimited JSON
+fn spawn_writer<T: Serialize + Send + 'static>(
+    mut writer: impl Write + Send + 'static,
+    connected: Arc<AtomicBool>,
+) -> mpsc::Sender<T> {
+    let (sender, receiver) = mpsc::channel::<T>();
+    std::thread::spawn(move || {
+        for message in receiver {
+            let written = serde_json::to_writer(&mut writer, &message)
+                .map_err(std::io::Error::from)
+                .and_then(|_| writer.write_all(b"\n"))
+                .and_then(|_| writer.flush());
+            if written.is_err() {
+                break;
+            }
+        }
+        connected.store(false, Ordering::Relaxed);
+    });
+    sender
+}
+
+fn spawn_reader<T: for<'de> Deserialize<'de> + Send + 'static>(
+    reader: impl Read + Send + 'static,
+    connected: Arc<AtomicBool>,
+) -> mpsc::Receiver<T> {
+    let (sender, receiver) = mpsc::channel::<T>();
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let message = match line.ok().and_then(|line| serde_json::from_str(&line).ok()) {
+                Some(message) => message,
+                None => break,
+            };
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+        connected.store(false, Ordering::Relaxed);
+    });
+    receiver
+}
+
+/// Streams frames drawn by the integration to a [`RemoteViewer`] & receives input events back, e.g. to debug a ui
+/// running on a headless or embedded device. Install with [`Gui::set_remote_mirror`](crate::Gui::set_remote_mirror).
+///
+/// Messages are newline delimited JSON sent & received on background threads.
+pub struct RemoteMirror {
+    frames: mpsc::Sender<RemoteFrame>,
+    events: mpsc::Receiver<egui::Event>,
+    connected: Arc<AtomicBool>,
+}
+
+impl RemoteMirror {
+    pub fn new(
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> RemoteMirror {
+        let connected = Arc::new(AtomicBool::new(true));
+        RemoteMirror {
+            frames: spawn_writer(writer, connected.clone()),
+            events: spawn_reader(reader, connected.clone()),
+            connected,
+        }
+    }
+
+    /// Mirrors over a connected TCP stream, e.g. accepted from a `TcpListener`
+    pub fn from_tcp(stream: TcpStream) -> std::io::Result<RemoteMirror> {
+        Ok(RemoteMirror::new(stream.try_clone()?, stream))
+    }
+
+    /// Whether the viewer is still connected
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn send_frame(
+        &self,
+        clipped_primitives: &[ClippedPrimitive],
+        textures_delta: &TexturesDelta,
+        screen_descriptor: ScreenDescriptor,
+    ) {
+        let meshes = clipped_primitives
+            .iter()
+            .filter_map(|ClippedPrimitive { clip_rect, primitive }| match primitive {
+                Primitive::Mesh(mesh) => {
+                    Some(RemoteMesh { clip_rect: *clip_rect, mesh: mesh.clone() })
+                }
+                Primitive::Callback(_) => None,
+            })
+            .collect();
+        // Writer thread stops on disconnect
+        let _ = self.frames.send(RemoteFrame {
+            meshes,
+            textures_delta: textures_delta.clone(),
+            screen_descriptor,
+        });
+    }
+
+    /// Input events received from the viewer since the last call
+    pub(crate) fn take_events(&self) -> Vec<egui::Event> {
+        self.events.try_iter().collect()
+    }
+}
+
+/// Viewer end of a [`RemoteMirror`]: receives frames to draw & sends input events back
+pub struct RemoteViewer {
+    frames: mpsc::Receiver<RemoteFrame>,
+    events: mpsc::Sender<egui::Event>,
+    connected: Arc<AtomicBool>,
+}
+
+impl RemoteViewer {
+    pub fn new(
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> RemoteViewer {
+        let connected = Arc::new(AtomicBool::new(true));
+        RemoteViewer {
+            frames: spawn_reader(reader, connected.clone()),
+            events: spawn_writer(writer, connected.clone()),
+            connected,
+        }
+    }
+
+    /// Connects to a mirror listening at `addr`
+    pub fn connect(addr: impl std::net::ToSocketAddrs) -> std::io::Result<RemoteViewer> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(RemoteViewer::new(stream.try_clone()?, stream))
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Frames received since the last call. Apply the textures deltas of all of them in order, even if you only
+    /// draw the last one.
+    pub fn take_frames(&self) -> Vec<RemoteFrame> {
+        self.frames.try_iter().collect()
+    }
+
+    /// Sends an input event to the mirrored ui, e.g. converted from your own window's events
+    pub fn send_event(&self, event: egui::Event) {
+        // Writer thread stops on disconnect
+        let _ = self.events.send(event);
+    }
+}
@@ -7,74 +7,756 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
-use std::{convert::TryFrom, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
 
 use ahash::AHashMap;
 use bytemuck::{Pod, Zeroable};
 use egui::{
     epaint::{Mesh, Primitive},
-    ClippedPrimitive, Rect, TexturesDelta,
+    ClippedPrimitive, PaintCallbackInfo, Rect, TexturesDelta,
 };
 use vulkano::{
-    buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, TypedBufferAccess},
+    buffer::{
+        cpu_pool::CpuBufferPoolChunk, BufferAccess, BufferUsage, CpuAccessibleBuffer,
+        CpuBufferPool, DeviceLocalBuffer, TypedBufferAccess,
+    },
     command_buffer::{
-        AutoCommandBufferBuilder, BlitImageInfo, CommandBufferInheritanceInfo, CommandBufferUsage,
-        CopyBufferToImageInfo, ImageBlit, PrimaryAutoCommandBuffer, PrimaryCommandBuffer,
-        RenderPassBeginInfo, SecondaryAutoCommandBuffer, SubpassContents,
+        AutoCommandBufferBuilder, BufferImageCopy, CommandBufferInheritanceInfo,
+        CommandBufferInheritanceRenderPassInfo, CommandBufferInheritanceRenderingInfo,
+        CommandBufferUsage, CopyBufferInfo, CopyBufferToImageInfo, PrimaryAutoCommandBuffer,
+        PrimaryCommandBuffer, RenderPassBeginInfo, RenderingAttachmentInfo, RenderingInfo,
+        SecondaryAutoCommandBuffer, SubpassContents,
+    },
+    descriptor_set::{
+        layout::DescriptorSetLayout, pool::DescriptorPool, DescriptorSet, DescriptorSetWithOffsets,
+        PersistentDescriptorSet, WriteDescriptorSet,
     },
-    descriptor_set::{layout::DescriptorSetLayout, PersistentDescriptorSet, WriteDescriptorSet},
     device::{Device, Queue},
-    format::Format,
+    format::{Format, NumericType},
     image::{
-        view::ImageView, ImageAccess, ImageLayout, ImageUsage, ImageViewAbstract, ImmutableImage,
+        view::{ImageView, ImageViewCreateInfo},
+        AttachmentImage, ImageAccess, ImageLayout, ImageUsage, ImageViewAbstract, ImmutableImage,
+        SampleCount,
     },
+    memory::pool::StdMemoryPool,
     pipeline::{
+        cache::PipelineCache,
         graphics::{
-            color_blend::{AttachmentBlend, BlendFactor, ColorBlendState},
+            color_blend::{AttachmentBlend, BlendFactor, ColorBlendState, ColorComponents},
+            depth_stencil::DepthStencilState,
             input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
             rasterization::{CullMode as CullModeEnum, RasterizationState},
+            render_pass::{PipelineRenderPassType, PipelineRenderingCreateInfo},
             vertex_input::BuffersDefinition,
             viewport::{Scissor, Viewport, ViewportState},
         },
         GraphicsPipeline, Pipeline, PipelineBindPoint,
     },
-    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
-    sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode},
-    sync::GpuFuture,
+    query::{QueryControlFlags, QueryPipelineStatisticFlags},
+    render_pass::{
+        AttachmentDescription, AttachmentReference, Framebuffer, FramebufferCreateInfo, LoadOp,
+        RenderPass, RenderPassCreateInfo, RenderPassCreationError, StoreOp, Subpass,
+        SubpassDescription,
+    },
+    sampler::{
+        ycbcr::SamplerYcbcrConversion, Filter, Sampler, SamplerAddressMode, SamplerCreateInfo,
+        SamplerMipmapMode,
+    },
+    shader::{ShaderModule, SpecializationConstants, SpecializationMapEntry},
+    sync::{FenceSignalFuture, FlushError, GpuFuture},
     DeviceSize,
 };
 
-const VERTICES_PER_QUAD: DeviceSize = 4;
-const VERTEX_BUFFER_SIZE: DeviceSize = 1024 * 1024 * VERTICES_PER_QUAD;
-const INDEX_BUFFER_SIZE: DeviceSize = 1024 * 1024 * 2;
+use crate::{
+    callback::{CallbackContext, CallbackRenderer, CallbackRenderers},
+    diagnostics::{pipeline_error_message, TEXTURE_FORMAT},
+    error::GuiError,
+    integration::UserTextureEntry,
+    raw_image::{
+        create_raw_image_view, destroy_raw_image_view, RawImageDescriptorSet, RawImageRelease,
+    },
+};
+
+/// Draws a frame's command buffers may still be executing for after it, see
+/// [`Gui::set_frames_in_flight`](crate::Gui::set_frames_in_flight)
+const DEFAULT_FRAMES_IN_FLIGHT: u32 = 2;
 
-/// Should match vertex definition of egui (except color is `[f32; 4]`)
+/// Primitive runs that stay unchanged for this many frames get cached into a texture
+const STATIC_CACHE_MIN_FRAMES: u32 = 3;
+const STATIC_CACHE_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+/// Should match vertex definition of egui (except color is `[f32; 4]`), with edge coverage for analytic
+/// anti-aliasing
 #[repr(C)]
 #[derive(Default, Debug, Clone, Copy, Zeroable, Pod)]
 pub struct EguiVertex {
     pub position: [f32; 2],
     pub tex_coords: [f32; 2],
     pub color: [f32; 4],
+    /// 0 on the outer edge of egui's feathering, 1 elsewhere
+    pub coverage: f32,
+}
+vulkano::impl_vertex!(EguiVertex, position, tex_coords, color, coverage);
+
+impl EguiVertex {
+    fn from_egui(v: &egui::epaint::Vertex, offset: egui::Vec2) -> EguiVertex {
+        EguiVertex {
+            position: [v.pos.x - offset.x, v.pos.y - offset.y],
+            tex_coords: [v.uv.x, v.uv.y],
+            color: [
+                v.color.r() as f32 / 255.0,
+                v.color.g() as f32 / 255.0,
+                v.color.b() as f32 / 255.0,
+                v.color.a() as f32 / 255.0,
+            ],
+            coverage: 1.0,
+        }
+    }
+}
+
+/// Turns egui's feathering into a coverage ramp for analytic anti-aliasing: the transparent outer vertices of
+/// feathered (untextured) edges take the color of their triangle's inner vertex & zero coverage, from which the
+/// fragment shader computes a pixel wide edge however the mesh is scaled
+fn feathering_to_coverage(vertices: &mut [EguiVertex], indices: &[u32]) {
+    let white_uv = [egui::epaint::WHITE_UV.x, egui::epaint::WHITE_UV.y];
+    for triangle in indices.chunks_exact(3) {
+        let inner = triangle
+            .iter()
+            .map(|&i| vertices[i as usize])
+            .find(|v| v.tex_coords == white_uv && v.color[3] > 0.0 && v.coverage > 0.0);
+        if let Some(inner) = inner {
+            for &i in triangle {
+                let v = &mut vertices[i as usize];
+                if v.tex_coords == white_uv && v.color == [0.0; 4] {
+                    v.color = inner.color;
+                    v.coverage = 0.0;
+                }
+            }
+        }
+    }
+}
+
+/// Orthographic projection (column major) egui is drawn with by default, mapping points on a screen of
+/// `screen_size` points to Vulkan's clip space
+pub fn orthographic_projection(screen_size: [f32; 2]) -> [[f32; 4]; 4] {
+    [
+        [2.0 / screen_size[0], 0.0, 0.0, 0.0],
+        [0.0, 2.0 / screen_size[1], 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [-1.0, -1.0, 0.0, 1.0],
+    ]
+}
+
+/// Color vision deficiencies egui's output can be filtered to simulate, see
+/// [`Gui::set_color_vision_simulation`](crate::Gui::set_color_vision_simulation)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorVisionDeficiency {
+    /// No red cones
+    Protanopia,
+    /// No green cones
+    Deuteranopia,
+    /// No blue cones
+    Tritanopia,
+    /// No color vision at all
+    Achromatopsia,
+}
+
+impl ColorVisionDeficiency {
+    /// Simulation matrix (row major) in linear RGB, after Machado et al. 2009 at full severity
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            ColorVisionDeficiency::Protanopia => {
+                [[0.152286, 1.052583, -0.204868], [0.114503, 0.786281, 0.099216], [
+                    -0.003882, -0.048116, 1.051998,
+                ]]
+            }
+            ColorVisionDeficiency::Deuteranopia => {
+                [[0.367322, 0.860646, -0.227968], [0.280085, 0.672501, 0.047413], [
+                    -0.011820, 0.042940, 0.968881,
+                ]]
+            }
+            ColorVisionDeficiency::Tritanopia => {
+                [[1.255528, -0.076749, -0.178779], [-0.078411, 0.930809, 0.147602], [
+                    0.004733, 0.691367, 0.303900,
+                ]]
+            }
+            ColorVisionDeficiency::Achromatopsia => [[0.2126, 0.7152, 0.0722]; 3],
+        }
+    }
+}
+
+/// Column major color matrix the fragment shader applies, identity unless simulating a deficiency
+fn color_matrix(deficiency: Option<ColorVisionDeficiency>) -> [f32; 9] {
+    let mut color_matrix = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    if let Some(deficiency) = deficiency {
+        let matrix = deficiency.matrix();
+        for (row, values) in matrix.iter().enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                color_matrix[column * 3 + row] = *value;
+            }
+        }
+    }
+    color_matrix
+}
+
+/// Inheritance of the secondary command buffers egui is recorded in when drawing on your subpass
+#[derive(Clone, Default)]
+pub struct SubpassInheritance {
+    /// Subpass the command buffer will be executed in, if it's not the one egui was created with.
+    /// Must be compatible with it.
+    pub subpass: Option<Subpass>,
+    /// Framebuffer the command buffer will be executed with, if known. May improve performance on some drivers.
+    pub framebuffer: Option<Arc<Framebuffer>>,
+    /// Allows executing the command buffer while an occlusion query with these flags is active
+    pub occlusion_query: Option<QueryControlFlags>,
+    /// Pipeline statistics that may be queried while executing the command buffer
+    pub query_statistics_flags: QueryPipelineStatisticFlags,
+}
+
+/// What the values of a user image encode, see [`SamplerOptions::color_space`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Linear values, e.g. of a linear render target
+    Linear,
+    /// sRGB encoded values, e.g. of a screenshot or an image file
+    Srgb,
+}
+
+/// `uv_rect` push constant of textures that aren't atlas regions
+const FULL_UV_RECT: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+/// Values of the low bits of the `texture_params` push constant
+const COLOR_CONVERSION_NONE: u32 = 0;
+const COLOR_CONVERSION_DECODE_SRGB: u32 = 1;
+const COLOR_CONVERSION_ENCODE_SRGB: u32 = 2;
+const COLOR_CONVERSION_MASK: u32 = 3;
+/// The bindless texture index is stored in the bits of `texture_params` above the color conversion
+const TEXTURE_INDEX_SHIFT: u32 = 2;
+
+/// Upper limit of the bindless texture array, lowered to the device's descriptor limits
+const MAX_BINDLESS_TEXTURES: u32 = 4096;
+
+/// SPIR-V modules replacing egui's shaders, see [`Gui::set_custom_shaders`](crate::Gui::set_custom_shaders)
+#[derive(Clone, Default)]
+pub struct CustomShaders {
+    pub vertex: Option<Arc<ShaderModule>>,
+    pub fragment: Option<Arc<ShaderModule>>,
+}
+
+/// What egui's pipelines are created with besides their state
+#[derive(Clone, Default)]
+struct PipelineConfig {
+    shaders: CustomShaders,
+    cache: Option<Arc<PipelineCache>>,
+    output_transfer: OutputTransfer,
+    blend_mode: BlendMode,
+    /// Disabled by default, which is also valid in subpasses with depth or stencil attachments
+    depth_stencil: DepthStencilState,
+    color_vision_simulation: Option<ColorVisionDeficiency>,
+}
+
+impl PipelineConfig {
+    /// UNORM targets store the output as is, so it's encoded in the shader to look like on sRGB targets
+    fn for_format(format: Format) -> PipelineConfig {
+        let output_transfer = if format.type_color() == Some(NumericType::UNORM) {
+            OutputTransfer::Srgb
+        } else {
+            OutputTransfer::Linear
+        };
+        PipelineConfig { output_transfer, ..Default::default() }
+    }
+
+    fn fragment_constants(&self) -> FragmentConstants {
+        let (hdr_transfer, output_scale) = match self.output_transfer {
+            OutputTransfer::Linear | OutputTransfer::Srgb => (0, 1.0),
+            OutputTransfer::ScRgb { sdr_white_nits } => (1, sdr_white_nits / SCRGB_WHITE_NITS),
+            OutputTransfer::Pq { sdr_white_nits } => (2, sdr_white_nits / PQ_MAX_NITS),
+        };
+        FragmentConstants {
+            encode_srgb: (self.output_transfer == OutputTransfer::Srgb) as u32,
+            output_scale,
+            straight_alpha: (self.blend_mode == BlendMode::Straight) as u32,
+            hdr_transfer,
+            color_matrix: color_matrix(self.color_vision_simulation),
+        }
+    }
+}
+
+/// Nits of scRGB's 1.0
+const SCRGB_WHITE_NITS: f32 = 80.0;
+/// Nits of PQ's 1.0
+const PQ_MAX_NITS: f32 = 10000.0;
+
+/// How egui's fragment shader encodes its linear output for the target, see
+/// [`Gui::set_output_transfer`](crate::Gui::set_output_transfer)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputTransfer {
+    /// Written as is, for sRGB targets which encode it themselves, or linear targets
+    Linear,
+    /// Encoded to sRGB, for UNORM targets
+    Srgb,
+    /// Scaled for scRGB targets (e.g. `R16G16B16A16_SFLOAT` swapchains of the `ExtendedSrgbLinear` color space),
+    /// where 1.0 is 80 nits. Ui white is drawn at `sdr_white_nits`.
+    ScRgb { sdr_white_nits: f32 },
+    /// Converted to BT.2020 primaries & PQ encoded, for HDR10 targets (e.g. `A2B10G10R10_UNORM_PACK32` swapchains
+    /// of the `Hdr10St2084` color space). Ui white is drawn at `sdr_white_nits`.
+    Pq { sdr_white_nits: f32 },
+}
+
+impl Default for OutputTransfer {
+    fn default() -> Self {
+        OutputTransfer::Linear
+    }
+}
+
+/// How egui's output is blended onto the target, see [`Gui::set_blend_mode`](crate::Gui::set_blend_mode)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Egui's premultiplied colors over the target
+    Premultiplied,
+    /// Colors unpremultiplied in the shader & multiplied by alpha when blending, e.g. to match a compositor or
+    /// custom shaders expecting straight alpha
+    Straight,
+    /// Colors added to the target, keeping its alpha, e.g. for glows or HUD elements over a scene
+    Additive,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Premultiplied
+    }
+}
+
+impl BlendMode {
+    fn attachment_blend(self) -> AttachmentBlend {
+        let mut blend = AttachmentBlend::alpha();
+        match self {
+            BlendMode::Premultiplied => blend.color_source = BlendFactor::One,
+            BlendMode::Straight => {}
+            BlendMode::Additive => {
+                blend.color_source = BlendFactor::One;
+                blend.color_destination = BlendFactor::One;
+                blend.alpha_source = BlendFactor::Zero;
+                blend.alpha_destination = BlendFactor::One;
+            }
+        }
+        blend
+    }
+}
+
+/// Specialization constants of egui's fragment shaders
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct FragmentConstants {
+    encode_srgb: u32,
+    output_scale: f32,
+    straight_alpha: u32,
+    hdr_transfer: u32,
+    /// Constants 4 to 12
+    color_matrix: [f32; 9],
+}
+
+unsafe impl SpecializationConstants for FragmentConstants {
+    fn descriptors() -> &'static [SpecializationMapEntry] {
+        static DESCRIPTORS: [SpecializationMapEntry; 13] = [
+            SpecializationMapEntry { constant_id: 0, offset: 0, size: 4 },
+            SpecializationMapEntry { constant_id: 1, offset: 4, size: 4 },
+            SpecializationMapEntry { constant_id: 2, offset: 8, size: 4 },
+            SpecializationMapEntry { constant_id: 3, offset: 12, size: 4 },
+            SpecializationMapEntry { constant_id: 4, offset: 16, size: 4 },
+            SpecializationMapEntry { constant_id: 5, offset: 20, size: 4 },
+            SpecializationMapEntry { constant_id: 6, offset: 24, size: 4 },
+            SpecializationMapEntry { constant_id: 7, offset: 28, size: 4 },
+            SpecializationMapEntry { constant_id: 8, offset: 32, size: 4 },
+            SpecializationMapEntry { constant_id: 9, offset: 36, size: 4 },
+            SpecializationMapEntry { constant_id: 10, offset: 40, size: 4 },
+            SpecializationMapEntry { constant_id: 11, offset: 44, size: 4 },
+            SpecializationMapEntry { constant_id: 12, offset: 48, size: 4 },
+        ];
+        &DESCRIPTORS
+    }
+}
+
+/// How the texture of egui's fragment shader is bound
+enum TextureBinding {
+    /// One combined image sampler per descriptor set
+    Single,
+    /// Baked into the set layout, e.g. a YUV conversion's sampler, which can't be written to descriptor sets
+    ImmutableSampler(Arc<Sampler>),
+    /// Array of up to this many textures, indexed by a push constant
+    Bindless(u32),
+}
+
+/// Textures in one descriptor array, see [`Gui::set_bindless`](crate::Gui::set_bindless)
+struct Bindless {
+    pipeline: Arc<GraphicsPipeline>,
+    max_textures: u32,
+    desc_set: Option<Arc<dyn DescriptorSet>>,
+    /// Array index of each texture in `desc_set`
+    indices: Arc<AHashMap<egui::TextureId, u32>>,
+    /// Generation of the user textures `desc_set` was written at
+    user_generation: u64,
+    /// Set when the renderer's own textures change
+    stale: bool,
+}
+
+/// How a user image is sampled, see [`Gui::register_user_image_view_with_sampler`](crate::Gui::register_user_image_view_with_sampler).
+/// The default filters linearly & clamps to the edge, like egui's own textures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerOptions {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+    pub address_mode: SamplerAddressMode,
+    /// What the image's values encode, if not what its view's format says. Samples of sRGB formats are decoded
+    /// & others are shown as is, so e.g. sRGB screenshots in a UNORM image would look washed out. Other
+    /// combinations are converted in the shader. `None` follows the format.
+    pub color_space: Option<ColorSpace>,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        SamplerOptions {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_mode: SamplerMipmapMode::Linear,
+            address_mode: SamplerAddressMode::ClampToEdge,
+            color_space: None,
+        }
+    }
+}
+
+impl SamplerOptions {
+    /// Nearest filtering, e.g. for pixel art
+    pub fn nearest() -> SamplerOptions {
+        SamplerOptions {
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            mipmap_mode: SamplerMipmapMode::Nearest,
+            ..Default::default()
+        }
+    }
+
+    /// Linear filtering with the image repeated outside of its uv range, e.g. for tiled previews
+    pub fn repeat() -> SamplerOptions {
+        SamplerOptions { address_mode: SamplerAddressMode::Repeat, ..Default::default() }
+    }
+
+    /// Sampled as is, for linear data whatever the view's format
+    pub fn linear() -> SamplerOptions {
+        SamplerOptions { color_space: Some(ColorSpace::Linear), ..Default::default() }
+    }
+
+    /// Decoded from sRGB, for sRGB encoded data whatever the view's format
+    pub fn srgb() -> SamplerOptions {
+        SamplerOptions { color_space: Some(ColorSpace::Srgb), ..Default::default() }
+    }
+
+    /// Conversion the fragment shader applies to the samples of an image viewed in `format`
+    fn color_conversion(self, format: Option<Format>) -> u32 {
+        let is_srgb_format =
+            format.and_then(|format| format.type_color()) == Some(NumericType::SRGB);
+        match self.color_space {
+            Some(ColorSpace::Srgb) if !is_srgb_format => COLOR_CONVERSION_DECODE_SRGB,
+            Some(ColorSpace::Linear) if is_srgb_format => COLOR_CONVERSION_ENCODE_SRGB,
+            _ => COLOR_CONVERSION_NONE,
+        }
+    }
+
+    fn create_info(self) -> SamplerCreateInfo {
+        SamplerCreateInfo {
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            address_mode: [self.address_mode; 3],
+            mipmap_mode: self.mipmap_mode,
+            ..Default::default()
+        }
+    }
+}
+
+/// Allocates the descriptor sets of egui's textures, see [`Gui::set_descriptor_pool`](crate::Gui::set_descriptor_pool).
+/// Implemented for vulkano's descriptor pools, e.g. your engine's own `DescriptorPool` implementation.
+pub trait DescriptorAllocator: Send {
+    fn allocate(
+        &mut self,
+        layout: Arc<DescriptorSetLayout>,
+        write: WriteDescriptorSet,
+    ) -> Arc<dyn DescriptorSet>;
+}
+
+impl<P> DescriptorAllocator for P
+where
+    P: DescriptorPool + Send,
+    P::Alloc: 'static,
+{
+    fn allocate(
+        &mut self,
+        layout: Arc<DescriptorSetLayout>,
+        write: WriteDescriptorSet,
+    ) -> Arc<dyn DescriptorSet> {
+        PersistentDescriptorSet::new_with_pool(layout, 0, self, [write]).unwrap()
+    }
+}
+
+/// Pipeline compiled on a background thread, so that creating the renderer doesn't wait for the driver to
+/// compile shaders. Using it blocks until it's done. If compiling fails (e.g. the subpass being incompatible), its
+/// error is returned & the pipeline is compiled again on the next use.
+struct AsyncPipeline {
+    compiling: Option<JoinHandle<Result<Arc<GraphicsPipeline>, GuiError>>>,
+    pipeline: Option<Arc<GraphicsPipeline>>,
+    create_info: PipelineCreateInfo,
+}
+
+/// What egui's pipeline is created with
+#[derive(Clone)]
+struct PipelineCreateInfo {
+    gfx_queue: Arc<Queue>,
+    render_pass: PipelineRenderPassType,
+    sample_count: SampleCount,
+    color_write_mask: ColorComponents,
+    config: PipelineConfig,
+}
+
+impl PipelineCreateInfo {
+    fn create(self) -> Result<Arc<GraphicsPipeline>, GuiError> {
+        Renderer::create_pipeline(
+            self.gfx_queue,
+            self.render_pass,
+            self.sample_count,
+            self.color_write_mask,
+            self.config,
+        )
+    }
+}
+
+impl AsyncPipeline {
+    fn spawn(create_info: PipelineCreateInfo) -> AsyncPipeline {
+        let thread_create_info = create_info.clone();
+        let compiling = std::thread::Builder::new()
+            .name("egui pipeline compilation".to_string())
+            .spawn(move || thread_create_info.create())
+            .expect("Failed to spawn egui pipeline compilation thread");
+        AsyncPipeline { compiling: Some(compiling), pipeline: None, create_info }
+    }
+
+    /// Already created, e.g. to check that custom shaders fit
+    fn ready(pipeline: Arc<GraphicsPipeline>, create_info: PipelineCreateInfo) -> AsyncPipeline {
+        AsyncPipeline { compiling: None, pipeline: Some(pipeline), create_info }
+    }
+
+    fn get(&mut self) -> Result<Arc<GraphicsPipeline>, GuiError> {
+        if let Some(pipeline) = &self.pipeline {
+            return Ok(pipeline.clone());
+        }
+        let pipeline = match self.compiling.take() {
+            Some(compiling) => compiling.join().unwrap_or_else(|_| {
+                Err(GuiError::PipelineCreation(
+                    "egui pipeline compilation thread panicked".to_string(),
+                ))
+            }),
+            // Compiling failed before
+            None => self.create_info.clone().create(),
+        }?;
+        self.pipeline = Some(pipeline.clone());
+        Ok(pipeline)
+    }
+}
+
+/// Caches runs of primitives (consecutive meshes sharing a clip rect, e.g. a panel or a window) that haven't
+/// changed between frames into textures, which are then drawn as a single quad
+struct StaticCache {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    hasher: ahash::RandomState,
+    entries: AHashMap<u64, StaticCacheEntry>,
+    /// Incremented on each draw. Entries unused for two draws are dropped, so that drawing a frame in two parts
+    /// (see `Gui::draw_on_images`) doesn't evict the other part's entries.
+    generation: u64,
+}
+
+/// Whole ui rendered into a texture while it stays unchanged, see [`Gui::set_ui_cache`](crate::Gui::set_ui_cache)
+struct UiCache {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    /// Cached texture & the rect (in points) it's drawn at
+    texture: Option<(egui::TextureId, Rect)>,
+    /// Scale factor & framebuffer dimensions the texture was rendered for
+    rendered_for: (f32, [u32; 2]),
+}
+
+/// Image the ui is drawn into at another resolution than the render target, see
+/// [`Gui::set_render_scale`](crate::Gui::set_render_scale)
+struct ScaledTarget {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    render_scale: f32,
+    /// Reused while the target's dimensions stay the same
+    image: Option<(Arc<ImageView<AttachmentImage>>, egui::TextureId)>,
+}
+
+#[derive(Default)]
+struct StaticCacheEntry {
+    unchanged_frames: u32,
+    texture: Option<(egui::TextureId, Rect)>,
+    last_used: u64,
+}
+
+/// Renderer's own allocations, see [`Gui::allocation_stats`](crate::Gui::allocation_stats). Sizes are in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    /// Vertex ring shared by all meshes & device-local vertex buffers, grown on demand
+    pub vertex_buffer_size: DeviceSize,
+    /// Index ring shared by all meshes & device-local index buffers, grown on demand
+    pub index_buffer_size: DeviceSize,
+    /// Textures managed by egui (e.g. the font atlas)
+    pub managed_textures: usize,
+    pub managed_texture_size: DeviceSize,
+    /// Textures rendered by the static cache, see [`Gui::set_static_cache`](crate::Gui::set_static_cache)
+    pub cached_textures: usize,
+    pub cached_texture_size: DeviceSize,
+    /// Images registered by you, their memory is not included as the renderer doesn't own it
+    pub user_textures: usize,
+    /// One per texture
+    pub descriptor_sets: usize,
+    pub samplers: usize,
+    /// Staging buffers created for texture uploads during the last draw, freed once the upload has finished. Egui's
+    /// texture deltas share one chunk of a staging ring, whose memory is reused by later frames.
+    pub staging_buffers: usize,
+    pub staging_buffer_size: DeviceSize,
+}
+
+/// Images registered as user textures. Shared by the renderers of guis created with
+/// [`Gui::new_for_window`](crate::Gui::new_for_window), so that a `TextureId` shows the same image in each window.
+/// Descriptor sets can be bound in any of their pipelines, as the pipelines' layouts are identical.
+#[derive(Default)]
+pub(crate) struct UserTextures {
+    /// Created once per texture, & only recreated when its sampler changes
+    desc_sets: AHashMap<egui::TextureId, Arc<dyn DescriptorSet>>,
+    images: AHashMap<egui::TextureId, Arc<dyn ImageViewAbstract + Send + Sync + 'static>>,
+    /// Conversions of images sampled in another color space than their format's
+    color_conversions: AHashMap<egui::TextureId, u32>,
+    /// Uv rects of atlas regions, as min & size
+    uv_rects: AHashMap<egui::TextureId, [f32; 4]>,
+    /// Samplers of images registered with other than the default options
+    samplers: AHashMap<egui::TextureId, Arc<Sampler>>,
+    /// Those options, including the color space, for snapshotting the images
+    sampler_options: AHashMap<egui::TextureId, SamplerOptions>,
+    /// Images drawn with another blend mode than the renderer's
+    blend_modes: AHashMap<egui::TextureId, BlendMode>,
+    /// Incremented when images are registered, unregistered or change samplers or blend modes
+    generation: u64,
+    /// Incremented when the texel data of images is updated, see `Renderer::user_textures_version`
+    updates: u64,
+    /// Uploads submitted to another queue, their textures aren't drawn until they've finished
+    pending_uploads: AHashMap<egui::TextureId, FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>>,
+    /// Also gives the ids of static cache textures, which are kept per renderer
+    next_id: u64,
+}
+
+/// Device-local vertex & index buffers the host-visible ones are copied to, see
+/// [`Gui::set_device_local_geometry`](crate::Gui::set_device_local_geometry)
+type DeviceBuffers = (Arc<DeviceLocalBuffer<[EguiVertex]>>, Arc<DeviceLocalBuffer<[u32]>>);
+
+/// Pipelines of YUV images & bindless textures, see `Renderer::create_derived_pipelines`
+type DerivedPipelines = (Vec<Arc<GraphicsPipeline>>, Option<Arc<GraphicsPipeline>>);
+
+/// Geometry written by a draw, to be copied to the device-local buffers before it's executed
+struct GeometryUpload {
+    vertices: Arc<CpuBufferPoolChunk<EguiVertex, Arc<StdMemoryPool>>>,
+    indices: Arc<CpuBufferPoolChunk<u32, Arc<StdMemoryPool>>>,
+    device_buffers: DeviceBuffers,
+}
+
+/// Pipeline drawing the YUV images sampled through one conversion, whose sampler is baked into the descriptor set
+/// layout
+struct YcbcrPipeline {
+    conversion: Arc<SamplerYcbcrConversion>,
+    sampler: Arc<Sampler>,
+    pipeline: Arc<GraphicsPipeline>,
+}
+
+impl UserTextures {
+    /// Whether the texture's upload has finished, dropping its future once it has
+    fn upload_finished(&mut self, texture_id: egui::TextureId) -> bool {
+        match self.pending_uploads.get(&texture_id) {
+            Some(future) if !future.is_signaled().unwrap_or(false) => false,
+            Some(_) => {
+                self.pending_uploads.remove(&texture_id);
+                self.generation += 1;
+                true
+            }
+            None => true,
+        }
+    }
 }
-vulkano::impl_vertex!(EguiVertex, position, tex_coords, color);
 
 pub struct Renderer {
     gfx_queue: Arc<Queue>,
     render_pass: Option<Arc<RenderPass>>,
     is_overlay: bool,
+    /// Color the image is cleared to when not an overlay
+    clear_color: [f32; 4],
+    /// Views of the owned render pass with multiview, 0 without
+    view_mask: u32,
 
-    #[allow(unused)]
     format: vulkano::format::Format,
     sampler: Arc<Sampler>,
+    /// Samplers of user images registered with other than the default options
+    samplers: AHashMap<SamplerOptions, Arc<Sampler>>,
 
-    vertex_buffer: Arc<CpuAccessibleBuffer<[EguiVertex]>>,
-    index_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
-    pipeline: Arc<GraphicsPipeline>,
-    subpass: Subpass,
+    /// Persistently mapped rings the geometry of each draw is written to at once. Their memory is reused once the
+    /// draws reading it have finished.
+    vertex_pool: CpuBufferPool<EguiVertex>,
+    index_pool: CpuBufferPool<u32>,
+    frames_in_flight: u32,
+    /// Meshes are drawn from these when geometry is device-local
+    device_buffers: Option<DeviceBuffers>,
+    /// Device-local buffers of the other frames in flight, cycled through so that a draw doesn't write the buffers
+    /// of the previous ones
+    spare_device_buffers: VecDeque<DeviceBuffers>,
+    device_local_geometry: bool,
+    geometry_upload: Option<GeometryUpload>,
+    pipeline: AsyncPipeline,
+    /// Subpass egui is drawn in, or the attachments of `begin_rendering` with dynamic rendering
+    pipeline_render_pass: PipelineRenderPassType,
+    sample_count: SampleCount,
 
-    texture_desc_sets: AHashMap<egui::TextureId, Arc<PersistentDescriptorSet>>,
+    /// Managed & static cache textures
+    texture_desc_sets: AHashMap<egui::TextureId, Arc<dyn DescriptorSet>>,
     texture_images: AHashMap<egui::TextureId, Arc<dyn ImageViewAbstract + Send + Sync + 'static>>,
-    next_native_tex_id: u64,
+    user_textures: Arc<Mutex<UserTextures>>,
+    ycbcr_pipelines: Vec<YcbcrPipeline>,
+    /// Index into `ycbcr_pipelines` of each YUV image, kept with the renderer's own textures like their pipelines
+    ycbcr_textures: AHashMap<egui::TextureId, usize>,
+    bindless: Option<Bindless>,
+    /// Pipelines of the blend modes user images are drawn with, created before each draw
+    blend_pipelines: AHashMap<BlendMode, Arc<GraphicsPipeline>>,
+    /// `None` allocates from the device's standard pool
+    descriptor_pool: Option<Arc<Mutex<dyn DescriptorAllocator>>>,
+
+    static_cache: Option<StaticCache>,
+    ui_cache: Option<UiCache>,
+    scaled_target: Option<ScaledTarget>,
+    /// Draws of cached runs into their textures, submitted before the commands of the next draw sampling them
+    offscreen_draws: Vec<PrimaryAutoCommandBuffer>,
+    callback_renderers: Arc<CallbackRenderers>,
+    subpass_inheritance: SubpassInheritance,
+    projection: Option<[[f32; 4]; 4]>,
+    /// Ring the texture deltas of each frame are staged in
+    staging_pool: CpuBufferPool<u8>,
+    staging_buffers: usize,
+    staging_buffer_size: DeviceSize,
+    pixel_snapping: bool,
+    analytic_anti_aliasing: bool,
+    color_write_mask: ColorComponents,
+    pipeline_config: PipelineConfig,
 }
 
 impl Renderer {
@@ -82,31 +764,70 @@ impl Renderer {
         gfx_queue: Arc<Queue>,
         final_output_format: Format,
         subpass: Subpass,
-    ) -> Renderer {
-        let (vertex_buffer, index_buffer) = Self::create_buffers(gfx_queue.device().clone());
-        let pipeline = Self::create_pipeline(gfx_queue.clone(), subpass.clone());
-        let sampler = Sampler::new(gfx_queue.device().clone(), SamplerCreateInfo {
-            mag_filter: Filter::Linear,
-            min_filter: Filter::Linear,
-            address_mode: [SamplerAddressMode::ClampToEdge; 3],
-            mipmap_mode: SamplerMipmapMode::Linear,
-            ..Default::default()
-        })
-        .unwrap();
-        Renderer {
+    ) -> Result<Renderer, GuiError> {
+        Self::assert_push_constants_fit(gfx_queue.device());
+        if subpass.subpass_desc().color_attachments.len() > 1
+            && !gfx_queue.device().enabled_features().independent_blend
+        {
+            return Err(GuiError::IndependentBlendNotEnabled);
+        }
+        let (vertex_pool, index_pool) = Self::create_geometry_pools(gfx_queue.device().clone());
+        // Rasterization samples must match the subpass' attachments, e.g. when drawing inside an MSAA pass
+        let sample_count = subpass.num_samples().unwrap_or(SampleCount::Sample1);
+        let pipeline_config = PipelineConfig::for_format(final_output_format);
+        let pipeline = AsyncPipeline::spawn(PipelineCreateInfo {
+            gfx_queue: gfx_queue.clone(),
+            render_pass: subpass.clone().into(),
+            sample_count,
+            color_write_mask: ColorComponents::all(),
+            config: pipeline_config.clone(),
+        });
+        let sampler =
+            Sampler::new(gfx_queue.device().clone(), SamplerOptions::default().create_info())?;
+        let staging_pool =
+            CpuBufferPool::new(gfx_queue.device().clone(), BufferUsage::transfer_src());
+        Ok(Renderer {
             gfx_queue,
             format: final_output_format,
             render_pass: None,
-            vertex_buffer,
-            index_buffer,
+            vertex_pool,
+            index_pool,
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            device_buffers: None,
+            spare_device_buffers: VecDeque::new(),
+            device_local_geometry: false,
+            geometry_upload: None,
             pipeline,
-            subpass,
+            pipeline_render_pass: subpass.into(),
+            sample_count,
             texture_desc_sets: AHashMap::default(),
             texture_images: AHashMap::default(),
-            next_native_tex_id: 0,
+            user_textures: Default::default(),
+            ycbcr_pipelines: vec![],
+            ycbcr_textures: AHashMap::default(),
+            bindless: None,
+            blend_pipelines: AHashMap::default(),
+            descriptor_pool: None,
             is_overlay: false,
+            clear_color: [0.0; 4],
+            view_mask: 0,
             sampler,
-        }
+            samplers: AHashMap::default(),
+            static_cache: None,
+            ui_cache: None,
+            scaled_target: None,
+            offscreen_draws: vec![],
+            callback_renderers: Default::default(),
+            subpass_inheritance: Default::default(),
+            projection: None,
+            staging_pool,
+            staging_buffers: 0,
+            staging_buffer_size: 0,
+            pixel_snapping: false,
+            analytic_anti_aliasing: false,
+            color_write_mask: ColorComponents::all(),
+            pipeline_config,
+        })
     }
 
     /// Creates a new [Renderer] which is responsible for rendering egui with its own renderpass
@@ -115,15 +836,261 @@ impl Renderer {
         gfx_queue: Arc<Queue>,
         final_output_format: Format,
         is_overlay: bool,
-    ) -> Renderer {
-        // Create Gui render pass with just depth and final color
-        let render_pass = if is_overlay {
-            vulkano::single_pass_renderpass!(gfx_queue.device().clone(),
+    ) -> Result<Renderer, GuiError> {
+        Self::new_with_view_mask(gfx_queue, final_output_format, is_overlay, 0)
+    }
+
+    /// Creates a new [Renderer] drawing egui once into each of the first `view_count` array layers of the image with
+    /// multiview, e.g. for the two eyes of a VR headset. The device must have the `multiview` feature enabled.
+    pub fn new_with_multiview(
+        gfx_queue: Arc<Queue>,
+        final_output_format: Format,
+        view_count: u32,
+        is_overlay: bool,
+    ) -> Result<Renderer, GuiError> {
+        assert!(view_count > 0 && view_count <= 32, "view_count must be within 1..=32");
+        Self::new_with_view_mask(
+            gfx_queue,
+            final_output_format,
+            is_overlay,
+            u32::MAX >> (32 - view_count),
+        )
+    }
+
+    fn new_with_view_mask(
+        gfx_queue: Arc<Queue>,
+        final_output_format: Format,
+        is_overlay: bool,
+        view_mask: u32,
+    ) -> Result<Renderer, GuiError> {
+        let render_pass = Self::create_render_pass(
+            gfx_queue.device().clone(),
+            final_output_format,
+            is_overlay,
+            view_mask,
+        )?;
+
+        Self::assert_push_constants_fit(gfx_queue.device());
+        let (vertex_pool, index_pool) = Self::create_geometry_pools(gfx_queue.device().clone());
+
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+        let pipeline_config = PipelineConfig::for_format(final_output_format);
+        let pipeline = AsyncPipeline::spawn(PipelineCreateInfo {
+            gfx_queue: gfx_queue.clone(),
+            render_pass: subpass.clone().into(),
+            sample_count: SampleCount::Sample1,
+            color_write_mask: ColorComponents::all(),
+            config: pipeline_config.clone(),
+        });
+        let sampler =
+            Sampler::new(gfx_queue.device().clone(), SamplerOptions::default().create_info())?;
+        let staging_pool =
+            CpuBufferPool::new(gfx_queue.device().clone(), BufferUsage::transfer_src());
+        Ok(Renderer {
+            gfx_queue,
+            format: final_output_format,
+            render_pass: Some(render_pass),
+            vertex_pool,
+            index_pool,
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            device_buffers: None,
+            spare_device_buffers: VecDeque::new(),
+            device_local_geometry: false,
+            geometry_upload: None,
+            pipeline,
+            pipeline_render_pass: subpass.into(),
+            sample_count: SampleCount::Sample1,
+            texture_desc_sets: AHashMap::default(),
+            texture_images: AHashMap::default(),
+            user_textures: Default::default(),
+            ycbcr_pipelines: vec![],
+            ycbcr_textures: AHashMap::default(),
+            bindless: None,
+            blend_pipelines: AHashMap::default(),
+            descriptor_pool: None,
+            is_overlay,
+            clear_color: [0.0; 4],
+            view_mask,
+            sampler,
+            samplers: AHashMap::default(),
+            static_cache: None,
+            ui_cache: None,
+            scaled_target: None,
+            offscreen_draws: vec![],
+            callback_renderers: Default::default(),
+            subpass_inheritance: Default::default(),
+            projection: None,
+            staging_pool,
+            staging_buffers: 0,
+            staging_buffer_size: 0,
+            pixel_snapping: false,
+            analytic_anti_aliasing: false,
+            color_write_mask: ColorComponents::all(),
+            pipeline_config,
+        })
+    }
+
+    /// Creates a new [Renderer] drawing egui with dynamic rendering (`begin_rendering`) instead of a render pass.
+    /// It begins rendering on the image itself in `draw_on_image`, or its commands can be executed within your
+    /// `begin_rendering` with `draw_on_subpass_image`. The device must have the `dynamic_rendering` feature enabled.
+    pub fn new_with_dynamic_rendering(
+        gfx_queue: Arc<Queue>,
+        final_output_format: Format,
+        sample_count: SampleCount,
+        is_overlay: bool,
+    ) -> Result<Renderer, GuiError> {
+        Self::assert_push_constants_fit(gfx_queue.device());
+        let (vertex_pool, index_pool) = Self::create_geometry_pools(gfx_queue.device().clone());
+        let pipeline_render_pass =
+            PipelineRenderPassType::BeginRendering(PipelineRenderingCreateInfo {
+                color_attachment_formats: vec![Some(final_output_format)],
+                ..Default::default()
+            });
+        let pipeline_config = PipelineConfig::for_format(final_output_format);
+        let pipeline = AsyncPipeline::spawn(PipelineCreateInfo {
+            gfx_queue: gfx_queue.clone(),
+            render_pass: pipeline_render_pass.clone(),
+            sample_count,
+            color_write_mask: ColorComponents::all(),
+            config: pipeline_config.clone(),
+        });
+        let sampler =
+            Sampler::new(gfx_queue.device().clone(), SamplerOptions::default().create_info())?;
+        let staging_pool =
+            CpuBufferPool::new(gfx_queue.device().clone(), BufferUsage::transfer_src());
+        Ok(Renderer {
+            gfx_queue,
+            format: final_output_format,
+            render_pass: None,
+            vertex_pool,
+            index_pool,
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            device_buffers: None,
+            spare_device_buffers: VecDeque::new(),
+            device_local_geometry: false,
+            geometry_upload: None,
+            pipeline,
+            pipeline_render_pass,
+            sample_count,
+            texture_desc_sets: AHashMap::default(),
+            texture_images: AHashMap::default(),
+            user_textures: Default::default(),
+            ycbcr_pipelines: vec![],
+            ycbcr_textures: AHashMap::default(),
+            bindless: None,
+            blend_pipelines: AHashMap::default(),
+            descriptor_pool: None,
+            is_overlay,
+            clear_color: [0.0; 4],
+            view_mask: 0,
+            sampler,
+            samplers: AHashMap::default(),
+            static_cache: None,
+            ui_cache: None,
+            scaled_target: None,
+            offscreen_draws: vec![],
+            callback_renderers: Default::default(),
+            subpass_inheritance: Default::default(),
+            projection: None,
+            staging_pool,
+            staging_buffers: 0,
+            staging_buffer_size: 0,
+            pixel_snapping: false,
+            analytic_anti_aliasing: false,
+            color_write_mask: ColorComponents::all(),
+            pipeline_config,
+        })
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub(crate) fn check_render_target_format(
+        &self,
+        image: &Arc<dyn ImageViewAbstract + 'static>,
+    ) -> Result<(), GuiError> {
+        let found = image.format();
+        if found != Some(self.format) {
+            return Err(GuiError::WrongRenderTargetFormat { found, expected: self.format });
+        }
+        Ok(())
+    }
+
+    pub fn is_overlay(&self) -> bool {
+        self.is_overlay
+    }
+
+    /// Clears the image to `clear_color` before drawing, or loads its contents with `None`, see
+    /// [`Gui::set_clear_color`](crate::Gui::set_clear_color)
+    pub fn set_clear_color(&mut self, clear_color: Option<[f32; 4]>) -> Result<(), GuiError> {
+        let is_overlay = clear_color.is_none();
+        if let Some(clear_color) = clear_color {
+            self.clear_color = clear_color;
+        }
+        if is_overlay == self.is_overlay {
+            return Ok(());
+        }
+        // Differs only in the load op, so it stays compatible with the pipelines & secondary command buffers
+        if self.render_pass.is_some() {
+            self.render_pass = Some(Self::create_render_pass(
+                self.gfx_queue.device().clone(),
+                self.format,
+                is_overlay,
+                self.view_mask,
+            )?);
+        }
+        self.is_overlay = is_overlay;
+        Ok(())
+    }
+
+    pub fn clear_color(&self) -> Option<[f32; 4]> {
+        if self.is_overlay {
+            None
+        } else {
+            Some(self.clear_color)
+        }
+    }
+
+    /// Gui render pass with just the final color, loaded for overlays & cleared otherwise
+    fn create_render_pass(
+        device: Arc<Device>,
+        format: Format,
+        is_overlay: bool,
+        view_mask: u32,
+    ) -> Result<Arc<RenderPass>, RenderPassCreationError> {
+        if view_mask != 0 {
+            // Each draw is broadcast to the views, i.e. array layers of the image
+            let layout = ImageLayout::ColorAttachmentOptimal;
+            RenderPass::new(device, RenderPassCreateInfo {
+                attachments: vec![AttachmentDescription {
+                    format: Some(format),
+                    samples: SampleCount::Sample1,
+                    load_op: if is_overlay { LoadOp::Load } else { LoadOp::Clear },
+                    store_op: StoreOp::Store,
+                    initial_layout: layout,
+                    final_layout: layout,
+                    ..Default::default()
+                }],
+                subpasses: vec![SubpassDescription {
+                    view_mask,
+                    color_attachments: vec![Some(AttachmentReference {
+                        attachment: 0,
+                        layout,
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                }],
+                correlated_view_masks: vec![view_mask],
+                ..Default::default()
+            })
+        } else if is_overlay {
+            vulkano::single_pass_renderpass!(device,
                 attachments: {
                     final_color: {
                         load: Load,
                         store: Store,
-                        format: final_output_format,
+                        format: format,
                         samples: 1,
                     }
                 },
@@ -132,14 +1099,13 @@ impl Renderer {
                         depth_stencil: {}
                 }
             )
-            .unwrap()
         } else {
-            vulkano::single_pass_renderpass!(gfx_queue.device().clone(),
+            vulkano::single_pass_renderpass!(device,
                 attachments: {
                     final_color: {
                         load: Clear,
                         store: Store,
-                        format: final_output_format,
+                        format: format,
                         samples: 1,
                     }
                 },
@@ -148,85 +1114,227 @@ impl Renderer {
                         depth_stencil: {}
                 }
             )
-            .unwrap()
-        };
+        }
+    }
 
-        let (vertex_buffer, index_buffer) = Self::create_buffers(gfx_queue.device().clone());
+    pub fn has_renderpass(&self) -> bool {
+        self.render_pass.is_some()
+    }
 
-        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
-        let pipeline = Self::create_pipeline(gfx_queue.clone(), subpass.clone());
-        let sampler = Sampler::new(gfx_queue.device().clone(), SamplerCreateInfo {
-            mag_filter: Filter::Linear,
-            min_filter: Filter::Linear,
-            address_mode: [SamplerAddressMode::ClampToEdge; 3],
-            mipmap_mode: SamplerMipmapMode::Linear,
-            ..Default::default()
-        })
-        .unwrap();
-        Renderer {
-            gfx_queue,
-            format: final_output_format,
-            render_pass: Some(render_pass),
-            vertex_buffer,
-            index_buffer,
-            pipeline,
-            subpass,
-            texture_desc_sets: AHashMap::default(),
-            texture_images: AHashMap::default(),
-            next_native_tex_id: 0,
-            is_overlay,
-            sampler,
-        }
+    pub fn is_dynamic_rendering(&self) -> bool {
+        matches!(self.pipeline_render_pass, PipelineRenderPassType::BeginRendering(_))
     }
 
-    pub fn has_renderpass(&self) -> bool {
-        self.render_pass.is_some()
+    /// Every device supports 128 bytes of push constants, which egui's shaders stay within
+    fn assert_push_constants_fit(device: &Device) {
+        let size = std::mem::size_of::<vs::ty::PushConstants>() as u32;
+        let max_size = device.physical_device().properties().max_push_constants_size;
+        assert!(
+            size <= max_size,
+            "Egui's {} bytes of push constants exceed the device's {}",
+            size,
+            max_size
+        );
     }
 
-    fn create_buffers(
+    fn create_geometry_pools(
         device: Arc<Device>,
-    ) -> (Arc<CpuAccessibleBuffer<[EguiVertex]>>, Arc<CpuAccessibleBuffer<[u32]>>) {
-        // Create vertex and index buffers
-        let vertex_buffer = unsafe {
-            CpuAccessibleBuffer::<[EguiVertex]>::uninitialized_array(
-                device.clone(),
-                VERTEX_BUFFER_SIZE,
-                BufferUsage::vertex_buffer(),
-                false,
-            )
-            .expect("failed to create gui vertex buffer")
-        };
-        let index_buffer = unsafe {
-            CpuAccessibleBuffer::<[u32]>::uninitialized_array(
-                device,
-                INDEX_BUFFER_SIZE,
-                BufferUsage::index_buffer(),
-                false,
-            )
-            .expect("failed to create gui vertex buffer")
-        };
+    ) -> (CpuBufferPool<EguiVertex>, CpuBufferPool<u32>) {
+        // Also the source of device-local geometry
+        let vertex_pool = CpuBufferPool::new(device.clone(), BufferUsage {
+            transfer_src: true,
+            ..BufferUsage::vertex_buffer()
+        });
+        let index_pool = CpuBufferPool::new(device, BufferUsage {
+            transfer_src: true,
+            ..BufferUsage::index_buffer()
+        });
+        (vertex_pool, index_pool)
+    }
+
+    /// Device-local buffers holding at least `vertices_len` vertices & `indices_len` indices, grown to the next
+    /// power of two if they don't
+    fn device_buffers(
+        &mut self,
+        vertices_len: DeviceSize,
+        indices_len: DeviceSize,
+    ) -> Option<DeviceBuffers> {
+        if !self.device_local_geometry {
+            return None;
+        }
+        match &self.device_buffers {
+            Some((vertices, indices))
+                if vertices.len() >= vertices_len && indices.len() >= indices_len => {}
+            _ => {
+                self.device_buffers = Some(self.create_device_buffers(
+                    vertices_len.next_power_of_two(),
+                    indices_len.next_power_of_two(),
+                ))
+            }
+        }
+        self.device_buffers.clone()
+    }
+
+    fn create_device_buffers(
+        &self,
+        vertices_len: DeviceSize,
+        indices_len: DeviceSize,
+    ) -> DeviceBuffers {
+        let device = self.gfx_queue.device();
+        let vertex_buffer = DeviceLocalBuffer::<[EguiVertex]>::array(
+            device.clone(),
+            vertices_len,
+            BufferUsage { transfer_dst: true, ..BufferUsage::vertex_buffer() },
+            [self.gfx_queue.family()],
+        )
+        .expect("failed to create gui vertex buffer");
+        let index_buffer = DeviceLocalBuffer::<[u32]>::array(
+            device.clone(),
+            indices_len,
+            BufferUsage { transfer_dst: true, ..BufferUsage::index_buffer() },
+            [self.gfx_queue.family()],
+        )
+        .expect("failed to create gui index buffer");
         (vertex_buffer, index_buffer)
     }
 
-    fn create_pipeline(gfx_queue: Arc<Queue>, subpass: Subpass) -> Arc<GraphicsPipeline> {
-        let vs = vs::load(gfx_queue.device().clone()).expect("failed to create shader module");
-        let fs = fs::load(gfx_queue.device().clone()).expect("failed to create shader module");
+    /// Draws meshes from device-local buffers, see
+    /// [`Gui::set_device_local_geometry`](crate::Gui::set_device_local_geometry)
+    pub fn set_device_local_geometry(&mut self, enabled: bool) {
+        self.device_local_geometry = enabled;
+        if !enabled {
+            self.device_buffers = None;
+            self.spare_device_buffers.clear();
+        }
+    }
+
+    /// Records the copy of the geometry written by the last draw to the device-local buffers
+    fn upload_geometry(&mut self) -> Option<PrimaryAutoCommandBuffer> {
+        let GeometryUpload { vertices, indices, device_buffers } = self.geometry_upload.take()?;
+        let (vertices_len, indices_len) = (vertices.len(), indices.len());
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            self.gfx_queue.device().clone(),
+            self.gfx_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        cbb.copy_buffer(CopyBufferInfo::buffers(
+            vertices,
+            device_buffers.0.into_buffer_slice().slice(0..vertices_len).unwrap(),
+        ))
+        .unwrap()
+        .copy_buffer(CopyBufferInfo::buffers(
+            indices,
+            device_buffers.1.into_buffer_slice().slice(0..indices_len).unwrap(),
+        ))
+        .unwrap();
+        Some(cbb.build().unwrap())
+    }
+
+    fn create_pipeline(
+        gfx_queue: Arc<Queue>,
+        render_pass: PipelineRenderPassType,
+        sample_count: SampleCount,
+        color_write_mask: ColorComponents,
+        config: PipelineConfig,
+    ) -> Result<Arc<GraphicsPipeline>, GuiError> {
+        Self::create_pipeline_with_blend(
+            gfx_queue,
+            render_pass,
+            sample_count,
+            config.blend_mode.attachment_blend(),
+            color_write_mask,
+            TextureBinding::Single,
+            config,
+        )
+    }
 
-        let mut blend = AttachmentBlend::alpha();
-        blend.color_source = BlendFactor::One;
-        let blend_state = ColorBlendState::new(1).blend(blend);
+    /// Fails e.g. on custom shaders that don't fit the pipeline
+    fn create_pipeline_with_blend(
+        gfx_queue: Arc<Queue>,
+        render_pass: PipelineRenderPassType,
+        sample_count: SampleCount,
+        blend: AttachmentBlend,
+        color_write_mask: ColorComponents,
+        texture_binding: TextureBinding,
+        config: PipelineConfig,
+    ) -> Result<Arc<GraphicsPipeline>, GuiError> {
+        let vs = match config.shaders.vertex.clone() {
+            Some(vs) => vs,
+            None => vs::load(gfx_queue.device().clone())?,
+        };
+        // The bindless pipeline samples an array, which custom shaders don't declare
+        let fs = match (&texture_binding, config.shaders.fragment.clone()) {
+            (TextureBinding::Bindless(_), _) => fs_bindless::load(gfx_queue.device().clone())?,
+            (_, Some(fs)) => fs,
+            _ => fs::load(gfx_queue.device().clone())?,
+        };
+        let vs_main = vs.entry_point("main").ok_or(GuiError::NoShaderEntryPoint)?;
+        let fs_main = fs.entry_point("main").ok_or(GuiError::NoShaderEntryPoint)?;
 
-        GraphicsPipeline::start()
+        // Subpasses of e.g. deferred renderers may have more color attachments, egui draws on the first one. Masking
+        // the others needs the `independent_blend` feature, which `new_with_subpass` checks for.
+        let attachments = match &render_pass {
+            PipelineRenderPassType::BeginRenderPass(subpass) => {
+                subpass.subpass_desc().color_attachments.len()
+            }
+            PipelineRenderPassType::BeginRendering(rendering_info) => {
+                rendering_info.color_attachment_formats.len()
+            }
+        };
+        let mut blend_state = ColorBlendState::new(attachments.max(1) as u32).blend(blend);
+        blend_state.attachments[0].color_write_mask = color_write_mask;
+        for attachment in &mut blend_state.attachments[1..] {
+            attachment.blend = None;
+            attachment.color_write_mask = ColorComponents::none();
+        }
+        let multisample_state =
+            MultisampleState { rasterization_samples: sample_count, ..Default::default() };
+
+        let mut builder = GraphicsPipeline::start()
             .vertex_input_state(BuffersDefinition::new().vertex::<EguiVertex>())
-            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .vertex_shader(vs_main, ())
             .input_assembly_state(InputAssemblyState::new())
-            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .fragment_shader(fs_main, config.fragment_constants())
+            // Dynamic, so resizing the target never recreates the pipeline
             .viewport_state(ViewportState::viewport_dynamic_scissor_dynamic(1))
             .color_blend_state(blend_state)
+            .multisample_state(multisample_state)
+            .depth_stencil_state(config.depth_stencil)
             .rasterization_state(RasterizationState::new().cull_mode(CullModeEnum::None))
-            .render_pass(subpass)
-            .build(gfx_queue.device().clone())
-            .unwrap()
+            .render_pass(render_pass.clone());
+        if let Some(pipeline_cache) = config.cache {
+            builder = builder.build_with_cache(pipeline_cache);
+        }
+        let device = gfx_queue.device().clone();
+        match texture_binding {
+            TextureBinding::Single => builder.build(device),
+            TextureBinding::ImmutableSampler(sampler) => {
+                builder.with_auto_layout(device, |set_layouts| {
+                    set_layouts[0].bindings.get_mut(&0).unwrap().immutable_samplers = vec![sampler];
+                })
+            }
+            TextureBinding::Bindless(max_textures) => {
+                builder.with_auto_layout(device, |set_layouts| {
+                    let binding = set_layouts[0].bindings.get_mut(&0).unwrap();
+                    binding.descriptor_count = max_textures;
+                    binding.variable_descriptor_count = true;
+                })
+            }
+        }
+        .map_err(|err| {
+            GuiError::PipelineCreation(pipeline_error_message(
+                err,
+                gfx_queue.device(),
+                &render_pass,
+                sample_count,
+            ))
+        })
+    }
+
+    /// Egui's pipeline, waits for it to finish compiling
+    fn pipeline(&mut self) -> Result<Arc<GraphicsPipeline>, GuiError> {
+        self.pipeline.get()
     }
 
     /// Creates a descriptor set for images
@@ -234,142 +1342,1407 @@ impl Renderer {
         &self,
         layout: &Arc<DescriptorSetLayout>,
         image: Arc<dyn ImageViewAbstract + 'static>,
-    ) -> Arc<PersistentDescriptorSet> {
-        PersistentDescriptorSet::new(layout.clone(), [WriteDescriptorSet::image_view_sampler(
-            0,
-            image.clone(),
+    ) -> Result<Arc<dyn DescriptorSet>, GuiError> {
+        self.desc_set_with_sampler(layout, image, self.sampler.clone())
+    }
+
+    fn desc_set_with_sampler(
+        &self,
+        layout: &Arc<DescriptorSetLayout>,
+        image: Arc<dyn ImageViewAbstract + 'static>,
+        sampler: Arc<Sampler>,
+    ) -> Result<Arc<dyn DescriptorSet>, GuiError> {
+        self.allocate_desc_set(layout, WriteDescriptorSet::image_view_sampler(0, image, sampler))
+    }
+
+    fn allocate_desc_set(
+        &self,
+        layout: &Arc<DescriptorSetLayout>,
+        write: WriteDescriptorSet,
+    ) -> Result<Arc<dyn DescriptorSet>, GuiError> {
+        Ok(match &self.descriptor_pool {
+            Some(pool) => pool.lock().unwrap().allocate(layout.clone(), write),
+            None => PersistentDescriptorSet::new(layout.clone(), [write])?,
+        })
+    }
+
+    /// Registers a user texture. User texture needs to be unregistered when it is no longer needed
+    pub fn register_image(
+        &mut self,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.register_image_with_sampler(image, SamplerOptions::default())
+    }
+
+    /// Same as `register_image`, but the image is sampled with `sampler_options`. Samplers are shared by images
+    /// registered with the same options.
+    pub fn register_image_with_sampler(
+        &mut self,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+        sampler_options: SamplerOptions,
+    ) -> Result<egui::TextureId, GuiError> {
+        self.insert_user_image(None, image, sampler_options)
+    }
+
+    /// Registers `image` as the user texture of `id`, or of the next free id when it's `None`
+    fn insert_user_image(
+        &mut self,
+        id: Option<u64>,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+        sampler_options: SamplerOptions,
+    ) -> Result<egui::TextureId, GuiError> {
+        let color_conversion = sampler_options.color_conversion(image.format());
+        let sampler = self.shared_sampler(sampler_options)?;
+        let pipeline = self.pipeline()?;
+        let layout = pipeline.layout().set_layouts().get(0).unwrap();
+        let desc_set = self.desc_set_with_sampler(layout, image.clone(), sampler.clone())?;
+        let mut user_textures = self.user_textures.lock().unwrap();
+        let id = id.unwrap_or(user_textures.next_id);
+        user_textures.next_id = user_textures.next_id.max(id + 1);
+        let id = egui::TextureId::User(id);
+        user_textures.desc_sets.insert(id, desc_set);
+        user_textures.images.insert(id, image);
+        if !Arc::ptr_eq(&sampler, &self.sampler) {
+            user_textures.samplers.insert(id, sampler);
+        }
+        if sampler_options != SamplerOptions::default() {
+            user_textures.sampler_options.insert(id, sampler_options);
+        }
+        user_textures.generation += 1;
+        if color_conversion != COLOR_CONVERSION_NONE {
+            user_textures.color_conversions.insert(id, color_conversion);
+        }
+        Ok(id)
+    }
+
+    /// Sampler of `sampler_options`, shared by the images registered with the same options
+    fn shared_sampler(
+        &mut self,
+        sampler_options: SamplerOptions,
+    ) -> Result<Arc<Sampler>, GuiError> {
+        // The color space is applied in the shader, so it doesn't need a sampler of its own
+        let sampler_options = SamplerOptions { color_space: None, ..sampler_options };
+        if sampler_options == SamplerOptions::default() {
+            return Ok(self.sampler.clone());
+        }
+        if let Some(sampler) = self.samplers.get(&sampler_options) {
+            return Ok(sampler.clone());
+        }
+        let sampler = Sampler::new(self.gfx_queue.device().clone(), sampler_options.create_info())?;
+        self.samplers.insert(sampler_options, sampler.clone());
+        Ok(sampler)
+    }
+
+    /// Samples a user image with `sampler_options` from now on, recreating its descriptor set, see
+    /// [`Gui::set_user_image_sampler`](crate::Gui::set_user_image_sampler)
+    pub fn set_image_sampler(
+        &mut self,
+        texture_id: egui::TextureId,
+        sampler_options: SamplerOptions,
+    ) -> Result<(), GuiError> {
+        let image = self
+            .user_textures
+            .lock()
+            .unwrap()
+            .images
+            .get(&texture_id)
+            .cloned()
+            .ok_or(GuiError::UnknownTexture(texture_id))?;
+        let color_conversion = sampler_options.color_conversion(image.format());
+        let sampler = self.shared_sampler(sampler_options)?;
+        let pipeline = self.pipeline()?;
+        let layout = pipeline.layout().set_layouts().get(0).unwrap();
+        let desc_set = self.desc_set_with_sampler(layout, image, sampler.clone())?;
+        let mut user_textures = self.user_textures.lock().unwrap();
+        if Arc::ptr_eq(&sampler, &self.sampler) {
+            user_textures.samplers.remove(&texture_id);
+        } else {
+            user_textures.samplers.insert(texture_id, sampler);
+        }
+        if sampler_options == SamplerOptions::default() {
+            user_textures.sampler_options.remove(&texture_id);
+        } else {
+            user_textures.sampler_options.insert(texture_id, sampler_options);
+        }
+        user_textures.generation += 1;
+        if color_conversion == COLOR_CONVERSION_NONE {
+            user_textures.color_conversions.remove(&texture_id);
+        } else {
+            user_textures.color_conversions.insert(texture_id, color_conversion);
+        }
+        user_textures.desc_sets.insert(texture_id, desc_set);
+        Ok(())
+    }
+
+    /// Draws a user image with `blend_mode` instead of the renderer's, see
+    /// [`Gui::set_user_image_blend_mode`](crate::Gui::set_user_image_blend_mode)
+    pub fn set_image_blend_mode(
+        &mut self,
+        texture_id: egui::TextureId,
+        blend_mode: Option<BlendMode>,
+    ) -> Result<(), GuiError> {
+        let mut user_textures = self.user_textures.lock().unwrap();
+        if !user_textures.images.contains_key(&texture_id) {
+            return Err(GuiError::UnknownTexture(texture_id));
+        }
+        match blend_mode {
+            Some(blend_mode) => user_textures.blend_modes.insert(texture_id, blend_mode),
+            None => user_textures.blend_modes.remove(&texture_id),
+        };
+        // Drawn with their own pipelines, so taken out of the bindless array
+        user_textures.generation += 1;
+        Ok(())
+    }
+
+    /// Creates the pipelines of blend modes user images are drawn with that don't exist yet
+    fn update_blend_pipelines(&mut self) -> Result<(), GuiError> {
+        let blend_modes: Vec<BlendMode> =
+            self.user_textures.lock().unwrap().blend_modes.values().copied().collect();
+        for blend_mode in blend_modes {
+            if blend_mode == self.pipeline_config.blend_mode
+                || self.blend_pipelines.contains_key(&blend_mode)
+            {
+                continue;
+            }
+            let pipeline = Self::create_pipeline(
+                self.gfx_queue.clone(),
+                self.pipeline_render_pass.clone(),
+                self.sample_count,
+                self.color_write_mask,
+                PipelineConfig { blend_mode, ..self.pipeline_config.clone() },
+            )?;
+            self.blend_pipelines.insert(blend_mode, pipeline);
+        }
+        Ok(())
+    }
+
+    /// Pipeline of a user image drawn with another blend mode than the renderer's
+    fn blend_pipeline(&self, texture_id: egui::TextureId) -> Option<Arc<GraphicsPipeline>> {
+        let blend_mode = *self.user_textures.lock().unwrap().blend_modes.get(&texture_id)?;
+        self.blend_pipelines.get(&blend_mode).cloned()
+    }
+
+    /// Registers an image whose upload is still running, e.g. on a transfer queue. It isn't drawn until `upload`
+    /// has signaled.
+    pub fn register_image_uploading(
+        &mut self,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+        upload: FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>,
+    ) -> Result<egui::TextureId, GuiError> {
+        let id = self.register_image(image)?;
+        self.user_textures.lock().unwrap().pending_uploads.insert(id, upload);
+        Ok(id)
+    }
+
+    /// Registers a multi-planar YUV image sampled through `conversion`, see
+    /// [`Gui::register_user_image_ycbcr`](crate::Gui::register_user_image_ycbcr)
+    pub fn register_ycbcr_image(
+        &mut self,
+        image: Arc<dyn ImageAccess>,
+        conversion: Arc<SamplerYcbcrConversion>,
+    ) -> Result<egui::TextureId, GuiError> {
+        let device = self.gfx_queue.device().clone();
+        if !device.enabled_features().sampler_ycbcr_conversion {
+            return Err(GuiError::YcbcrConversionNotEnabled);
+        }
+        let index = match self
+            .ycbcr_pipelines
+            .iter()
+            .position(|ycbcr| Arc::ptr_eq(&ycbcr.conversion, &conversion))
+        {
+            Some(index) => index,
+            None => {
+                // Filters must match the conversion's chroma filter
+                let sampler = Sampler::new(device, SamplerCreateInfo {
+                    mag_filter: conversion.chroma_filter(),
+                    min_filter: conversion.chroma_filter(),
+                    address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                    sampler_ycbcr_conversion: Some(conversion.clone()),
+                    ..Default::default()
+                })?;
+                let pipeline = self.create_derived_pipeline(
+                    TextureBinding::ImmutableSampler(sampler.clone()),
+                    &self.pipeline_config,
+                    self.color_write_mask,
+                )?;
+                self.ycbcr_pipelines.push(YcbcrPipeline {
+                    conversion: conversion.clone(),
+                    sampler,
+                    pipeline,
+                });
+                self.ycbcr_pipelines.len() - 1
+            }
+        };
+        let view = ImageView::new(image.clone(), ImageViewCreateInfo {
+            sampler_ycbcr_conversion: Some(conversion),
+            ..ImageViewCreateInfo::from_image(&*image)
+        })?;
+        let layout = self.ycbcr_pipelines[index].pipeline.layout().set_layouts()[0].clone();
+        let desc_set =
+            self.allocate_desc_set(&layout, WriteDescriptorSet::image_view(0, view.clone()))?;
+        let texture_id = {
+            let mut user_textures = self.user_textures.lock().unwrap();
+            user_textures.next_id += 1;
+            egui::TextureId::User(user_textures.next_id - 1)
+        };
+        self.texture_desc_sets.insert(texture_id, desc_set);
+        self.texture_images.insert(texture_id, view);
+        self.own_textures_changed();
+        self.ycbcr_textures.insert(texture_id, index);
+        Ok(texture_id)
+    }
+
+    /// Pipeline of YUV images or bindless textures, with the state of egui's pipeline for `config`
+    fn create_derived_pipeline(
+        &self,
+        texture_binding: TextureBinding,
+        config: &PipelineConfig,
+        color_write_mask: ColorComponents,
+    ) -> Result<Arc<GraphicsPipeline>, GuiError> {
+        Self::create_pipeline_with_blend(
+            self.gfx_queue.clone(),
+            self.pipeline_render_pass.clone(),
+            self.sample_count,
+            config.blend_mode.attachment_blend(),
+            color_write_mask,
+            texture_binding,
+            config.clone(),
+        )
+    }
+
+    /// Draws textures from one descriptor array, see [`Gui::set_bindless`](crate::Gui::set_bindless)
+    pub fn set_bindless(&mut self, enabled: bool) -> Result<(), GuiError> {
+        if !enabled {
+            self.bindless = None;
+            return Ok(());
+        }
+        if self.bindless.is_some() {
+            return Ok(());
+        }
+        let device = self.gfx_queue.device();
+        let features = device.enabled_features();
+        // The shader is SPIR-V 1.5 & indexes the array with a push constant
+        if device.api_version() < vulkano::Version::V1_2
+            || !features.runtime_descriptor_array
+            || !features.descriptor_binding_variable_descriptor_count
+            || !features.shader_sampled_image_array_dynamic_indexing
+        {
+            return Err(GuiError::DescriptorIndexingNotEnabled);
+        }
+        let properties = device.physical_device().properties();
+        let max_textures = MAX_BINDLESS_TEXTURES
+            .min(properties.max_per_stage_descriptor_samplers)
+            .min(properties.max_per_stage_descriptor_sampled_images)
+            .min(properties.max_descriptor_set_samplers)
+            .min(properties.max_descriptor_set_sampled_images);
+        self.bindless = Some(Bindless {
+            pipeline: self.create_derived_pipeline(
+                TextureBinding::Bindless(max_textures),
+                &self.pipeline_config,
+                self.color_write_mask,
+            )?,
+            max_textures,
+            desc_set: None,
+            indices: Default::default(),
+            user_generation: 0,
+            stale: true,
+        });
+        Ok(())
+    }
+
+    /// Marks the bindless texture array for rewriting after the renderer's own textures changed
+    fn own_textures_changed(&mut self) {
+        if let Some(bindless) = &mut self.bindless {
+            bindless.stale = true;
+        }
+    }
+
+    /// Rewrites the bindless texture array if textures were registered or freed since it was written. Textures
+    /// beyond its size, YUV & raw images are drawn with their own descriptor sets.
+    fn update_bindless_set(&mut self) {
+        let bindless = match &self.bindless {
+            Some(bindless) => bindless,
+            None => return,
+        };
+        let user_textures = self.user_textures.lock().unwrap();
+        if !bindless.stale && bindless.user_generation == user_textures.generation {
+            return;
+        }
+        let own_textures = self
+            .texture_images
+            .iter()
+            .filter(|(texture_id, _)| !self.ycbcr_textures.contains_key(texture_id))
+            .map(|(texture_id, image)| (*texture_id, image, &self.sampler));
+        // Images still uploading can't be used yet, & ones with their own blend modes need their own pipelines
+        let registered_textures = user_textures
+            .images
+            .iter()
+            .filter(|(texture_id, _)| {
+                !user_textures.pending_uploads.contains_key(texture_id)
+                    && !user_textures.blend_modes.contains_key(texture_id)
+            })
+            .map(|(texture_id, image)| {
+                (
+                    *texture_id,
+                    image,
+                    user_textures.samplers.get(texture_id).unwrap_or(&self.sampler),
+                )
+            });
+        let mut indices = AHashMap::default();
+        let mut elements: Vec<(Arc<dyn ImageViewAbstract>, Arc<Sampler>)> = vec![];
+        for (texture_id, image, sampler) in
+            own_textures.chain(registered_textures).take(bindless.max_textures as usize)
+        {
+            indices.insert(texture_id, elements.len() as u32);
+            elements.push((image.clone(), sampler.clone()));
+        }
+        let desc_set = if elements.is_empty() {
+            None
+        } else {
+            let layout = bindless.pipeline.layout().set_layouts().get(0).unwrap().clone();
+            let desc_set: Arc<dyn DescriptorSet> =
+                PersistentDescriptorSet::new_variable(layout, elements.len() as u32, [
+                    WriteDescriptorSet::image_view_sampler_array(0, 0, elements),
+                ])
+                .unwrap();
+            Some(desc_set)
+        };
+        let user_generation = user_textures.generation;
+        drop(user_textures);
+        let bindless = self.bindless.as_mut().unwrap();
+        bindless.desc_set = desc_set;
+        bindless.indices = Arc::new(indices);
+        bindless.user_generation = user_generation;
+        bindless.stale = false;
+    }
+
+    /// Registers an image view created outside of vulkano as a user texture
+    ///
+    /// # Safety
+    ///
+    /// See [`Gui::register_raw_image_view`](crate::Gui::register_raw_image_view)
+    pub unsafe fn register_raw_image(
+        &mut self,
+        image_view: ash::vk::ImageView,
+        image_layout: ImageLayout,
+        release: Option<RawImageRelease>,
+    ) -> Result<egui::TextureId, GuiError> {
+        let pipeline = match self.pipeline() {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                if let Some(release) = release {
+                    release();
+                }
+                return Err(err);
+            }
+        };
+        let layout = pipeline.layout().set_layouts().get(0).unwrap().clone();
+        let desc_set = RawImageDescriptorSet::new(
+            self.gfx_queue.clone(),
+            layout,
             self.sampler.clone(),
-        )])
-        .unwrap()
+            image_view,
+            image_layout,
+            release,
+        )?;
+        let mut user_textures = self.user_textures.lock().unwrap();
+        let id = egui::TextureId::User(user_textures.next_id);
+        user_textures.next_id += 1;
+        user_textures.desc_sets.insert(id, Arc::new(desc_set));
+        user_textures.generation += 1;
+        Ok(id)
+    }
+
+    /// Registers an image created outside of vulkano as a user texture, through a view of its own
+    ///
+    /// # Safety
+    ///
+    /// See [`Gui::register_raw_image`](crate::Gui::register_raw_image)
+    pub unsafe fn register_raw_vk_image(
+        &mut self,
+        image: ash::vk::Image,
+        format: Format,
+        image_layout: ImageLayout,
+        release: Option<RawImageRelease>,
+    ) -> Result<egui::TextureId, GuiError> {
+        let device = self.gfx_queue.device().clone();
+        let image_view = match create_raw_image_view(&device, image, format) {
+            Ok(image_view) => image_view,
+            Err(err) => {
+                if let Some(release) = release {
+                    release();
+                }
+                return Err(err);
+            }
+        };
+        // The view is ours, so it's destroyed before the image is released
+        let release: RawImageRelease = Box::new(move || {
+            destroy_raw_image_view(&device, image_view);
+            if let Some(release) = release {
+                release();
+            }
+        });
+        self.register_raw_image(image_view, image_layout, Some(release))
+    }
+
+    /// Unregister user texture.
+    /// Command buffers drawn before keep the texture's image & descriptor set alive until their futures are dropped.
+    pub fn unregister_image(&mut self, texture_id: egui::TextureId) {
+        if self.texture_desc_sets.remove(&texture_id).is_some() {
+            self.texture_images.remove(&texture_id);
+            self.ycbcr_textures.remove(&texture_id);
+            self.own_textures_changed();
+        } else {
+            let mut user_textures = self.user_textures.lock().unwrap();
+            user_textures.desc_sets.remove(&texture_id);
+            user_textures.images.remove(&texture_id);
+            user_textures.color_conversions.remove(&texture_id);
+            user_textures.uv_rects.remove(&texture_id);
+            // Waits for the upload to finish
+            user_textures.pending_uploads.remove(&texture_id);
+            user_textures.samplers.remove(&texture_id);
+            user_textures.sampler_options.remove(&texture_id);
+            user_textures.blend_modes.remove(&texture_id);
+            user_textures.generation += 1;
+        }
+    }
+
+    /// Starts a draw, switching to the device-local buffers of the oldest frame in flight
+    fn advance_frame(&mut self) {
+        if self.device_local_geometry && self.frames_in_flight > 1 {
+            let spare = if self.spare_device_buffers.len() + 1 >= self.frames_in_flight as usize {
+                self.spare_device_buffers.pop_front()
+            } else {
+                None
+            };
+            // Created by the draw if not yet, or if they're too small
+            if let Some(device_buffers) = std::mem::replace(&mut self.device_buffers, spare) {
+                self.spare_device_buffers.push_back(device_buffers);
+            }
+        }
+    }
+
+    /// Sets how many draws may be executing at once, see
+    /// [`Gui::set_frames_in_flight`](crate::Gui::set_frames_in_flight)
+    pub fn set_frames_in_flight(&mut self, frames_in_flight: u32) {
+        assert!(frames_in_flight > 0, "At least one frame must be in flight");
+        self.frames_in_flight = frames_in_flight;
+        self.spare_device_buffers.truncate(frames_in_flight as usize - 1);
+    }
+
+    /// Shares the user textures of `other`, dropping the ones registered with this renderer
+    pub(crate) fn share_user_textures(&mut self, other: &Renderer) {
+        self.user_textures = other.user_textures.clone();
+    }
+
+    fn texture_desc_set(&self, texture_id: egui::TextureId) -> Option<Arc<dyn DescriptorSet>> {
+        match self.texture_desc_sets.get(&texture_id) {
+            Some(desc_set) => Some(desc_set.clone()),
+            None => {
+                let mut user_textures = self.user_textures.lock().unwrap();
+                if !user_textures.upload_finished(texture_id) {
+                    return None;
+                }
+                user_textures.desc_sets.get(&texture_id).cloned()
+            }
+        }
+    }
+
+    /// Changes whenever user images may be drawn differently, i.e. when they're registered, unregistered, updated,
+    /// or have finished uploading, also by other guis sharing them
+    pub(crate) fn user_textures_version(&self) -> u64 {
+        let mut user_textures = self.user_textures.lock().unwrap();
+        let uploading: Vec<egui::TextureId> =
+            user_textures.pending_uploads.keys().copied().collect();
+        for texture_id in uploading {
+            user_textures.upload_finished(texture_id);
+        }
+        user_textures.generation + user_textures.updates
+    }
+
+    /// Whether the texture is registered but its upload to another queue hasn't finished yet
+    pub fn is_upload_pending(&self, texture_id: egui::TextureId) -> bool {
+        !self.texture_desc_sets.contains_key(&texture_id)
+            && !self.user_textures.lock().unwrap().upload_finished(texture_id)
+    }
+
+    /// Sets the push constants that differ per texture for drawing a mesh of `texture_id`
+    fn set_texture_constants(
+        &self,
+        texture_id: egui::TextureId,
+        push_constants: &mut vs::ty::PushConstants,
+    ) {
+        push_constants.uv_rect = FULL_UV_RECT;
+        // Keeps the bindless texture index
+        push_constants.texture_params &= !COLOR_CONVERSION_MASK;
+        if self.texture_desc_sets.contains_key(&texture_id) {
+            return;
+        }
+        let user_textures = self.user_textures.lock().unwrap();
+        if let Some(uv_rect) = user_textures.uv_rects.get(&texture_id) {
+            push_constants.uv_rect = *uv_rect;
+        }
+        if let Some(color_conversion) = user_textures.color_conversions.get(&texture_id) {
+            push_constants.texture_params |= *color_conversion;
+        }
+    }
+
+    /// Enables or disables caching of unchanged primitive runs into textures
+    pub fn set_static_cache(&mut self, enabled: bool) -> Result<(), GuiError> {
+        if !enabled {
+            self.clear_static_cache();
+            self.static_cache = None;
+        } else if self.static_cache.is_none() {
+            self.static_cache = Some(self.create_static_cache()?);
+        }
+        Ok(())
+    }
+
+    /// Drops cached textures, keeping the cache enabled
+    fn clear_static_cache(&mut self) {
+        let entries = match &mut self.static_cache {
+            Some(cache) => std::mem::take(&mut cache.entries),
+            None => return,
+        };
+        for (texture_id, _) in entries.values().filter_map(|entry| entry.texture) {
+            self.unregister_image(texture_id);
+        }
+    }
+
+    /// Releases transient GPU memory (mesh buffers, cache & render scale textures) while keeping textures, see
+    /// [`Gui::suspend`](crate::Gui::suspend)
+    pub fn suspend(&mut self) {
+        self.clear_static_cache();
+        self.invalidate_ui_cache();
+        let scaled_image = self.scaled_target.as_mut().and_then(|target| target.image.take());
+        if let Some((_, texture_id)) = scaled_image {
+            self.unregister_image(texture_id);
+        }
+        let (vertex_pool, index_pool) =
+            Self::create_geometry_pools(self.gfx_queue.device().clone());
+        self.vertex_pool = vertex_pool;
+        self.index_pool = index_pool;
+        self.device_buffers = None;
+        self.spare_device_buffers.clear();
+    }
+
+    fn create_static_cache(&self) -> Result<StaticCache, GuiError> {
+        let (render_pass, pipeline) = self.create_cache_pipeline()?;
+        Ok(StaticCache {
+            render_pass,
+            pipeline,
+            hasher: ahash::RandomState::new(),
+            entries: AHashMap::default(),
+            generation: 0,
+        })
+    }
+
+    /// Render pass & pipeline meshes are cached into textures with
+    fn create_cache_pipeline(&self) -> Result<(Arc<RenderPass>, Arc<GraphicsPipeline>), GuiError> {
+        let render_pass = vulkano::single_pass_renderpass!(self.gfx_queue.device().clone(),
+            attachments: {
+                cached_color: {
+                    load: Clear,
+                    store: Store,
+                    format: STATIC_CACHE_FORMAT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                    color: [cached_color],
+                    depth_stencil: {}
+            }
+        )?;
+        // Accumulate premultiplied alpha, so the cached texture can be blended like the primitives it replaces
+        let mut blend = AttachmentBlend::alpha();
+        blend.color_source = BlendFactor::One;
+        blend.alpha_source = BlendFactor::One;
+        let pipeline = Self::create_pipeline_with_blend(
+            self.gfx_queue.clone(),
+            Subpass::from(render_pass.clone(), 0).unwrap().into(),
+            SampleCount::Sample1,
+            blend,
+            ColorComponents::all(),
+            TextureBinding::Single,
+            // Custom shaders & encoding are applied when the cached texture is drawn
+            PipelineConfig { cache: self.pipeline_config.cache.clone(), ..Default::default() },
+        )?;
+        Ok((render_pass, pipeline))
+    }
+
+    /// Enables or disables caching of the whole ui into a texture
+    pub fn set_ui_cache(&mut self, enabled: bool) -> Result<(), GuiError> {
+        if !enabled {
+            self.invalidate_ui_cache();
+            self.ui_cache = None;
+        } else if self.ui_cache.is_none() {
+            let (render_pass, pipeline) = self.create_cache_pipeline()?;
+            self.ui_cache =
+                Some(UiCache { render_pass, pipeline, texture: None, rendered_for: (0.0, [0, 0]) });
+        }
+        Ok(())
+    }
+
+    pub fn has_ui_cache(&self) -> bool {
+        self.ui_cache.is_some()
+    }
+
+    /// Drops the cached ui texture, keeping the cache enabled
+    pub fn invalidate_ui_cache(&mut self) {
+        let texture = self.ui_cache.as_mut().and_then(|cache| cache.texture.take());
+        if let Some((texture_id, _)) = texture {
+            self.unregister_image(texture_id);
+        }
+    }
+
+    /// The cached ui as a textured quad, if it was rendered with the same scale & framebuffer dimensions
+    pub fn cached_ui(
+        &self,
+        scale_factor: f32,
+        framebuffer_dimensions: [u32; 2],
+    ) -> Option<Vec<ClippedPrimitive>> {
+        let cache = self.ui_cache.as_ref()?;
+        if cache.rendered_for != (scale_factor, framebuffer_dimensions) {
+            return None;
+        }
+        let (texture_id, rect) = cache.texture?;
+        let mut quad = Mesh::with_texture(texture_id);
+        quad.add_rect_with_uv(
+            rect,
+            Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+        Some(vec![ClippedPrimitive { clip_rect: rect, primitive: Primitive::Mesh(quad) }])
     }
 
-    /// Registers a user texture. User texture needs to be unregistered when it is no longer needed
-    pub fn register_image(
-        &mut self,
-        image: Arc<dyn ImageViewAbstract + Send + Sync>,
-    ) -> egui::TextureId {
-        let layout = self.pipeline.layout().set_layouts().get(0).unwrap();
-        let desc_set = self.sampled_image_desc_set(layout, image.clone());
-        let id = egui::TextureId::User(self.next_native_tex_id);
-        self.next_native_tex_id += 1;
-        self.texture_desc_sets.insert(id, desc_set);
-        self.texture_images.insert(id, image);
-        id
+    /// Renders `clipped_meshes` into the ui cache & returns the quad drawing it, see [`Renderer::cached_ui`].
+    /// `None` if the ui can't be cached, see [`Renderer::can_draw_offscreen`].
+    pub fn cache_ui(
+        &mut self,
+        clipped_meshes: &[ClippedPrimitive],
+        scale_factor: f32,
+        framebuffer_dimensions: [u32; 2],
+    ) -> Result<Option<Vec<ClippedPrimitive>>, GuiError> {
+        self.invalidate_ui_cache();
+        let cache = match self.ui_cache.take() {
+            Some(cache) => cache,
+            None => return Ok(None),
+        };
+        let texture = if self.can_draw_offscreen(clipped_meshes) && !clipped_meshes.is_empty() {
+            self.render_run_to_texture(
+                &cache.render_pass,
+                &cache.pipeline,
+                clipped_meshes,
+                scale_factor,
+                framebuffer_dimensions,
+            )
+        } else {
+            Ok(None)
+        };
+        // Kept enabled on errors, without a texture
+        self.ui_cache = Some(UiCache {
+            texture: texture.as_ref().ok().copied().flatten(),
+            rendered_for: (scale_factor, framebuffer_dimensions),
+            ..cache
+        });
+        texture?;
+        Ok(self.cached_ui(scale_factor, framebuffer_dimensions))
+    }
+
+    /// Replaces primitive runs that have stayed unchanged with quads textured with their cached rendering.
+    /// Only runs using egui's own textures are cached, because user images may change under the hood.
+    fn apply_static_cache(
+        &mut self,
+        clipped_meshes: &[ClippedPrimitive],
+        scale_factor: f32,
+        framebuffer_dimensions: [u32; 2],
+        textures_replaced: bool,
+    ) -> Result<Vec<ClippedPrimitive>, GuiError> {
+        let mut cache = match self.static_cache.take() {
+            Some(cache) => cache,
+            None => return Ok(clipped_meshes.to_vec()),
+        };
+        cache.generation += 1;
+        if textures_replaced {
+            // Replaced egui textures may change how any of the runs look
+            for entry in cache.entries.values_mut() {
+                entry.unchanged_frames = 0;
+                if let Some((texture_id, _)) = entry.texture.take() {
+                    self.unregister_image(texture_id);
+                }
+            }
+        }
+
+        let mut output = Vec::with_capacity(clipped_meshes.len());
+        let mut run_start = 0;
+        let is_callback =
+            |clipped: &ClippedPrimitive| matches!(clipped.primitive, Primitive::Callback(_));
+        while run_start < clipped_meshes.len() {
+            // Callbacks are drawn as they are & split runs
+            if is_callback(&clipped_meshes[run_start]) {
+                output.push(clipped_meshes[run_start].clone());
+                run_start += 1;
+                continue;
+            }
+            let clip_rect = clipped_meshes[run_start].clip_rect;
+            let run_end = clipped_meshes[run_start..]
+                .iter()
+                .position(|clipped| clipped.clip_rect != clip_rect || is_callback(clipped))
+                .map_or(clipped_meshes.len(), |len| run_start + len);
+            let run = &clipped_meshes[run_start..run_end];
+            run_start = run_end;
+
+            let cacheable = run.iter().all(|clipped| {
+                matches!(&clipped.primitive,
+                    Primitive::Mesh(mesh) if matches!(mesh.texture_id, egui::TextureId::Managed(_)))
+            });
+            if !cacheable {
+                output.extend_from_slice(run);
+                continue;
+            }
+            let key = Self::hash_run(&cache.hasher, run, scale_factor, framebuffer_dimensions);
+            let entry = cache.entries.entry(key).or_default();
+            entry.last_used = cache.generation;
+            entry.unchanged_frames += 1;
+            if entry.texture.is_none() && entry.unchanged_frames >= STATIC_CACHE_MIN_FRAMES {
+                let texture = self.render_run_to_texture(
+                    &cache.render_pass,
+                    &cache.pipeline,
+                    run,
+                    scale_factor,
+                    framebuffer_dimensions,
+                );
+                match texture {
+                    Ok(texture) => entry.texture = texture,
+                    Err(err) => {
+                        self.static_cache = Some(cache);
+                        return Err(err);
+                    }
+                }
+            }
+            match entry.texture {
+                Some((texture_id, rect)) => {
+                    let mut quad = Mesh::with_texture(texture_id);
+                    quad.add_rect_with_uv(
+                        rect,
+                        Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                        egui::Color32::WHITE,
+                    );
+                    output.push(ClippedPrimitive { clip_rect, primitive: Primitive::Mesh(quad) });
+                }
+                None => output.extend_from_slice(run),
+            }
+        }
+
+        // Drop textures of runs that are no longer drawn
+        let mut unused = vec![];
+        let generation = cache.generation;
+        cache.entries.retain(|_, entry| {
+            let used = entry.last_used + 1 >= generation;
+            if !used {
+                unused.extend(entry.texture.map(|(texture_id, _)| texture_id));
+            }
+            used
+        });
+        for texture_id in unused {
+            self.unregister_image(texture_id);
+        }
+        self.static_cache = Some(cache);
+        Ok(output)
+    }
+
+    fn hash_run(
+        hasher: &ahash::RandomState,
+        run: &[ClippedPrimitive],
+        scale_factor: f32,
+        framebuffer_dimensions: [u32; 2],
+    ) -> u64 {
+        use std::hash::{BuildHasher, Hash, Hasher};
+
+        let mut state = hasher.build_hasher();
+        scale_factor.to_bits().hash(&mut state);
+        // Only the part of the run within the framebuffer gets cached, so resizing keeps entries of runs that
+        // fit in both sizes
+        Self::run_pixel_bounds(run, scale_factor, framebuffer_dimensions)
+            .map(|(min, max)| [min[0], min[1], max[0], max[1]].map(f32::to_bits))
+            .hash(&mut state);
+        for ClippedPrimitive { clip_rect, primitive } in run {
+            [clip_rect.min.x, clip_rect.min.y, clip_rect.max.x, clip_rect.max.y]
+                .map(f32::to_bits)
+                .hash(&mut state);
+            if let Primitive::Mesh(mesh) = primitive {
+                mesh.texture_id.hash(&mut state);
+                mesh.indices.hash(&mut state);
+                for v in &mesh.vertices {
+                    [v.pos.x, v.pos.y, v.uv.x, v.uv.y].map(f32::to_bits).hash(&mut state);
+                    v.color.hash(&mut state);
+                }
+            }
+        }
+        state.finish()
+    }
+
+    /// Whether the whole ui can be drawn into an image with the cache pipeline: paint callbacks draw live content on
+    /// the target, YUV images or user images with their own blend mode need pipelines of their own & images still
+    /// uploading (or no longer registered) can't be drawn
+    fn can_draw_offscreen(&self, clipped_meshes: &[ClippedPrimitive]) -> bool {
+        let mut user_textures = self.user_textures.lock().unwrap();
+        clipped_meshes.iter().all(|clipped| match &clipped.primitive {
+            Primitive::Mesh(mesh) => {
+                let registered = self.texture_desc_sets.contains_key(&mesh.texture_id)
+                    || user_textures.desc_sets.contains_key(&mesh.texture_id);
+                registered
+                    && !self.ycbcr_textures.contains_key(&mesh.texture_id)
+                    && !user_textures.blend_modes.contains_key(&mesh.texture_id)
+                    && user_textures.upload_finished(mesh.texture_id)
+            }
+            Primitive::Callback(_) => false,
+        })
+    }
+
+    /// Draws the ui at `render_scale` times the target's resolution, scaled onto the target when drawn. 1 (default)
+    /// draws it directly.
+    pub fn set_render_scale(&mut self, render_scale: f32) -> Result<(), GuiError> {
+        assert!(render_scale > 0.0, "Render scale must be positive, got {}", render_scale);
+        if render_scale == 1.0 {
+            if let Some((_, texture_id)) = self.scaled_target.take().and_then(|target| target.image)
+            {
+                self.unregister_image(texture_id);
+            }
+        } else if let Some(target) = &mut self.scaled_target {
+            target.render_scale = render_scale;
+        } else {
+            let (render_pass, pipeline) = self.create_cache_pipeline()?;
+            self.scaled_target =
+                Some(ScaledTarget { render_pass, pipeline, render_scale, image: None });
+        }
+        Ok(())
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.scaled_target.as_ref().map_or(1.0, |target| target.render_scale)
+    }
+
+    /// Records drawing `clipped_meshes` into the scaled target & returns the quad drawing it onto the target with the
+    /// commands to execute before. `None` if there's no render scale or the ui can't be drawn offscreen.
+    fn draw_scaled(
+        &mut self,
+        clipped_meshes: &[ClippedPrimitive],
+        scale_factor: f32,
+        framebuffer_dimensions: [u32; 2],
+    ) -> Result<Option<(Vec<ClippedPrimitive>, PrimaryAutoCommandBuffer)>, GuiError> {
+        if self.scaled_target.is_none() || !self.can_draw_offscreen(clipped_meshes) {
+            return Ok(None);
+        }
+        let mut target = self.scaled_target.take().unwrap();
+        let dimensions = framebuffer_dimensions
+            .map(|side| ((side as f32 * target.render_scale).round() as u32).max(1));
+        let (image, texture_id) = match target.image.take() {
+            Some((image, texture_id))
+                if image.image().dimensions().width_height() == dimensions =>
+            {
+                (image, texture_id)
+            }
+            previous => {
+                if let Some((_, texture_id)) = previous {
+                    self.unregister_image(texture_id);
+                }
+                let registered = self
+                    .create_offscreen_image(dimensions)
+                    .and_then(|image| Ok((image.clone(), self.register_own_texture(image)?)));
+                match registered {
+                    Ok(registered) => registered,
+                    Err(err) => {
+                        self.scaled_target = Some(target);
+                        return Err(err);
+                    }
+                }
+            }
+        };
+        let rect = Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(
+                framebuffer_dimensions[0] as f32 / scale_factor,
+                framebuffer_dimensions[1] as f32 / scale_factor,
+            ),
+        );
+        let command_buffer = self.record_run(
+            &target.render_pass,
+            &target.pipeline,
+            clipped_meshes,
+            scale_factor * target.render_scale,
+            rect,
+            image.clone(),
+        );
+        target.image = Some((image, texture_id));
+        self.scaled_target = Some(target);
+
+        let mut quad = Mesh::with_texture(texture_id);
+        quad.add_rect_with_uv(
+            rect,
+            Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+        Ok(Some((
+            vec![ClippedPrimitive { clip_rect: rect, primitive: Primitive::Mesh(quad) }],
+            command_buffer,
+        )))
+    }
+
+    /// Pixel aligned bounds of a run's meshes within their clip rects & the framebuffer, `None` if empty
+    fn run_pixel_bounds(
+        run: &[ClippedPrimitive],
+        scale_factor: f32,
+        framebuffer_dimensions: [u32; 2],
+    ) -> Option<([f32; 2], [f32; 2])> {
+        let mut bounds = Rect::NOTHING;
+        for ClippedPrimitive { clip_rect, primitive } in run {
+            if let Primitive::Mesh(mesh) = primitive {
+                bounds = bounds.union(mesh.calc_bounds().intersect(*clip_rect));
+            }
+        }
+        let min = [
+            (bounds.min.x * scale_factor).floor().max(0.0),
+            (bounds.min.y * scale_factor).floor().max(0.0),
+        ];
+        let max = [
+            (bounds.max.x * scale_factor).ceil().min(framebuffer_dimensions[0] as f32),
+            (bounds.max.y * scale_factor).ceil().min(framebuffer_dimensions[1] as f32),
+        ];
+        if max[0] <= min[0] || max[1] <= min[1] {
+            return None;
+        }
+        Some((min, max))
+    }
+
+    /// Renders a run of meshes (or the whole ui) into a new texture covering their pixel aligned bounds.
+    /// Returns the registered texture & the rect (in points) it should be drawn at.
+    fn render_run_to_texture(
+        &mut self,
+        render_pass: &Arc<RenderPass>,
+        pipeline: &Arc<GraphicsPipeline>,
+        run: &[ClippedPrimitive],
+        scale_factor: f32,
+        framebuffer_dimensions: [u32; 2],
+    ) -> Result<Option<(egui::TextureId, Rect)>, GuiError> {
+        let all_registered = run.iter().all(|clipped| match &clipped.primitive {
+            Primitive::Mesh(mesh) => self.is_image_registered(mesh.texture_id),
+            Primitive::Callback(_) => true,
+        });
+        if !all_registered {
+            return Ok(None);
+        }
+        let (min, max) = match Self::run_pixel_bounds(run, scale_factor, framebuffer_dimensions) {
+            Some(bounds) => bounds,
+            None => return Ok(None),
+        };
+        let dimensions = [(max[0] - min[0]) as u32, (max[1] - min[1]) as u32];
+        let rect = Rect::from_min_max(
+            egui::pos2(min[0] / scale_factor, min[1] / scale_factor),
+            egui::pos2(max[0] / scale_factor, max[1] / scale_factor),
+        );
+
+        let image = self.create_offscreen_image(dimensions)?;
+        let command_buffer =
+            self.record_run(render_pass, pipeline, run, scale_factor, rect, image.clone());
+        let texture_id = self.register_own_texture(image)?;
+        self.offscreen_draws.push(command_buffer);
+        Ok(Some((texture_id, rect)))
+    }
+
+    /// Chains the draws of cached runs after `before_future`
+    fn submit_offscreen_draws(
+        &mut self,
+        mut before_future: Box<dyn GpuFuture>,
+    ) -> Result<Box<dyn GpuFuture>, GuiError> {
+        for offscreen_draw in std::mem::take(&mut self.offscreen_draws) {
+            before_future =
+                Box::new(before_future.then_execute(self.gfx_queue.clone(), offscreen_draw)?);
+        }
+        Ok(before_future)
+    }
+
+    /// Registers an image rendered by the renderer, kept with its own textures so that other windows' renderers
+    /// don't list it as a user image
+    fn register_own_texture(
+        &mut self,
+        image: Arc<ImageView<AttachmentImage>>,
+    ) -> Result<egui::TextureId, GuiError> {
+        let egui_pipeline = self.pipeline()?;
+        let layout = egui_pipeline.layout().set_layouts().get(0).unwrap();
+        let desc_set = self.sampled_image_desc_set(layout, image.clone())?;
+        let texture_id = {
+            let mut user_textures = self.user_textures.lock().unwrap();
+            user_textures.next_id += 1;
+            egui::TextureId::User(user_textures.next_id - 1)
+        };
+        self.texture_desc_sets.insert(texture_id, desc_set);
+        self.texture_images.insert(texture_id, image);
+        self.own_textures_changed();
+        Ok(texture_id)
+    }
+
+    /// Image of the static cache, ui cache or scaled target, rendered to & then sampled like a texture
+    fn create_offscreen_image(
+        &self,
+        dimensions: [u32; 2],
+    ) -> Result<Arc<ImageView<AttachmentImage>>, GuiError> {
+        let image = AttachmentImage::with_usage(
+            self.gfx_queue.device().clone(),
+            dimensions,
+            STATIC_CACHE_FORMAT,
+            ImageUsage { sampled: true, color_attachment: true, ..ImageUsage::none() },
+        )
+        .map_err(|err| GuiError::ImageCreation(err.into()))?;
+        Ok(ImageView::new_default(image)?)
+    }
+
+    /// Records drawing `run` into `image`, which covers `rect` (in points) at `scale_factor` & is cleared first
+    fn record_run(
+        &mut self,
+        render_pass: &Arc<RenderPass>,
+        pipeline: &Arc<GraphicsPipeline>,
+        run: &[ClippedPrimitive],
+        scale_factor: f32,
+        rect: Rect,
+        image: Arc<ImageView<AttachmentImage>>,
+    ) -> PrimaryAutoCommandBuffer {
+        let dimensions = image.image().dimensions().width_height();
+        let framebuffer = Framebuffer::new(render_pass.clone(), FramebufferCreateInfo {
+            attachments: vec![image],
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Gather run's geometry relative to the texture's origin
+        let offset = rect.min.to_vec2();
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut draws = vec![];
+        for ClippedPrimitive { clip_rect, primitive } in run {
+            if let Primitive::Mesh(mesh) = primitive {
+                draws.push((
+                    *clip_rect,
+                    mesh.texture_id,
+                    indices.len() as u32,
+                    mesh.indices.len() as u32,
+                    vertices.len() as i32,
+                ));
+                let vertex_start = vertices.len();
+                vertices.extend(mesh.vertices.iter().map(|v| EguiVertex::from_egui(v, offset)));
+                if self.analytic_anti_aliasing {
+                    feathering_to_coverage(&mut vertices[vertex_start..], &mesh.indices);
+                }
+                indices.extend_from_slice(&mesh.indices);
+            }
+        }
+        let vertex_buffer = self.vertex_pool.chunk(vertices).unwrap();
+        let index_buffer = self.index_pool.chunk(indices).unwrap();
+
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            self.gfx_queue.device().clone(),
+            self.gfx_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        cbb.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([0.0; 4].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            SubpassContents::Inline,
+        )
+        .unwrap();
+        let mut push_constants = vs::ty::PushConstants {
+            projection: orthographic_projection([rect.width(), rect.height()]),
+            uv_rect: FULL_UV_RECT,
+            texture_params: COLOR_CONVERSION_NONE,
+        };
+        cbb.bind_pipeline_graphics(pipeline.clone())
+            .set_viewport(0, vec![Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+                depth_range: 0.0..1.0,
+            }])
+            .bind_vertex_buffers(0, vertex_buffer)
+            .bind_index_buffer(index_buffer);
+        for (clip_rect, texture_id, first_index, index_count, vertex_offset) in draws {
+            let desc_set = self.texture_desc_set(texture_id).unwrap();
+            self.set_texture_constants(texture_id, &mut push_constants);
+            cbb.set_scissor(0, vec![self.get_rect_scissor(
+                scale_factor,
+                dimensions,
+                clip_rect.translate(-offset),
+            )])
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                pipeline.layout().clone(),
+                0,
+                DescriptorSetWithOffsets::new(desc_set, std::iter::empty()),
+            )
+            .push_constants(pipeline.layout().clone(), 0, push_constants)
+            .draw_indexed(index_count, 1, first_index, vertex_offset, 0)
+            .unwrap();
+        }
+        cbb.end_render_pass().unwrap();
+        cbb.build().unwrap()
+    }
+
+    /// Stages the pixels of all texture deltas of the frame in one chunk of the staging ring, & uploads them with one
+    /// command buffer
+    fn update_textures(&mut self, textures_delta: &TexturesDelta) -> Result<(), GuiError> {
+        self.staging_buffers = 0;
+        self.staging_buffer_size = 0;
+        if textures_delta.set.is_empty() {
+            return Ok(());
+        }
+        // Extract pixel data from egui
+        let mut data = Vec::new();
+        let mut buffer_offsets = Vec::with_capacity(textures_delta.set.len());
+        for (_, delta) in &textures_delta.set {
+            buffer_offsets.push(data.len() as DeviceSize);
+            match &delta.image {
+                egui::ImageData::Color(image) => {
+                    assert_eq!(
+                        image.width() * image.height(),
+                        image.pixels.len(),
+                        "Mismatch between texture size and texel count"
+                    );
+                    data.extend(image.pixels.iter().flat_map(|color| color.to_array()));
+                }
+                egui::ImageData::Font(image) => {
+                    let gamma = 1.0;
+                    data.extend(image.srgba_pixels(gamma).flat_map(|color| color.to_array()));
+                }
+            }
+        }
+        self.staging_buffers = 1;
+        self.staging_buffer_size = data.len() as DeviceSize;
+        // Room for the uploads of each frame in flight, so that the ring isn't reallocated while they're in use
+        self.staging_pool
+            .reserve(data.len() as DeviceSize * self.frames_in_flight as DeviceSize)
+            .unwrap();
+        let staging_buffer = self.staging_pool.chunk(data).unwrap();
+
+        // Create command buffer builder
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            self.gfx_queue.device().clone(),
+            self.gfx_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        // Images created in this command buffer are written through their initialization, also by later deltas
+        let mut created: AHashMap<egui::TextureId, Arc<dyn ImageAccess>> = AHashMap::default();
+        for ((texture_id, delta), buffer_offset) in textures_delta.set.iter().zip(buffer_offsets) {
+            let (dst_image, pos) = match delta.pos {
+                // Copy texture data to existing image if delta pos exists (e.g. font changed)
+                Some(pos) => {
+                    let existing_image = created.get(texture_id).cloned().or_else(|| {
+                        self.texture_images.get(texture_id).map(|image| image.image().clone())
+                    });
+                    match existing_image {
+                        Some(existing_image) => (existing_image, [pos[0] as u32, pos[1] as u32]),
+                        None => continue,
+                    }
+                }
+                // Otherwise create the image
+                None => {
+                    let (img, init) = ImmutableImage::uninitialized(
+                        self.gfx_queue.device().clone(),
+                        vulkano::image::ImageDimensions::Dim2d {
+                            width: delta.image.width() as u32,
+                            height: delta.image.height() as u32,
+                            array_layers: 1,
+                        },
+                        TEXTURE_FORMAT,
+                        vulkano::image::MipmapsCount::One,
+                        ImageUsage {
+                            transfer_dst: true,
+                            transfer_src: true,
+                            sampled: true,
+                            ..ImageUsage::none()
+                        },
+                        Default::default(),
+                        ImageLayout::ShaderReadOnlyOptimal,
+                        Some(self.gfx_queue.family()),
+                    )?;
+                    let image = ImageView::new_default(img)?;
+                    let pipeline = self.pipeline()?;
+                    let layout = pipeline.layout().set_layouts().get(0).unwrap();
+                    let desc_set = self.sampled_image_desc_set(layout, image.clone())?;
+                    self.texture_desc_sets.insert(*texture_id, desc_set);
+                    self.texture_images.insert(*texture_id, image);
+                    self.own_textures_changed();
+                    created.insert(*texture_id, init.clone());
+                    (init as Arc<dyn ImageAccess>, [0, 0])
+                }
+            };
+            cbb.copy_buffer_to_image(CopyBufferToImageInfo {
+                regions: [BufferImageCopy {
+                    buffer_offset,
+                    image_subresource: dst_image.subresource_layers(),
+                    image_offset: [pos[0], pos[1], 0],
+                    image_extent: [delta.image.width() as u32, delta.image.height() as u32, 1],
+                    ..Default::default()
+                }]
+                .into(),
+                ..CopyBufferToImageInfo::buffer_image(staging_buffer.clone(), dst_image)
+            })?;
+        }
+        // Execute command buffer
+        let command_buffer = cbb.build()?;
+        let finished = command_buffer.execute(self.gfx_queue.clone())?;
+        let _fut = finished.then_signal_fence_and_flush()?;
+        Ok(())
+    }
+
+    /// Copies a region of `image` to host memory after `before_future` (e.g. the draw on it) and waits for the copy
+    /// to finish. Returns tightly packed texel data of the region. The image must have `transfer_src` usage.
+    pub fn read_image_region<F>(
+        &self,
+        before_future: F,
+        image: Arc<dyn ImageAccess>,
+        offset: [u32; 2],
+        extent: [u32; 2],
+    ) -> Result<Vec<u8>, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        use vulkano::command_buffer::CopyImageToBufferInfo;
+
+        let texel_size =
+            image.format().block_size().ok_or(GuiError::UnreadableFormat(image.format()))?;
+        let buffer = unsafe {
+            CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+                self.gfx_queue.device().clone(),
+                extent[0] as DeviceSize * extent[1] as DeviceSize * texel_size,
+                BufferUsage::transfer_dst(),
+                true,
+            )
+            .map_err(GuiError::ReadbackBuffer)?
+        };
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            self.gfx_queue.device().clone(),
+            self.gfx_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        cbb.copy_image_to_buffer(CopyImageToBufferInfo {
+            regions: [BufferImageCopy {
+                image_subresource: image.subresource_layers(),
+                image_offset: [offset[0], offset[1], 0],
+                image_extent: [extent[0], extent[1], 1],
+                ..Default::default()
+            }]
+            .into(),
+            ..CopyImageToBufferInfo::image_buffer(image, buffer.clone())
+        })?;
+        let command_buffer = cbb.build()?;
+        // The future is dropped once waited on, releasing its lock of the buffer
+        before_future
+            .then_execute(self.gfx_queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+        let data =
+            buffer.read().expect("readback buffer is unlocked once the copy finished").to_vec();
+        Ok(data)
     }
 
-    /// Unregister user texture.
-    pub fn unregister_image(&mut self, texture_id: egui::TextureId) {
-        self.texture_desc_sets.remove(&texture_id);
-        self.texture_images.remove(&texture_id);
+    /// Re-uploads the texel data of a user image registered with a vulkano image view, see
+    /// [`Gui::update_user_image`](crate::Gui::update_user_image)
+    pub fn update_image(
+        &mut self,
+        texture_id: egui::TextureId,
+        byte_data: &[u8],
+    ) -> Result<(), GuiError> {
+        let dimensions = self
+            .user_textures
+            .lock()
+            .unwrap()
+            .images
+            .get(&texture_id)
+            .ok_or(GuiError::UnknownTexture(texture_id))?
+            .image()
+            .dimensions()
+            .width_height();
+        self.update_image_region(texture_id, [0, 0], dimensions, byte_data)
     }
 
-    fn update_texture(&mut self, texture_id: egui::TextureId, delta: &egui::epaint::ImageDelta) {
-        // Extract pixel data from egui
-        let data: Vec<u8> = match &delta.image {
-            egui::ImageData::Color(image) => {
-                assert_eq!(
-                    image.width() * image.height(),
-                    image.pixels.len(),
-                    "Mismatch between texture size and texel count"
-                );
-                image.pixels.iter().flat_map(|color| color.to_array()).collect()
-            }
-            egui::ImageData::Font(image) => {
-                let gamma = 1.0;
-                image.srgba_pixels(gamma).flat_map(|color| color.to_array()).collect()
-            }
-        };
-        // Create buffer to be copied to the image
-        let texture_data_buffer = CpuAccessibleBuffer::from_iter(
+    /// Re-uploads a region of a user image, see
+    /// [`Gui::update_user_image_region`](crate::Gui::update_user_image_region)
+    pub fn update_image_region(
+        &mut self,
+        texture_id: egui::TextureId,
+        offset: [u32; 2],
+        extent: [u32; 2],
+        byte_data: &[u8],
+    ) -> Result<(), GuiError> {
+        let image = self
+            .user_textures
+            .lock()
+            .unwrap()
+            .images
+            .get(&texture_id)
+            .cloned()
+            .ok_or(GuiError::UnknownTexture(texture_id))?;
+        self.staging_buffers += 1;
+        self.staging_buffer_size += byte_data.len() as DeviceSize;
+        let buffer = CpuAccessibleBuffer::from_iter(
             self.gfx_queue.device().clone(),
             BufferUsage::transfer_src(),
             false,
-            data,
-        )
-        .unwrap();
-        // Create image
-        let (img, init) = ImmutableImage::uninitialized(
-            self.gfx_queue.device().clone(),
-            vulkano::image::ImageDimensions::Dim2d {
-                width: delta.image.width() as u32,
-                height: delta.image.height() as u32,
-                array_layers: 1,
-            },
-            Format::R8G8B8A8_SRGB,
-            vulkano::image::MipmapsCount::One,
-            ImageUsage {
-                transfer_dst: true,
-                transfer_src: true,
-                sampled: true,
-                ..ImageUsage::none()
-            },
-            Default::default(),
-            ImageLayout::ShaderReadOnlyOptimal,
-            Some(self.gfx_queue.family()),
+            byte_data.iter().copied(),
         )
         .unwrap();
-        let font_image = ImageView::new_default(img).unwrap();
-
-        // Create command buffer builder
         let mut cbb = AutoCommandBufferBuilder::primary(
             self.gfx_queue.device().clone(),
             self.gfx_queue.family(),
             CommandBufferUsage::OneTimeSubmit,
         )
         .unwrap();
+        // Tightly packed rows of the region in the first mip level
+        cbb.copy_buffer_to_image(CopyBufferToImageInfo {
+            regions: [BufferImageCopy {
+                image_subresource: image.image().subresource_layers(),
+                image_offset: [offset[0], offset[1], 0],
+                image_extent: [extent[0], extent[1], 1],
+                ..Default::default()
+            }]
+            .into(),
+            ..CopyBufferToImageInfo::buffer_image(buffer, image.image().clone())
+        })?;
+        let command_buffer = cbb.build().unwrap();
+        let finished = command_buffer.execute(self.gfx_queue.clone())?;
+        let _fut = finished.then_signal_fence_and_flush()?;
+        self.user_textures.lock().unwrap().updates += 1;
+        Ok(())
+    }
 
-        // Copy buffer to image
-        cbb.copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
-            texture_data_buffer,
-            init.clone(),
-        ))
-        .unwrap();
-
-        // Blit texture data to existing image if delta pos exists (e.g. font changed)
-        if let Some(pos) = delta.pos {
-            if let Some(existing_image) = self.texture_images.get(&texture_id) {
-                let src_dims = font_image.image().dimensions();
-                let top_left = [pos[0] as u32, pos[1] as u32, 0];
-                let bottom_right = [
-                    pos[0] as u32 + src_dims.width() as u32,
-                    pos[1] as u32 + src_dims.height() as u32,
-                    1,
-                ];
-
-                cbb.blit_image(BlitImageInfo {
-                    src_image_layout: ImageLayout::General,
-                    dst_image_layout: ImageLayout::General,
-                    regions: [ImageBlit {
-                        src_subresource: font_image.image().subresource_layers(),
-                        src_offsets: [[0, 0, 0], [
-                            src_dims.width() as u32,
-                            src_dims.height() as u32,
-                            1,
-                        ]],
-                        dst_subresource: existing_image.image().subresource_layers(),
-                        dst_offsets: [top_left, bottom_right],
-                        ..Default::default()
-                    }]
-                    .into(),
-                    filter: Filter::Nearest,
-                    ..BlitImageInfo::images(
-                        font_image.image().clone(),
-                        existing_image.image().clone(),
-                    )
-                })
-                .unwrap();
+    /// Same as `read_image_region`, but the texels are converted to RGBA. Only 8 bit per channel RGBA & BGRA
+    /// formats can be read.
+    pub(crate) fn read_rgba_region<F>(
+        &self,
+        before_future: F,
+        image: Arc<dyn ImageAccess>,
+        offset: [u32; 2],
+        extent: [u32; 2],
+    ) -> Result<Vec<u8>, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        let bgra = match image.format() {
+            Format::R8G8B8A8_SRGB
+            | Format::R8G8B8A8_UNORM
+            | Format::A8B8G8R8_SRGB_PACK32
+            | Format::A8B8G8R8_UNORM_PACK32 => false,
+            Format::B8G8R8A8_SRGB | Format::B8G8R8A8_UNORM => true,
+            format => return Err(GuiError::UnreadableFormat(format)),
+        };
+        let mut bytes = self.read_image_region(before_future, image, offset, extent)?;
+        if bgra {
+            for texel in bytes.chunks_exact_mut(4) {
+                texel.swap(0, 2);
             }
-            // Otherwise save the newly created image
-        } else {
-            let layout = self.pipeline.layout().set_layouts().get(0).unwrap();
-            let font_desc_set = self.sampled_image_desc_set(layout, font_image.clone());
-            self.texture_desc_sets.insert(texture_id, font_desc_set);
-            self.texture_images.insert(texture_id, font_image);
         }
-        // Execute command buffer
-        let command_buffer = cbb.build().unwrap();
-        let finished = command_buffer.execute(self.gfx_queue.clone()).unwrap();
-        let _fut = finished.then_signal_fence_and_flush().unwrap();
+        Ok(bytes)
     }
 
     fn get_rect_scissor(
@@ -396,74 +2769,77 @@ impl Renderer {
         }
     }
 
-    fn resize_allocations(&mut self, new_vertices_size: DeviceSize, new_indices_size: DeviceSize) {
-        let vertex_buffer = unsafe {
-            CpuAccessibleBuffer::<[EguiVertex]>::uninitialized_array(
-                self.gfx_queue.device().clone(),
-                new_vertices_size,
-                BufferUsage::vertex_buffer(),
-                false,
-            )
-            .expect("failed to create gui vertex buffer")
-        };
-        let index_buffer = unsafe {
-            CpuAccessibleBuffer::<[u32]>::uninitialized_array(
-                self.gfx_queue.device().clone(),
-                new_indices_size,
-                BufferUsage::index_buffer(),
-                false,
-            )
-            .expect("failed to create gui vertex buffer")
-        };
-        self.vertex_buffer = vertex_buffer;
-        self.index_buffer = index_buffer;
-    }
-
-    fn copy_mesh(&self, mesh: &Mesh, vertex_start: DeviceSize, index_start: DeviceSize) {
-        // Copy vertices to buffer
-        let v_slice = &mesh.vertices;
-        let mut vertex_content = self.vertex_buffer.write().unwrap();
-        let mut slice_i = 0;
-        for i in vertex_start..(vertex_start + v_slice.len() as DeviceSize) {
-            let v = v_slice[slice_i];
-            vertex_content[i as usize] = EguiVertex {
-                position: [v.pos.x, v.pos.y],
-                tex_coords: [v.uv.x, v.uv.y],
-                color: [
-                    v.color.r() as f32 / 255.0,
-                    v.color.g() as f32 / 255.0,
-                    v.color.b() as f32 / 255.0,
-                    v.color.a() as f32 / 255.0,
-                ],
-            };
-            slice_i += 1;
-        }
-        // Copy indices to buffer
-        let i_slice = &mesh.indices;
-        let mut index_content = self.index_buffer.write().unwrap();
-        slice_i = 0;
-        for i in index_start..(index_start + i_slice.len() as DeviceSize) {
-            let index = i_slice[slice_i];
-            index_content[i as usize] = index;
-            slice_i += 1;
+    /// Appends the vertices & indices of `mesh` to the geometry of the draw, indices staying relative to the mesh
+    fn append_mesh(
+        &self,
+        mesh: &Mesh,
+        scale_factor: f32,
+        vertices: &mut Vec<EguiVertex>,
+        indices: &mut Vec<u32>,
+    ) {
+        let vertex_start = vertices.len();
+        vertices.extend(mesh.vertices.iter().map(|v| {
+            let mut v = *v;
+            // Snap untextured geometry (rects, lines...), but not glyphs which would get distorted
+            if self.pixel_snapping && v.uv == egui::epaint::WHITE_UV {
+                v.pos = ((v.pos.to_vec2() * scale_factor).round() / scale_factor).to_pos2();
+            }
+            EguiVertex::from_egui(&v, egui::Vec2::ZERO)
+        }));
+        if self.analytic_anti_aliasing {
+            feathering_to_coverage(&mut vertices[vertex_start..], &mesh.indices);
         }
-    }
-
-    fn resize_needed(&self, vertex_end: DeviceSize, index_end: DeviceSize) -> bool {
-        vertex_end >= self.vertex_buffer.len() || index_end >= self.index_buffer.len()
+        indices.extend_from_slice(&mesh.indices);
     }
 
     fn create_secondary_command_buffer_builder(
         &self,
     ) -> AutoCommandBufferBuilder<SecondaryAutoCommandBuffer> {
+        let SubpassInheritance { subpass, framebuffer, occlusion_query, query_statistics_flags } =
+            self.subpass_inheritance.clone();
+        let inheritance_info = match &self.pipeline_render_pass {
+            // Our own render pass has nothing to configure
+            PipelineRenderPassType::BeginRenderPass(own_subpass) if self.render_pass.is_some() => {
+                CommandBufferInheritanceInfo {
+                    render_pass: Some(own_subpass.clone().into()),
+                    ..Default::default()
+                }
+            }
+            PipelineRenderPassType::BeginRenderPass(own_subpass) => CommandBufferInheritanceInfo {
+                render_pass: Some(
+                    CommandBufferInheritanceRenderPassInfo {
+                        subpass: subpass.unwrap_or_else(|| own_subpass.clone()),
+                        framebuffer,
+                    }
+                    .into(),
+                ),
+                occlusion_query,
+                query_statistics_flags,
+                ..Default::default()
+            },
+            PipelineRenderPassType::BeginRendering(rendering_info) => {
+                CommandBufferInheritanceInfo {
+                    render_pass: Some(
+                        CommandBufferInheritanceRenderingInfo {
+                            color_attachment_formats: rendering_info
+                                .color_attachment_formats
+                                .clone(),
+                            rasterization_samples: self.sample_count,
+                            ..Default::default()
+                        }
+                        .into(),
+                    ),
+                    occlusion_query,
+                    query_statistics_flags,
+                    ..Default::default()
+                }
+            }
+        };
         AutoCommandBufferBuilder::secondary(
             self.gfx_queue.device().clone(),
             self.gfx_queue.family(),
             CommandBufferUsage::MultipleSubmit,
-            CommandBufferInheritanceInfo {
-                render_pass: Some(self.subpass.clone().into()),
-                ..Default::default()
-            },
+            inheritance_info,
         )
         .unwrap()
     }
@@ -475,6 +2851,27 @@ impl Renderer {
     ) -> (AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, [u32; 2]) {
         // Get dimensions
         let img_dims = final_image.image().dimensions().width_height();
+        let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
+            self.gfx_queue.device().clone(),
+            self.gfx_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        if self.is_dynamic_rendering() {
+            command_buffer_builder
+                .begin_rendering(RenderingInfo {
+                    color_attachments: vec![Some(RenderingAttachmentInfo {
+                        load_op: if self.is_overlay { LoadOp::Load } else { LoadOp::Clear },
+                        store_op: StoreOp::Store,
+                        clear_value: self.clear_color().map(Into::into),
+                        ..RenderingAttachmentInfo::image_view(final_image)
+                    })],
+                    contents: SubpassContents::SecondaryCommandBuffers,
+                    ..Default::default()
+                })
+                .unwrap();
+            return (command_buffer_builder, img_dims);
+        }
         // Create framebuffer (must be in same order as render pass description in `new`
         let framebuffer = Framebuffer::new(
             self.render_pass
@@ -487,17 +2884,11 @@ impl Renderer {
             FramebufferCreateInfo { attachments: vec![final_image], ..Default::default() },
         )
         .unwrap();
-        let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
-            self.gfx_queue.device().clone(),
-            self.gfx_queue.family(),
-            CommandBufferUsage::OneTimeSubmit,
-        )
-        .unwrap();
         // Add clear values here for attachments and begin render pass
         command_buffer_builder
             .begin_render_pass(
                 RenderPassBeginInfo {
-                    clear_values: vec![if !self.is_overlay { Some([0.0; 4].into()) } else { None }],
+                    clear_values: vec![self.clear_color().map(Into::into)],
                     ..RenderPassBeginInfo::framebuffer(framebuffer)
                 },
                 SubpassContents::SecondaryCommandBuffers,
@@ -514,27 +2905,49 @@ impl Renderer {
         scale_factor: f32,
         before_future: F,
         final_image: Arc<dyn ImageViewAbstract + 'static>,
-    ) -> Box<dyn GpuFuture>
+    ) -> Result<Box<dyn GpuFuture>, GuiError>
     where
         F: GpuFuture + 'static,
     {
-        for (id, image_delta) in &textures_delta.set {
-            self.update_texture(*id, image_delta);
-        }
+        self.advance_frame();
+        self.update_textures(textures_delta)?;
 
+        let textures_replaced = textures_delta.set.iter().any(|(_, delta)| delta.pos.is_none());
         let (mut command_buffer_builder, framebuffer_dimensions) = self.start(final_image);
-        let mut builder = self.create_secondary_command_buffer_builder();
-        self.draw_egui(scale_factor, clipped_meshes, framebuffer_dimensions, &mut builder);
+        let scaled = self.draw_scaled(clipped_meshes, scale_factor, framebuffer_dimensions)?;
+        let (clipped_meshes, scaled_draw) = match scaled {
+            Some((quad, command_buffer)) => (Cow::Owned(quad), Some(command_buffer)),
+            None => (Cow::Borrowed(clipped_meshes), None),
+        };
+        let builder = self.create_secondary_command_buffer_builder();
+        let builder = self.draw_egui(
+            scale_factor,
+            &clipped_meshes,
+            framebuffer_dimensions,
+            textures_replaced,
+            builder,
+        )?;
         // Execute draw commands
-        let command_buffer = builder.build().unwrap();
+        let command_buffer = builder.build()?;
         command_buffer_builder.execute_commands(command_buffer).unwrap();
-        let done_future = self.finish(command_buffer_builder, Box::new(before_future));
+        let before_future = self.submit_offscreen_draws(before_future.boxed())?;
+        let before_future: Box<dyn GpuFuture> = match self.upload_geometry() {
+            Some(upload) => Box::new(before_future.then_execute(self.gfx_queue.clone(), upload)?),
+            None => before_future,
+        };
+        let before_future: Box<dyn GpuFuture> = match scaled_draw {
+            Some(scaled_draw) => {
+                Box::new(before_future.then_execute(self.gfx_queue.clone(), scaled_draw)?)
+            }
+            None => before_future,
+        };
+        let done_future = self.finish(command_buffer_builder, before_future);
 
         for &id in &textures_delta.free {
             self.unregister_image(id);
         }
 
-        done_future
+        Ok(done_future?)
     }
 
     // Finishes the rendering pipeline
@@ -542,17 +2955,20 @@ impl Renderer {
         &self,
         mut command_buffer_builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
         before_main_cb_future: Box<dyn GpuFuture>,
-    ) -> Box<dyn GpuFuture> {
+    ) -> Result<Box<dyn GpuFuture>, FlushError> {
         // We end render pass
-        command_buffer_builder.end_render_pass().unwrap();
+        if self.is_dynamic_rendering() {
+            command_buffer_builder.end_rendering().unwrap();
+        } else {
+            command_buffer_builder.end_render_pass().unwrap();
+        }
         // Then execute our whole command buffer
         let command_buffer = command_buffer_builder.build().unwrap();
         let after_main_cb =
             before_main_cb_future.then_execute(self.gfx_queue.clone(), command_buffer).unwrap();
-        let future =
-            after_main_cb.then_signal_fence_and_flush().expect("Failed to signal fence and flush");
+        let future = after_main_cb.then_signal_fence_and_flush()?;
         // Return our future
-        Box::new(future)
+        Ok(Box::new(future))
     }
 
     pub fn draw_on_subpass_image(
@@ -561,17 +2977,34 @@ impl Renderer {
         textures_delta: &TexturesDelta,
         scale_factor: f32,
         framebuffer_dimensions: [u32; 2],
-    ) -> SecondaryAutoCommandBuffer {
-        for (id, image_delta) in &textures_delta.set {
-            self.update_texture(*id, image_delta);
+    ) -> Result<SecondaryAutoCommandBuffer, GuiError> {
+        self.advance_frame();
+        self.update_textures(textures_delta)?;
+        let textures_replaced = textures_delta.set.iter().any(|(_, delta)| delta.pos.is_none());
+        let builder = self.create_secondary_command_buffer_builder();
+        let builder = self.draw_egui(
+            scale_factor,
+            clipped_meshes,
+            framebuffer_dimensions,
+            textures_replaced,
+            builder,
+        )?;
+        let buffer = builder.build()?;
+        // The command buffer is executed by the caller, so the draws & the copy it depends on are waited for
+        let mut pending = !self.offscreen_draws.is_empty();
+        let mut future = self
+            .submit_offscreen_draws(vulkano::sync::now(self.gfx_queue.device().clone()).boxed())?;
+        if let Some(upload) = self.upload_geometry() {
+            future = Box::new(future.then_execute(self.gfx_queue.clone(), upload)?);
+            pending = true;
+        }
+        if pending {
+            future.then_signal_fence_and_flush()?.wait(None)?;
         }
-        let mut builder = self.create_secondary_command_buffer_builder();
-        self.draw_egui(scale_factor, clipped_meshes, framebuffer_dimensions, &mut builder);
-        let buffer = builder.build().unwrap();
         for &id in &textures_delta.free {
             self.unregister_image(id);
         }
-        buffer
+        Ok(buffer)
     }
 
     fn draw_egui(
@@ -579,17 +3012,77 @@ impl Renderer {
         scale_factor: f32,
         clipped_meshes: &[ClippedPrimitive],
         framebuffer_dimensions: [u32; 2],
-        builder: &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
-    ) {
-        let push_constants = vs::ty::PushConstants {
-            screen_size: [
-                framebuffer_dimensions[0] as f32 / scale_factor,
-                framebuffer_dimensions[1] as f32 / scale_factor,
-            ],
+        textures_replaced: bool,
+        mut builder: AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+    ) -> Result<AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>, GuiError> {
+        let cached_meshes;
+        let clipped_meshes = if self.static_cache.is_some() {
+            cached_meshes = self.apply_static_cache(
+                clipped_meshes,
+                scale_factor,
+                framebuffer_dimensions,
+                textures_replaced,
+            )?;
+            &cached_meshes[..]
+        } else {
+            clipped_meshes
+        };
+
+        let mut push_constants = vs::ty::PushConstants {
+            projection: self.projection.unwrap_or_else(|| {
+                orthographic_projection([
+                    framebuffer_dimensions[0] as f32 / scale_factor,
+                    framebuffer_dimensions[1] as f32 / scale_factor,
+                ])
+            }),
+            uv_rect: FULL_UV_RECT,
+            texture_params: COLOR_CONVERSION_NONE,
+        };
+
+        // Geometry of all meshes is written to one chunk of each ring at once
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut mesh_ranges = Vec::new();
+        for ClippedPrimitive { primitive, .. } in clipped_meshes {
+            if let Primitive::Mesh(mesh) = primitive {
+                if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+                    continue;
+                }
+                let (vertex_start, index_start) = (vertices.len(), indices.len());
+                self.append_mesh(mesh, scale_factor, &mut vertices, &mut indices);
+                mesh_ranges.push((
+                    vertex_start as DeviceSize..vertices.len() as DeviceSize,
+                    index_start as DeviceSize..indices.len() as DeviceSize,
+                ));
+            }
+        }
+        let geometry = if mesh_ranges.is_empty() {
+            None
+        } else {
+            let (vertices_len, indices_len) =
+                (vertices.len() as DeviceSize, indices.len() as DeviceSize);
+            // Room for the geometry of each frame in flight, so that the rings wrap around instead of growing
+            let frames_in_flight = self.frames_in_flight as DeviceSize;
+            self.vertex_pool.reserve(vertices_len * frames_in_flight).unwrap();
+            self.index_pool.reserve(indices_len * frames_in_flight).unwrap();
+            let host_buffers = (
+                self.vertex_pool.chunk(vertices).unwrap(),
+                self.index_pool.chunk(indices).unwrap(),
+            );
+            let device_buffers = self.device_buffers(vertices_len, indices_len);
+            Some((host_buffers, device_buffers))
         };
+        let mut mesh_ranges = mesh_ranges.into_iter();
 
-        let mut vertex_start = 0;
-        let mut index_start = 0;
+        let pipeline = self.pipeline()?;
+        self.update_bindless_set();
+        self.update_blend_pipelines()?;
+        let bindless = self.bindless.as_ref().and_then(|bindless| {
+            let desc_set = bindless.desc_set.clone()?;
+            Some((bindless.pipeline.clone(), desc_set, bindless.indices.clone()))
+        });
+        // The texture array is bound once, until another pipeline or a paint callback is
+        let mut bindless_bound = false;
         for ClippedPrimitive { clip_rect, primitive } in clipped_meshes {
             match primitive {
                 Primitive::Mesh(mesh) => {
@@ -597,45 +3090,52 @@ impl Renderer {
                     if mesh.vertices.is_empty() || mesh.indices.is_empty() {
                         continue;
                     }
-                    if self.texture_desc_sets.get(&mesh.texture_id).is_none() {
-                        eprintln!("This texture no longer exists {:?}", mesh.texture_id);
-                        continue;
-                    }
+                    let (vertex_range, index_range) = mesh_ranges.next().unwrap();
+                    let (host_buffers, device_buffers) = geometry.as_ref().unwrap();
+                    let bindless_index = bindless
+                        .as_ref()
+                        .and_then(|(_, _, indices)| indices.get(&mesh.texture_id).copied());
+                    let (pipeline, desc_set) = match (&bindless, bindless_index) {
+                        (Some((bindless_pipeline, bindless_set, _)), Some(texture_index)) => {
+                            push_constants.texture_params = texture_index << TEXTURE_INDEX_SHIFT;
+                            let desc_set =
+                                if bindless_bound { None } else { Some(bindless_set.clone()) };
+                            bindless_bound = true;
+                            (bindless_pipeline.clone(), desc_set)
+                        }
+                        _ => {
+                            let desc_set = match self.texture_desc_set(mesh.texture_id) {
+                                Some(desc_set) => desc_set,
+                                None => {
+                                    if !self.is_upload_pending(mesh.texture_id) {
+                                        eprintln!(
+                                            "This texture no longer exists {:?}",
+                                            mesh.texture_id
+                                        );
+                                    }
+                                    continue;
+                                }
+                            };
+                            bindless_bound = false;
+                            let pipeline = match self.ycbcr_textures.get(&mesh.texture_id) {
+                                Some(&index) => self.ycbcr_pipelines[index].pipeline.clone(),
+                                None => self
+                                    .blend_pipeline(mesh.texture_id)
+                                    .unwrap_or_else(|| pipeline.clone()),
+                            };
+                            (pipeline, Some(desc_set))
+                        }
+                    };
+                    self.set_texture_constants(mesh.texture_id, &mut push_constants);
 
                     let scissors = vec![self.get_rect_scissor(
                         scale_factor,
                         framebuffer_dimensions,
                         *clip_rect,
                     )];
-                    let vertices_count = mesh.vertices.len() as DeviceSize;
-                    let indices_count = mesh.indices.len() as DeviceSize;
-                    // Resize buffers if needed
-                    if self
-                        .resize_needed(vertex_start + vertices_count, index_start + indices_count)
-                    {
-                        // Double the allocations
-                        self.resize_allocations(
-                            self.vertex_buffer.len() * 2,
-                            self.index_buffer.len() * 2,
-                        );
-                        // Stop copying and continue next frame
-                        break;
-                    }
-                    self.copy_mesh(mesh, vertex_start, index_start);
-                    // Access vertex & index slices for drawing
-                    let vertices = self
-                        .vertex_buffer
-                        .into_buffer_slice()
-                        .slice(vertex_start..(vertex_start + vertices_count))
-                        .unwrap();
-                    let indices = self
-                        .index_buffer
-                        .into_buffer_slice()
-                        .slice(index_start..(index_start + indices_count))
-                        .unwrap();
-                    let desc_set = self.texture_desc_sets.get(&mesh.texture_id).unwrap().clone();
+                    let indices_count = mesh.indices.len() as u32;
                     builder
-                        .bind_pipeline_graphics(self.pipeline.clone())
+                        .bind_pipeline_graphics(pipeline.clone())
                         .set_viewport(0, vec![Viewport {
                             origin: [0.0, 0.0],
                             dimensions: [
@@ -644,46 +3144,517 @@ impl Renderer {
                             ],
                             depth_range: 0.0..1.0,
                         }])
-                        .set_scissor(0, scissors)
-                        .bind_descriptor_sets(
+                        .set_scissor(0, scissors);
+                    if let Some(desc_set) = desc_set {
+                        builder.bind_descriptor_sets(
                             PipelineBindPoint::Graphics,
-                            self.pipeline.layout().clone(),
+                            pipeline.layout().clone(),
                             0,
-                            desc_set.clone(),
-                        )
-                        .push_constants(self.pipeline.layout().clone(), 0, push_constants)
-                        .bind_vertex_buffers(0, vertices.clone())
-                        .bind_index_buffer(indices.clone())
-                        .draw_indexed(indices.len() as u32, 1, 0, 0, 0)
-                        .unwrap();
-                    vertex_start += vertices_count;
-                    index_start += indices_count;
+                            DescriptorSetWithOffsets::new(desc_set, std::iter::empty()),
+                        );
+                    }
+                    builder.push_constants(pipeline.layout().clone(), 0, push_constants);
+                    // Access vertex & index slices for drawing
+                    match device_buffers {
+                        Some((vertices, indices)) => builder
+                            .bind_vertex_buffers(
+                                0,
+                                vertices.into_buffer_slice().slice(vertex_range).unwrap(),
+                            )
+                            .bind_index_buffer(
+                                indices.into_buffer_slice().slice(index_range).unwrap(),
+                            ),
+                        None => builder
+                            .bind_vertex_buffers(
+                                0,
+                                host_buffers.0.into_buffer_slice().slice(vertex_range).unwrap(),
+                            )
+                            .bind_index_buffer(
+                                host_buffers.1.into_buffer_slice().slice(index_range).unwrap(),
+                            ),
+                    };
+                    builder.draw_indexed(indices_count, 1, 0, 0, 0).unwrap();
+                }
+                Primitive::Callback(callback) => {
+                    if !callback.rect.is_positive() {
+                        continue;
+                    }
+                    let rect = callback.rect;
+                    builder
+                        .set_viewport(0, vec![Viewport {
+                            origin: [rect.min.x * scale_factor, rect.min.y * scale_factor],
+                            dimensions: [rect.width() * scale_factor, rect.height() * scale_factor],
+                            depth_range: 0.0..1.0,
+                        }])
+                        .set_scissor(0, vec![self.get_rect_scissor(
+                            scale_factor,
+                            framebuffer_dimensions,
+                            *clip_rect,
+                        )]);
+                    let info = PaintCallbackInfo {
+                        viewport: rect,
+                        clip_rect: *clip_rect,
+                        pixels_per_point: scale_factor,
+                        screen_size_px: framebuffer_dimensions,
+                    };
+                    // Callback records into our builder through its render context, see `paint_callback`
+                    let mut callback_ctx =
+                        CallbackContext { builder, renderers: self.callback_renderers.clone() };
+                    callback.call(&info, &mut callback_ctx);
+                    builder = callback_ctx.builder;
+                    bindless_bound = false;
+                }
+            }
+        }
+        self.geometry_upload = match geometry {
+            Some(((vertices, indices), Some(device_buffers))) => {
+                Some(GeometryUpload { vertices, indices, device_buffers })
+            }
+            _ => None,
+        };
+        Ok(builder)
+    }
+
+    pub fn texture_image(
+        &self,
+        texture_id: egui::TextureId,
+    ) -> Option<Arc<dyn ImageViewAbstract + Send + Sync + 'static>> {
+        match self.texture_images.get(&texture_id) {
+            Some(image) => Some(image.clone()),
+            None => self.user_textures.lock().unwrap().images.get(&texture_id).cloned(),
+        }
+    }
+
+    /// User textures registered with `register_image`, excluding static cache textures
+    pub fn user_images(
+        &self,
+    ) -> Vec<(egui::TextureId, Arc<dyn ImageViewAbstract + Send + Sync + 'static>)> {
+        let mut images: Vec<_> = self
+            .user_textures
+            .lock()
+            .unwrap()
+            .images
+            .iter()
+            .map(|(texture_id, image)| (*texture_id, image.clone()))
+            .collect();
+        images.sort_by_key(|(texture_id, _)| *texture_id);
+        images
+    }
+
+    pub fn is_image_registered(&self, texture_id: egui::TextureId) -> bool {
+        self.texture_desc_sets.contains_key(&texture_id)
+            || self.user_textures.lock().unwrap().desc_sets.contains_key(&texture_id)
+    }
+
+    /// Registers the region `uv_rect` (in 0..1 uvs) of `image` as a user texture of its own, see
+    /// [`Gui::register_user_image_view_region`](crate::Gui::register_user_image_view_region)
+    pub fn register_image_region(
+        &mut self,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+        uv_rect: Rect,
+    ) -> Result<egui::TextureId, GuiError> {
+        let id = self.register_image(image)?;
+        self.set_image_uv_rect(id, uv_rect);
+        Ok(id)
+    }
+
+    /// Draws the region `uv_rect` of a registered user image, see `register_image_region`
+    fn set_image_uv_rect(&mut self, texture_id: egui::TextureId, uv_rect: Rect) {
+        self.user_textures.lock().unwrap().uv_rects.insert(texture_id, [
+            uv_rect.min.x,
+            uv_rect.min.y,
+            uv_rect.width(),
+            uv_rect.height(),
+        ]);
+    }
+
+    /// Registers `image` with a given user id, e.g. one given by `register_image` when restoring a snapshot.
+    /// Later ids given by `register_image` won't collide with it.
+    pub fn register_image_with_id(
+        &mut self,
+        texture_id: egui::TextureId,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+        sampler_options: SamplerOptions,
+    ) -> Result<(), GuiError> {
+        let id = match texture_id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Managed(_) => return Err(GuiError::NotAUserTextureId(texture_id)),
+        };
+        self.insert_user_image(Some(id), image, sampler_options)?;
+        Ok(())
+    }
+
+    /// User images with what they're drawn with, see
+    /// [`Gui::snapshot_user_textures`](crate::Gui::snapshot_user_textures). Waits for the uploads of images still
+    /// uploading.
+    pub(crate) fn snapshot_user_images(&self) -> Result<Vec<UserTextureEntry>, GuiError> {
+        let mut user_textures = self.user_textures.lock().unwrap();
+        // Raw images have descriptor sets but no vulkano image
+        let unsnapshottable = self.ycbcr_textures.keys().copied().chain(
+            user_textures
+                .desc_sets
+                .keys()
+                .copied()
+                .filter(|texture_id| !user_textures.images.contains_key(texture_id)),
+        );
+        if let Some(texture_id) = unsnapshottable.min() {
+            return Err(GuiError::UnsnapshottableTexture(texture_id));
+        }
+        let uploading: Vec<egui::TextureId> =
+            user_textures.pending_uploads.keys().copied().collect();
+        for texture_id in uploading {
+            user_textures.pending_uploads[&texture_id].wait(None)?;
+            user_textures.upload_finished(texture_id);
+        }
+        let mut entries: Vec<UserTextureEntry> = user_textures
+            .images
+            .iter()
+            .map(|(texture_id, image)| {
+                let dimensions = image.image().dimensions();
+                UserTextureEntry {
+                    texture_id: *texture_id,
+                    size: [dimensions.width(), dimensions.height()],
+                    image: image.clone(),
+                    sampler_options: user_textures
+                        .sampler_options
+                        .get(texture_id)
+                        .copied()
+                        .unwrap_or_default(),
+                    uv_rect: user_textures.uv_rects.get(texture_id).map(|[x, y, width, height]| {
+                        Rect::from_min_size(egui::pos2(*x, *y), egui::vec2(*width, *height))
+                    }),
+                    blend_mode: user_textures.blend_modes.get(texture_id).copied(),
                 }
-                _ => continue,
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.texture_id);
+        Ok(entries)
+    }
+
+    /// Registers a user image of a snapshot again, with the options it was drawn with
+    pub(crate) fn restore_user_image(
+        &mut self,
+        entry: &UserTextureEntry,
+        image: Arc<dyn ImageViewAbstract + Send + Sync>,
+    ) -> Result<(), GuiError> {
+        self.register_image_with_id(entry.texture_id, image, entry.sampler_options)?;
+        if let Some(uv_rect) = entry.uv_rect {
+            self.set_image_uv_rect(entry.texture_id, uv_rect);
+        }
+        self.set_image_blend_mode(entry.texture_id, entry.blend_mode)
+    }
+
+    pub fn allocation_stats(&self) -> AllocationStats {
+        let image_size = |image: &Arc<dyn ImageViewAbstract + Send + Sync + 'static>| {
+            let image = image.image();
+            image.dimensions().num_texels() as DeviceSize * image.format().block_size().unwrap_or(0)
+        };
+        let device_buffers = self.device_buffers.iter().chain(&self.spare_device_buffers);
+        let user_textures = self.user_textures.lock().unwrap();
+        let mut stats = AllocationStats {
+            vertex_buffer_size: self.vertex_pool.capacity()
+                * std::mem::size_of::<EguiVertex>() as DeviceSize
+                + device_buffers.clone().map(|(vertices, _)| vertices.size()).sum::<DeviceSize>(),
+            index_buffer_size: self.index_pool.capacity()
+                * std::mem::size_of::<u32>() as DeviceSize
+                + device_buffers.map(|(_, indices)| indices.size()).sum::<DeviceSize>(),
+            user_textures: user_textures.desc_sets.len(),
+            descriptor_sets: self.texture_desc_sets.len() + user_textures.desc_sets.len(),
+            samplers: 1 + self.samplers.len(),
+            staging_buffers: self.staging_buffers,
+            staging_buffer_size: self.staging_buffer_size,
+            ..Default::default()
+        };
+        for (texture_id, image) in &self.texture_images {
+            if let egui::TextureId::Managed(_) = texture_id {
+                stats.managed_textures += 1;
+                stats.managed_texture_size += image_size(image);
+            } else {
+                stats.cached_textures += 1;
+                stats.cached_texture_size += image_size(image);
             }
         }
+        stats
+    }
+
+    /// Installs a renderer for paint callbacks created with `keyed_paint_callback`
+    pub fn register_callback_renderer(
+        &mut self,
+        key: impl Into<String>,
+        renderer: Arc<dyn CallbackRenderer>,
+    ) {
+        Arc::make_mut(&mut self.callback_renderers).insert(key.into(), renderer);
+    }
+
+    pub fn unregister_callback_renderer(&mut self, key: &str) {
+        Arc::make_mut(&mut self.callback_renderers).remove(key);
     }
 
     pub fn queue(&self) -> Arc<Queue> {
         self.gfx_queue.clone()
     }
+
+    pub fn set_pixel_snapping(&mut self, enabled: bool) {
+        self.pixel_snapping = enabled;
+    }
+
+    /// Computes edge coverage of feathered shapes in the fragment shader instead of using egui's vertex alphas
+    pub fn set_analytic_anti_aliasing(&mut self, enabled: bool) {
+        self.analytic_anti_aliasing = enabled;
+    }
+
+    /// Recreates egui's pipelines to filter their output with the deficiency's matrix, see
+    /// [`Gui::set_color_vision_simulation`](crate::Gui::set_color_vision_simulation)
+    pub fn set_color_vision_simulation(
+        &mut self,
+        deficiency: Option<ColorVisionDeficiency>,
+    ) -> Result<(), GuiError> {
+        if deficiency == self.pipeline_config.color_vision_simulation {
+            return Ok(());
+        }
+        let config =
+            PipelineConfig { color_vision_simulation: deficiency, ..self.pipeline_config.clone() };
+        self.recreate_pipelines(config, self.color_write_mask)
+    }
+
+    /// Recreates the pipeline to write only the given components of the target image
+    pub fn set_color_write_mask(
+        &mut self,
+        color_write_mask: ColorComponents,
+    ) -> Result<(), GuiError> {
+        if color_write_mask == self.color_write_mask {
+            return Ok(());
+        }
+        self.recreate_pipelines(self.pipeline_config.clone(), color_write_mask)
+    }
+
+    /// Recreates egui's pipelines with `pipeline_cache`, see
+    /// [`Gui::set_pipeline_cache`](crate::Gui::set_pipeline_cache)
+    pub fn set_pipeline_cache(
+        &mut self,
+        pipeline_cache: Option<Arc<PipelineCache>>,
+    ) -> Result<(), GuiError> {
+        let config = PipelineConfig { cache: pipeline_cache, ..self.pipeline_config.clone() };
+        self.recreate_pipelines(config, self.color_write_mask)
+    }
+
+    pub fn pipeline_cache(&self) -> Option<Arc<PipelineCache>> {
+        self.pipeline_config.cache.clone()
+    }
+
+    /// Recreates egui's pipelines to encode their output with `output_transfer`, see
+    /// [`Gui::set_output_transfer`](crate::Gui::set_output_transfer)
+    pub fn set_output_transfer(&mut self, output_transfer: OutputTransfer) -> Result<(), GuiError> {
+        if output_transfer == self.pipeline_config.output_transfer {
+            return Ok(());
+        }
+        let config = PipelineConfig { output_transfer, ..self.pipeline_config.clone() };
+        self.recreate_pipelines(config, self.color_write_mask)
+    }
+
+    pub fn output_transfer(&self) -> OutputTransfer {
+        self.pipeline_config.output_transfer
+    }
+
+    /// Recreates egui's pipelines to blend with `blend_mode`, see
+    /// [`Gui::set_blend_mode`](crate::Gui::set_blend_mode)
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) -> Result<(), GuiError> {
+        if blend_mode == self.pipeline_config.blend_mode {
+            return Ok(());
+        }
+        let config = PipelineConfig { blend_mode, ..self.pipeline_config.clone() };
+        self.recreate_pipelines(config, self.color_write_mask)
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.pipeline_config.blend_mode
+    }
+
+    /// Recreates egui's pipelines with `depth_stencil`, see
+    /// [`Gui::set_depth_stencil_state`](crate::Gui::set_depth_stencil_state). Egui's pipeline is created right
+    /// away, keeping the current pipelines on errors.
+    pub fn set_depth_stencil_state(
+        &mut self,
+        depth_stencil: DepthStencilState,
+    ) -> Result<(), GuiError> {
+        let (has_depth, has_stencil) = match &self.pipeline_render_pass {
+            PipelineRenderPassType::BeginRenderPass(subpass) => {
+                (subpass.has_depth(), subpass.has_stencil())
+            }
+            PipelineRenderPassType::BeginRendering(rendering_info) => (
+                rendering_info.depth_attachment_format.is_some(),
+                rendering_info.stencil_attachment_format.is_some(),
+            ),
+        };
+        let needs_depth = depth_stencil.depth.is_some() || depth_stencil.depth_bounds.is_some();
+        if needs_depth && !has_depth || depth_stencil.stencil.is_some() && !has_stencil {
+            return Err(GuiError::NoDepthStencilAttachment);
+        }
+        self.set_pipeline_config(PipelineConfig { depth_stencil, ..self.pipeline_config.clone() })
+    }
+
+    pub fn depth_stencil_state(&self) -> &DepthStencilState {
+        &self.pipeline_config.depth_stencil
+    }
+
+    /// Recreates egui's pipelines with `shaders`, see [`Gui::set_custom_shaders`](crate::Gui::set_custom_shaders).
+    /// Egui's pipeline is created right away to check that they fit it, keeping the current pipelines on errors.
+    pub fn set_custom_shaders(&mut self, shaders: CustomShaders) -> Result<(), GuiError> {
+        self.set_pipeline_config(PipelineConfig { shaders, ..self.pipeline_config.clone() })
+    }
+
+    pub fn custom_shaders(&self) -> &CustomShaders {
+        &self.pipeline_config.shaders
+    }
+
+    /// Creates egui's pipeline with `config` right away & switches to it, keeping the current pipelines on errors
+    fn set_pipeline_config(&mut self, config: PipelineConfig) -> Result<(), GuiError> {
+        let create_info = self.pipeline_create_info(config.clone(), self.color_write_mask);
+        let pipeline = create_info.clone().create()?;
+        let derived_pipelines = self.create_derived_pipelines(&config, self.color_write_mask)?;
+        self.pipeline_config = config;
+        self.pipeline = AsyncPipeline::ready(pipeline, create_info);
+        self.set_derived_pipelines(derived_pipelines);
+        Ok(())
+    }
+
+    /// Switches to `config` & `color_write_mask`, compiling egui's pipeline in the background. The pipelines of YUV
+    /// images & bindless textures are created right away, keeping the current pipelines on errors.
+    fn recreate_pipelines(
+        &mut self,
+        config: PipelineConfig,
+        color_write_mask: ColorComponents,
+    ) -> Result<(), GuiError> {
+        let derived_pipelines = self.create_derived_pipelines(&config, color_write_mask)?;
+        self.pipeline =
+            AsyncPipeline::spawn(self.pipeline_create_info(config.clone(), color_write_mask));
+        self.pipeline_config = config;
+        self.color_write_mask = color_write_mask;
+        self.set_derived_pipelines(derived_pipelines);
+        Ok(())
+    }
+
+    fn pipeline_create_info(
+        &self,
+        config: PipelineConfig,
+        color_write_mask: ColorComponents,
+    ) -> PipelineCreateInfo {
+        PipelineCreateInfo {
+            gfx_queue: self.gfx_queue.clone(),
+            render_pass: self.pipeline_render_pass.clone(),
+            sample_count: self.sample_count,
+            color_write_mask,
+            config,
+        }
+    }
+
+    /// Creates the pipelines of YUV images & bindless textures for `config`
+    fn create_derived_pipelines(
+        &self,
+        config: &PipelineConfig,
+        color_write_mask: ColorComponents,
+    ) -> Result<DerivedPipelines, GuiError> {
+        let ycbcr_pipelines = self
+            .ycbcr_pipelines
+            .iter()
+            .map(|ycbcr| {
+                self.create_derived_pipeline(
+                    TextureBinding::ImmutableSampler(ycbcr.sampler.clone()),
+                    config,
+                    color_write_mask,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let bindless_pipeline = self
+            .bindless
+            .as_ref()
+            .map(|bindless| {
+                self.create_derived_pipeline(
+                    TextureBinding::Bindless(bindless.max_textures),
+                    config,
+                    color_write_mask,
+                )
+            })
+            .transpose()?;
+        Ok((ycbcr_pipelines, bindless_pipeline))
+    }
+
+    /// Switches to pipelines created by `create_derived_pipelines`, the pipelines of blend modes are recreated on
+    /// the next draw
+    fn set_derived_pipelines(&mut self, (ycbcr_pipelines, bindless_pipeline): DerivedPipelines) {
+        self.blend_pipelines.clear();
+        for (ycbcr, pipeline) in self.ycbcr_pipelines.iter_mut().zip(ycbcr_pipelines) {
+            ycbcr.pipeline = pipeline;
+        }
+        if let (Some(bindless), Some(pipeline)) = (&mut self.bindless, bindless_pipeline) {
+            bindless.pipeline = pipeline;
+            // Written for the previous pipeline's layout
+            bindless.stale = true;
+        }
+    }
+
+    /// Allocates descriptor sets of textures registered from now on from `descriptor_pool`
+    pub fn set_descriptor_pool(
+        &mut self,
+        descriptor_pool: Option<Arc<Mutex<dyn DescriptorAllocator>>>,
+    ) {
+        self.descriptor_pool = descriptor_pool;
+    }
+
+    /// Overrides the projection used to draw egui, `None` restores [`orthographic_projection`]
+    pub fn set_projection(&mut self, projection: Option<[[f32; 4]; 4]>) {
+        self.projection = projection;
+    }
+
+    /// Sets inheritance of the command buffers returned by `draw_on_subpass_image`
+    pub fn set_subpass_inheritance(&mut self, subpass_inheritance: SubpassInheritance) {
+        self.subpass_inheritance = subpass_inheritance;
+    }
+
+    /// The subpass egui is drawn in. Pipelines used in paint callbacks must be created for it.
+    ///
+    /// Panics if the renderer draws with dynamic rendering, see [`Renderer::pipeline_render_pass`].
+    pub fn subpass(&self) -> Subpass {
+        match &self.pipeline_render_pass {
+            PipelineRenderPassType::BeginRenderPass(subpass) => subpass.clone(),
+            PipelineRenderPassType::BeginRendering(_) => {
+                panic!("Renderer draws with dynamic rendering and has no subpass")
+            }
+        }
+    }
+
+    /// The subpass or dynamic rendering attachments egui is drawn in, to create pipelines for
+    pub fn pipeline_render_pass(&self) -> PipelineRenderPassType {
+        self.pipeline_render_pass.clone()
+    }
+
+    pub fn sample_count(&self) -> SampleCount {
+        self.sample_count
+    }
 }
 
 mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
+        vulkan_version: "1.0",
+        spirv_version: "1.0",
         src: "
 #version 450
 
 layout(location = 0) in vec2 position;
 layout(location = 1) in vec2 tex_coords;
 layout(location = 2) in vec4 color;
+layout(location = 3) in float coverage;
 
 layout(location = 0) out vec4 v_color;
 layout(location = 1) out vec2 v_tex_coords;
+layout(location = 2) out float v_coverage;
 
+// Same as in the fragment shader, so that both stages share one push constant range
 layout(push_constant) uniform PushConstants {
-    vec2 screen_size;
+    mat4 projection;
+    vec4 uv_rect;
+    uint texture_params;
 } push_constants;
 
 // 0-1 linear  from  0-255 sRGB
@@ -699,11 +3670,11 @@ vec4 linear_from_srgba(vec4 srgba) {
 }
 
 void main() {
-  gl_Position =
-      vec4(2.0 * position.x / push_constants.screen_size.x - 1.0,
-           2.0 * position.y / push_constants.screen_size.y - 1.0, 0.0, 1.0);
+  gl_Position = push_constants.projection * vec4(position, 0.0, 1.0);
   v_color = linear_from_srgba(color);
-  v_tex_coords = tex_coords;
+  // Uvs of atlas regions are relative to the region
+  v_tex_coords = push_constants.uv_rect.xy + tex_coords * push_constants.uv_rect.zw;
+  v_coverage = coverage;
 }"
     }
 }
@@ -711,18 +3682,20 @@ void main() {
 mod fs {
     vulkano_shaders::shader! {
         ty: "fragment",
-        src: "
-#version 450
-
-layout(location = 0) in vec4 v_color;
-layout(location = 1) in vec2 v_tex_coords;
-
-layout(location = 0) out vec4 f_color;
-
-layout(binding = 0, set = 0) uniform sampler2D font_texture;
+        vulkan_version: "1.0",
+        spirv_version: "1.0",
+        path: "src/shaders/egui.frag"
+    }
+}
 
-void main() {
-    f_color = v_color * texture(font_texture, v_tex_coords);
-}"
+/// Same as `fs`, but the texture is picked from an array by the push constants
+mod fs_bindless {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        // Runtime descriptor arrays need descriptor indexing
+        vulkan_version: "1.2",
+        spirv_version: "1.5",
+        path: "src/shaders/egui.frag",
+        define: [("BINDLESS", "1")]
     }
 }
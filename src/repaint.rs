@@ -0,0 +1,34 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::time::{Duration, Instant};
+
+use egui::Id;
+
+fn repaint_deadline_id() -> Id {
+    Id::new("egui_winit_vulkano::repaint_deadline")
+}
+
+/// Requests a repaint once `delay` has passed, e.g. for a clock or polling a progress bar. Egui 0.18 has no
+/// `request_repaint_after` of its own, so this stands in for it. Call it from ui code each frame the repaint is
+/// still wanted, [`Gui::control_flow`](crate::Gui::control_flow) then waits until the earliest one is due.
+pub fn request_repaint_after(ctx: &egui::Context, delay: Duration) {
+    let deadline = Instant::now() + delay;
+    let mut data = ctx.data();
+    let earliest = data.get_temp_mut_or_insert_with(repaint_deadline_id(), || deadline);
+    *earliest = (*earliest).min(deadline);
+}
+
+/// Takes the earliest repaint requested during the frame
+pub(crate) fn take_repaint_deadline(ctx: &egui::Context) -> Option<Instant> {
+    let mut data = ctx.data();
+    let deadline = data.get_temp(repaint_deadline_id());
+    data.remove::<Instant>(repaint_deadline_id());
+    deadline
+}
@@ -0,0 +1,129 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ahash::AHashSet;
+use egui::{epaint::Primitive, ClippedPrimitive, Rect, TextureId, TexturesDelta};
+
+/// Managed texture ids of secondary contexts are offset into their own range, far above the main context's
+const TEXTURE_NAMESPACE_SHIFT: u64 = 48;
+
+static NEXT_TEXTURE_NAMESPACE: AtomicU64 = AtomicU64::new(1);
+
+/// Egui context drawn on top of the main ui in the same pass, e.g. a debug overlay with its own scale & input.
+/// Run its frames with [`Gui::run_secondary`](crate::Gui::run_secondary). It shares the renderer's pipelines &
+/// user images, while its own textures (e.g. its font atlas) are kept apart from the main context's.
+pub struct SecondaryContext {
+    ctx: egui::Context,
+    raw_input: egui::RawInput,
+    pixels_per_point: Option<f32>,
+    texture_namespace: u64,
+    /// Managed textures it has allocated, freed by [`Gui::remove_secondary`](crate::Gui::remove_secondary)
+    textures: AHashSet<TextureId>,
+}
+
+impl Default for SecondaryContext {
+    fn default() -> Self {
+        SecondaryContext::new()
+    }
+}
+
+impl SecondaryContext {
+    pub fn new() -> SecondaryContext {
+        SecondaryContext {
+            ctx: egui::Context::default(),
+            raw_input: egui::RawInput::default(),
+            pixels_per_point: None,
+            texture_namespace: NEXT_TEXTURE_NAMESPACE.fetch_add(1, Ordering::Relaxed)
+                << TEXTURE_NAMESPACE_SHIFT,
+            textures: AHashSet::default(),
+        }
+    }
+
+    pub fn context(&self) -> egui::Context {
+        self.ctx.clone()
+    }
+
+    /// Input of the next frame. It gets none of the window's events, route the ones meant for it here
+    /// (positions in its own points).
+    pub fn raw_input_mut(&mut self) -> &mut egui::RawInput {
+        &mut self.raw_input
+    }
+
+    /// Scale of the context, `None` (the default) uses the main context's
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: Option<f32>) {
+        self.pixels_per_point = pixels_per_point;
+    }
+
+    /// Runs a frame on a screen of `size_in_pixels`, returning its primitives in points of the main context
+    /// (`main_pixels_per_point`), its texture deltas with ids moved to its own range & the link it opened
+    pub(crate) fn run(
+        &mut self,
+        size_in_pixels: [u32; 2],
+        main_pixels_per_point: f32,
+        max_texture_side: usize,
+        run_ui: impl FnOnce(&egui::Context),
+    ) -> (Vec<ClippedPrimitive>, TexturesDelta, Option<egui::output::OpenUrl>) {
+        let pixels_per_point = self.pixels_per_point.unwrap_or(main_pixels_per_point);
+        let mut raw_input = std::mem::take(&mut self.raw_input);
+        raw_input.pixels_per_point = Some(pixels_per_point);
+        raw_input.max_texture_side = Some(max_texture_side);
+        raw_input.screen_rect = Some(Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(size_in_pixels[0] as f32, size_in_pixels[1] as f32) / pixels_per_point,
+        ));
+        let output = self.ctx.run(raw_input, run_ui);
+        let mut primitives = self.ctx.tessellate(output.shapes);
+
+        let scale = pixels_per_point / main_pixels_per_point;
+        for ClippedPrimitive { clip_rect, primitive } in &mut primitives {
+            *clip_rect = scale_rect(*clip_rect, scale);
+            match primitive {
+                Primitive::Mesh(mesh) => {
+                    mesh.texture_id = self.namespaced(mesh.texture_id);
+                    for vertex in &mut mesh.vertices {
+                        vertex.pos = (vertex.pos.to_vec2() * scale).to_pos2();
+                    }
+                }
+                Primitive::Callback(callback) => callback.rect = scale_rect(callback.rect, scale),
+            }
+        }
+
+        let TexturesDelta { set, free } = output.textures_delta;
+        let textures_delta = TexturesDelta {
+            set: set.into_iter().map(|(id, delta)| (self.namespaced(id), delta)).collect(),
+            free: free.into_iter().map(|id| self.namespaced(id)).collect(),
+        };
+        self.textures.extend(textures_delta.set.keys().copied());
+        for id in &textures_delta.free {
+            self.textures.remove(id);
+        }
+        (primitives, textures_delta, output.platform_output.open_url)
+    }
+
+    /// Frees all textures the context has allocated
+    pub(crate) fn free_textures(&mut self) -> TexturesDelta {
+        TexturesDelta { set: Default::default(), free: self.textures.drain().collect() }
+    }
+
+    fn namespaced(&self, id: TextureId) -> TextureId {
+        match id {
+            TextureId::Managed(id) => TextureId::Managed(id | self.texture_namespace),
+            TextureId::User(_) => id,
+        }
+    }
+}
+
+fn scale_rect(rect: Rect, scale: f32) -> Rect {
+    Rect::from_min_max(
+        (rect.min.to_vec2() * scale).to_pos2(),
+        (rect.max.to_vec2() * scale).to_pos2(),
+    )
+}
@@ -0,0 +1,107 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use vulkano::{device::Device, shader::ShaderModule};
+
+use crate::{CustomShaders, GuiError};
+
+/// SPIR-V file whose modification time is compared on each poll
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    /// Last module loaded from the file
+    module: Option<Arc<ShaderModule>>,
+}
+
+impl WatchedFile {
+    fn new(path: PathBuf) -> WatchedFile {
+        WatchedFile { path, modified: None, module: None }
+    }
+
+    /// Whether the file was written since the last call. Missing files, e.g. while being replaced, aren't changes.
+    fn changed(&mut self) -> bool {
+        let modified = fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+/// Custom shaders reloaded at the start of each frame their files changed, see
+/// [`Gui::watch_custom_shaders`](crate::Gui::watch_custom_shaders)
+pub(crate) struct ShaderWatcher {
+    vertex: Option<WatchedFile>,
+    fragment: Option<WatchedFile>,
+}
+
+impl ShaderWatcher {
+    pub(crate) fn new(vertex: Option<PathBuf>, fragment: Option<PathBuf>) -> ShaderWatcher {
+        ShaderWatcher {
+            vertex: vertex.map(WatchedFile::new),
+            fragment: fragment.map(WatchedFile::new),
+        }
+    }
+
+    /// The last modules loaded from the files if any changed, or `None` if none did. A file that fails to load is
+    /// retried once it changes again, while the other stage's new module is kept for then.
+    ///
+    /// # Safety
+    ///
+    /// The files must hold valid SPIR-V, see `ShaderModule::from_bytes`.
+    pub(crate) unsafe fn poll(
+        &mut self,
+        device: &Arc<Device>,
+    ) -> Option<Result<CustomShaders, GuiError>> {
+        let vertex = reload(device, &mut self.vertex);
+        let fragment = reload(device, &mut self.fragment);
+        let result = match (vertex, fragment) {
+            (None, None) => return None,
+            (Some(Err(err)), _) | (_, Some(Err(err))) => Err(err),
+            _ => Ok(CustomShaders {
+                vertex: self.vertex.as_ref().and_then(|file| file.module.clone()),
+                fragment: self.fragment.as_ref().and_then(|file| file.module.clone()),
+            }),
+        };
+        Some(result)
+    }
+}
+
+/// Loads the file if it changed, keeping the module
+unsafe fn reload(
+    device: &Arc<Device>,
+    file: &mut Option<WatchedFile>,
+) -> Option<Result<(), GuiError>> {
+    let file = file.as_mut()?;
+    if !file.changed() {
+        return None;
+    }
+    Some(load(device, &file.path).map(|module| file.module = Some(module)))
+}
+
+unsafe fn load(device: &Arc<Device>, path: &Path) -> Result<Arc<ShaderModule>, GuiError> {
+    let bytes = fs::read(path).map_err(|err| GuiError::ShaderFile(path.to_owned(), err))?;
+    // E.g. while the compiler is still writing it
+    if bytes.len() % 4 != 0 {
+        let err = std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "not a whole number of SPIR-V words",
+        );
+        return Err(GuiError::ShaderFile(path.to_owned(), err));
+    }
+    Ok(ShaderModule::from_bytes(device.clone(), &bytes)?)
+}
@@ -0,0 +1,125 @@
+#version 450
+#ifdef BINDLESS
+// Runtime arrays of samplers
+#extension GL_EXT_nonuniform_qualifier : require
+#endif
+
+layout(location = 0) in vec4 v_color;
+layout(location = 1) in vec2 v_tex_coords;
+layout(location = 2) in float v_coverage;
+
+layout(location = 0) out vec4 f_color;
+
+// Encode to sRGB for UNORM targets
+layout(constant_id = 0) const bool encode_srgb = false;
+// Scale of linear values in the HDR transfers, i.e. ui white relative to 80 nits for scRGB & 10000 nits for PQ
+layout(constant_id = 1) const float output_scale = 1.0;
+// Output straight instead of premultiplied alpha
+layout(constant_id = 2) const bool straight_alpha = false;
+// 0: none, 1: scRGB, 2: HDR10 PQ
+layout(constant_id = 3) const uint hdr_transfer = 0;
+// Column-major matrix of the color vision simulation, identity unless simulating a deficiency
+layout(constant_id = 4) const float color_matrix_0 = 1.0;
+layout(constant_id = 5) const float color_matrix_1 = 0.0;
+layout(constant_id = 6) const float color_matrix_2 = 0.0;
+layout(constant_id = 7) const float color_matrix_3 = 0.0;
+layout(constant_id = 8) const float color_matrix_4 = 1.0;
+layout(constant_id = 9) const float color_matrix_5 = 0.0;
+layout(constant_id = 10) const float color_matrix_6 = 0.0;
+layout(constant_id = 11) const float color_matrix_7 = 0.0;
+layout(constant_id = 12) const float color_matrix_8 = 1.0;
+
+#ifdef BINDLESS
+layout(binding = 0, set = 0) uniform sampler2D textures[];
+#else
+layout(binding = 0, set = 0) uniform sampler2D font_texture;
+#endif
+
+layout(push_constant) uniform PushConstants {
+    mat4 projection;
+    vec4 uv_rect;
+    // Bits 0-1: 0 none, 1 decode sRGB samples, 2 encode linear samples to sRGB.
+    // Bits 2-31: index of the texture in the bindless pipeline's array.
+    uint texture_params;
+} push_constants;
+
+vec3 linear_from_srgb(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+    vec3 lower = srgb / vec3(12.92);
+    vec3 higher = pow((srgb + vec3(0.055)) / vec3(1.055), vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+vec3 srgb_from_linear(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+vec4 sample_texture() {
+#ifdef BINDLESS
+    // Uniform within a draw, so the index needn't be nonuniform
+    vec4 texel = texture(textures[push_constants.texture_params >> 2], v_tex_coords);
+#else
+    vec4 texel = texture(font_texture, v_tex_coords);
+#endif
+    uint color_conversion = push_constants.texture_params & 3u;
+    if (color_conversion == 1u) {
+        texel.rgb = linear_from_srgb(texel.rgb);
+    } else if (color_conversion == 2u) {
+        texel.rgb = srgb_from_linear(texel.rgb);
+    }
+    return texel;
+}
+
+// Column-major
+const mat3 BT2020_FROM_BT709 = mat3(
+    0.6274, 0.0691, 0.0164,
+    0.3293, 0.9195, 0.0880,
+    0.0433, 0.0114, 0.8956
+);
+
+// 0-1 of 10000 nits to SMPTE ST 2084
+vec3 pq_from_linear(vec3 linear) {
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+    vec3 p = pow(max(linear, vec3(0.0)), vec3(m1));
+    return pow((vec3(c1) + c2 * p) / (vec3(1.0) + c3 * p), vec3(m2));
+}
+
+// Coverage of a pixel wide edge at the middle of the coverage ramp, from the pixel's signed distance to it
+float edge_coverage(float ramp) {
+    float ramp_per_pixel = fwidth(ramp);
+    if (ramp >= 1.0 || ramp_per_pixel <= 0.0) {
+        return 1.0;
+    }
+    return clamp((ramp - 0.5) / ramp_per_pixel + 0.5, 0.0, 1.0);
+}
+
+void main() {
+    vec4 color = v_color * sample_texture() * edge_coverage(v_coverage);
+    // Linear & premultiplied, so the matrix applies as is
+    mat3 color_matrix = mat3(
+        color_matrix_0, color_matrix_1, color_matrix_2,
+        color_matrix_3, color_matrix_4, color_matrix_5,
+        color_matrix_6, color_matrix_7, color_matrix_8
+    );
+    f_color = vec4(clamp(color_matrix * color.rgb, 0.0, color.a), color.a);
+    if (straight_alpha) {
+        f_color.rgb = f_color.a > 0.0 ? f_color.rgb / f_color.a : vec3(0.0);
+    }
+    if (encode_srgb) {
+        f_color.rgb = srgb_from_linear(f_color.rgb);
+    } else if (hdr_transfer == 1u) {
+        f_color.rgb *= output_scale;
+    } else if (hdr_transfer == 2u) {
+        // Encoded unpremultiplied, as PQ's curve is far steeper than alpha's
+        float alpha = straight_alpha ? 1.0 : f_color.a;
+        vec3 straight = alpha > 0.0 ? f_color.rgb / alpha : vec3(0.0);
+        f_color.rgb = pq_from_linear(BT2020_FROM_BT709 * straight * output_scale) * alpha;
+    }
+}
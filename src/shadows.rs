@@ -0,0 +1,295 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::{any::Any, sync::Arc};
+
+use egui::{
+    epaint::{ClippedShape, RectShape},
+    Color32, PaintCallbackInfo, Rect, Shape, Vec2,
+};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer},
+    device::Queue,
+    image::SampleCount,
+    pipeline::{
+        graphics::{
+            color_blend::{AttachmentBlend, BlendFactor, ColorBlendState},
+            input_assembly::{InputAssemblyState, PrimitiveTopology},
+            multisample::MultisampleState,
+            render_pass::PipelineRenderPassType,
+            vertex_input::VertexInputState,
+            viewport::ViewportState,
+        },
+        GraphicsPipeline, Pipeline,
+    },
+};
+
+use crate::{
+    callback::CallbackRenderer, diagnostics::pipeline_error_message, keyed_paint_callback,
+    layers::LayerMarkers, GuiError,
+};
+
+/// Key the shadow renderer is registered with as a [`CallbackRenderer`]
+pub(crate) const PANEL_SHADOW_KEY: &str = "egui_winit_vulkano::panel_shadow";
+
+/// Soft shadows drawn beneath windows, see [`Gui::set_panel_shadows`](crate::Gui::set_panel_shadows).
+/// The shadow is a gaussian blur of the window's rounded rect computed in the fragment shader.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PanelShadows {
+    /// Standard deviation of the blur in points
+    pub blur: f32,
+    /// How much larger than the window the shadow is, in points
+    pub spread: f32,
+    /// Offset of the shadow from the window in points
+    pub offset: Vec2,
+    pub color: Color32,
+    /// Samples taken along the blur per pixel, 4 is plenty for most blur sizes
+    pub quality: u32,
+}
+
+impl Default for PanelShadows {
+    fn default() -> Self {
+        PanelShadows {
+            blur: 8.0,
+            spread: 0.0,
+            offset: egui::vec2(0.0, 4.0),
+            color: Color32::from_black_alpha(96),
+            quality: 4,
+        }
+    }
+}
+
+/// Shadow of one window given to the renderer as paint callback data
+struct PanelShadow {
+    rect: Rect,
+    rounding: f32,
+    shadows: PanelShadows,
+}
+
+/// Replaces the markers at the start of window layers with shadows of the windows' frames, which egui paints
+/// as the first shape of the layer
+pub(crate) fn add_panel_shadows(
+    shapes: &mut [ClippedShape],
+    markers: &LayerMarkers,
+    shadows: PanelShadows,
+    screen_rect: Rect,
+) {
+    for i in 0..shapes.len() {
+        let is_marker = match &shapes[i].1 {
+            Shape::Callback(callback) => markers.starts_layer(callback),
+            _ => false,
+        };
+        if !is_marker {
+            continue;
+        }
+        let frame = shapes[i + 1..]
+            .iter()
+            .find(|ClippedShape(_, shape)| !matches!(shape, Shape::Callback(_) | Shape::Noop))
+            .and_then(|ClippedShape(_, shape)| frame_rect(shape));
+        shapes[i].1 = match frame {
+            Some(RectShape { rect, rounding, .. }) => {
+                Shape::Callback(keyed_paint_callback(screen_rect, PANEL_SHADOW_KEY, PanelShadow {
+                    rect,
+                    rounding: rounding.nw.max(rounding.ne).max(rounding.sw).max(rounding.se),
+                    shadows,
+                }))
+            }
+            None => Shape::Noop,
+        };
+    }
+}
+
+/// Window frames are a rect, possibly along with egui's own shadow
+fn frame_rect(shape: &Shape) -> Option<RectShape> {
+    match shape {
+        Shape::Rect(rect) => Some(*rect),
+        Shape::Vec(shapes) => shapes.iter().find_map(frame_rect),
+        _ => None,
+    }
+}
+
+/// Draws [`PanelShadow`]s
+pub(crate) struct PanelShadowRenderer {
+    pipeline: Arc<GraphicsPipeline>,
+}
+
+impl PanelShadowRenderer {
+    pub fn new(
+        gfx_queue: Arc<Queue>,
+        render_pass: PipelineRenderPassType,
+        sample_count: SampleCount,
+    ) -> Result<PanelShadowRenderer, GuiError> {
+        let vs = vs::load(gfx_queue.device().clone())?;
+        let fs = fs::load(gfx_queue.device().clone())?;
+        // Premultiplied alpha like egui
+        let mut blend = AttachmentBlend::alpha();
+        blend.color_source = BlendFactor::One;
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(VertexInputState::new())
+            .vertex_shader(vs.entry_point("main").ok_or(GuiError::NoShaderEntryPoint)?, ())
+            .input_assembly_state(
+                InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip),
+            )
+            .fragment_shader(fs.entry_point("main").ok_or(GuiError::NoShaderEntryPoint)?, ())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_dynamic(1))
+            .color_blend_state(ColorBlendState::new(1).blend(blend))
+            .multisample_state(MultisampleState {
+                rasterization_samples: sample_count,
+                ..Default::default()
+            })
+            .render_pass(render_pass.clone())
+            .build(gfx_queue.device().clone())
+            .map_err(|err| {
+                GuiError::PipelineCreation(pipeline_error_message(
+                    err,
+                    gfx_queue.device(),
+                    &render_pass,
+                    sample_count,
+                ))
+            })?;
+        Ok(PanelShadowRenderer { pipeline })
+    }
+}
+
+impl CallbackRenderer for PanelShadowRenderer {
+    fn draw(
+        &self,
+        info: &PaintCallbackInfo,
+        data: &(dyn Any + Send + Sync),
+        builder: &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+    ) {
+        let PanelShadow { rect, rounding, shadows } = match data.downcast_ref::<PanelShadow>() {
+            Some(shadow) => shadow,
+            None => return,
+        };
+        let rect = rect.translate(shadows.offset).expand(shadows.spread);
+        let push_constants = vs::ty::PushConstants {
+            color: egui::Rgba::from(shadows.color).to_array(),
+            screen_size: [info.viewport.width(), info.viewport.height()],
+            rect_min: [rect.min.x - info.viewport.min.x, rect.min.y - info.viewport.min.y],
+            rect_max: [rect.max.x - info.viewport.min.x, rect.max.y - info.viewport.min.y],
+            sigma: shadows.blur.max(0.01),
+            corner_radius: (*rounding + shadows.spread).max(0.0),
+            samples: shadows.quality.max(1) as i32,
+        };
+        builder
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .push_constants(self.pipeline.layout().clone(), 0, push_constants)
+            .draw(4, 1, 0, 0)
+            .unwrap();
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        vulkan_version: "1.0",
+        spirv_version: "1.0",
+        src: "
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec4 color;
+    vec2 screen_size;
+    vec2 rect_min;
+    vec2 rect_max;
+    float sigma;
+    float corner_radius;
+    int samples;
+} push_constants;
+
+layout(location = 0) out vec2 v_point;
+layout(location = 1) flat out vec4 v_color;
+layout(location = 2) flat out vec2 v_rect_min;
+layout(location = 3) flat out vec2 v_rect_max;
+layout(location = 4) flat out float v_sigma;
+layout(location = 5) flat out float v_corner_radius;
+layout(location = 6) flat out int v_samples;
+
+void main() {
+    // Quad covering the rect & its blur, as a triangle strip
+    vec2 corner = vec2(gl_VertexIndex & 1, gl_VertexIndex >> 1);
+    vec2 margin = vec2(3.0 * push_constants.sigma);
+    vec2 point = mix(push_constants.rect_min - margin, push_constants.rect_max + margin, corner);
+    gl_Position = vec4(point / push_constants.screen_size * 2.0 - 1.0, 0.0, 1.0);
+    v_point = point;
+    v_color = push_constants.color;
+    v_rect_min = push_constants.rect_min;
+    v_rect_max = push_constants.rect_max;
+    v_sigma = push_constants.sigma;
+    v_corner_radius = push_constants.corner_radius;
+    v_samples = push_constants.samples;
+}"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        vulkan_version: "1.0",
+        spirv_version: "1.0",
+        src: "
+#version 450
+
+layout(location = 0) in vec2 v_point;
+layout(location = 1) flat in vec4 v_color;
+layout(location = 2) flat in vec2 v_rect_min;
+layout(location = 3) flat in vec2 v_rect_max;
+layout(location = 4) flat in float v_sigma;
+layout(location = 5) flat in float v_corner_radius;
+layout(location = 6) flat in int v_samples;
+
+layout(location = 0) out vec4 f_color;
+
+// Gaussian blurred rounded rect, after Evan Wallace's fast rounded rectangle shadows
+vec2 erf(vec2 x) {
+    vec2 s = sign(x);
+    vec2 a = abs(x);
+    x = 1.0 + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    x *= x;
+    return s - s / (x * x);
+}
+
+float gaussian(float x, float sigma) {
+    return exp(-(x * x) / (2.0 * sigma * sigma)) / (2.506628275 * sigma);
+}
+
+// Blur along x of the rect's row at y
+float shadow_x(float x, float y, float sigma, float corner, vec2 half_size) {
+    float delta = min(half_size.y - corner - abs(y), 0.0);
+    float curved = half_size.x - corner + sqrt(max(0.0, corner * corner - delta * delta));
+    vec2 integral = 0.5 + 0.5 * erf((x + vec2(-curved, curved)) * (0.7071067 / sigma));
+    return integral.y - integral.x;
+}
+
+float shadow(vec2 point, float sigma, float corner) {
+    vec2 center = (v_rect_min + v_rect_max) * 0.5;
+    vec2 half_size = (v_rect_max - v_rect_min) * 0.5;
+    corner = min(corner, min(half_size.x, half_size.y));
+    point -= center;
+    // Integrate the blur along y over the rows within 3 sigma
+    float low = point.y - half_size.y;
+    float high = point.y + half_size.y;
+    float start = clamp(-3.0 * sigma, low, high);
+    float end = clamp(3.0 * sigma, low, high);
+    float step = (end - start) / float(v_samples);
+    float y = start + step * 0.5;
+    float value = 0.0;
+    for (int i = 0; i < v_samples; i++) {
+        value += shadow_x(point.x, point.y - y, sigma, corner, half_size) * gaussian(y, sigma) * step;
+        y += step;
+    }
+    return value;
+}
+
+void main() {
+    f_color = v_color * shadow(v_point, v_sigma, v_corner_radius);
+}"
+    }
+}
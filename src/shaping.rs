@@ -0,0 +1,273 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use egui::{epaint::Mesh, Color32, ColorImage, Pos2, Rect, Shape, TextureHandle};
+
+/// Glyph positioned by a [`TextShaper`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapedGlyph {
+    /// Identifies the glyph (e.g. font & glyph index) for [`TextShaper::rasterize`] & caching in the atlas
+    pub key: u64,
+    /// Where the glyph's bitmap goes, in points relative to the text's origin (left end of the baseline, also for
+    /// right-to-left text)
+    pub rect: Rect,
+}
+
+/// External text shaper (e.g. harfbuzz or swash) for scripts egui's own text layout handles poorly.
+/// Install with [`Gui::set_text_shaper`](crate::Gui::set_text_shaper) & draw with
+/// [`Gui::shaped_text`](crate::Gui::shaped_text).
+pub trait TextShaper: Send + Sync {
+    /// Shapes `text` of `font_size` points into positioned glyphs in visual order, i.e. bidi reordering is up to
+    /// the shaper. `direction` is the paragraph direction, never `Auto`.
+    fn shape(&self, text: &str, font_size: f32, direction: TextDirection) -> Vec<ShapedGlyph>;
+
+    /// Rasterizes a glyph returned by `shape` at `font_size * pixels_per_point` pixels, covering the glyph's rect.
+    /// Colors should be premultiplied white coverage (e.g. `Color32::from_white_alpha`) to be tinted when drawn,
+    /// but color glyphs (emoji) work too. `None` for glyphs without a bitmap (e.g. spaces).
+    fn rasterize(&self, key: u64, font_size: f32, pixels_per_point: f32) -> Option<ColorImage>;
+}
+
+/// One pixel gap between glyphs so linear filtering doesn't bleed neighbors in
+const GLYPH_PADDING: usize = 1;
+/// Atlas pages allocated before starting over, scripts with many glyphs (e.g. CJK or Indic) need more than one
+const MAX_GLYPH_ATLAS_PAGES: usize = 4;
+
+/// Paragraph direction of shaped text, see [`Gui::set_text_direction`](crate::Gui::set_text_direction)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDirection {
+    LeftToRight,
+    RightToLeft,
+    /// Direction of the first strong (letter) character, right-to-left for Hebrew & Arabic scripts
+    Auto,
+}
+
+impl Default for TextDirection {
+    fn default() -> Self {
+        TextDirection::Auto
+    }
+}
+
+impl TextDirection {
+    /// Resolves `Auto` for `text`
+    pub fn resolve(self, text: &str) -> TextDirection {
+        if self != TextDirection::Auto {
+            return self;
+        }
+        match text.chars().find(|c| c.is_alphabetic()) {
+            Some(c) if is_rtl_char(c) => TextDirection::RightToLeft,
+            _ => TextDirection::LeftToRight,
+        }
+    }
+}
+
+/// Hebrew, Arabic, Syriac, Thaana, NKo, Samaritan, Mandaic & their presentation forms
+fn is_rtl_char(c: char) -> bool {
+    matches!(c, '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}')
+}
+
+/// Page of the glyph atlas, uploaded as an egui managed texture.
+/// Egui keeps one texture update per frame, so glyphs are packed into a copy of the page and the changed region
+/// is uploaded at the end of the frame.
+struct AtlasPage {
+    texture: TextureHandle,
+    pixels: ColorImage,
+    /// Min & max corners of the region changed this frame
+    dirty: Option<([usize; 2], [usize; 2])>,
+    cursor: [usize; 2],
+    row_height: usize,
+}
+
+impl AtlasPage {
+    fn new(ctx: &egui::Context, size: [usize; 2]) -> AtlasPage {
+        AtlasPage {
+            texture: ctx.load_texture("shaped_glyphs", ColorImage::new(size, Color32::TRANSPARENT)),
+            pixels: ColorImage::new(size, Color32::TRANSPARENT),
+            dirty: None,
+            cursor: [0, 0],
+            row_height: 0,
+        }
+    }
+
+    /// Packs the glyph on shelves, `None` if the page is full
+    fn insert(&mut self, image: &ColorImage) -> Option<Rect> {
+        let size = self.pixels.size;
+        let [width, height] = image.size;
+        let mut pos = self.cursor;
+        let mut row_height = self.row_height;
+        if pos[0] + width + GLYPH_PADDING > size[0] {
+            pos = [0, pos[1] + row_height];
+            row_height = 0;
+        }
+        if pos[1] + height + GLYPH_PADDING > size[1] {
+            return None;
+        }
+        for y in 0..height {
+            let row = (pos[1] + y) * size[0] + pos[0];
+            self.pixels.pixels[row..row + width]
+                .copy_from_slice(&image.pixels[y * width..(y + 1) * width]);
+        }
+        let max = [pos[0] + width, pos[1] + height];
+        self.dirty = Some(match self.dirty {
+            Some((dirty_min, dirty_max)) => {
+                ([dirty_min[0].min(pos[0]), dirty_min[1].min(pos[1])], [
+                    dirty_max[0].max(max[0]),
+                    dirty_max[1].max(max[1]),
+                ])
+            }
+            None => (pos, max),
+        });
+        self.cursor = [pos[0] + width + GLYPH_PADDING, pos[1]];
+        self.row_height = row_height.max(height + GLYPH_PADDING);
+        Some(Rect::from_min_max(
+            egui::pos2(pos[0] as f32 / size[0] as f32, pos[1] as f32 / size[1] as f32),
+            egui::pos2(max[0] as f32 / size[0] as f32, max[1] as f32 / size[1] as f32),
+        ))
+    }
+
+    fn flush(&mut self) {
+        let (min, max) = match self.dirty.take() {
+            Some(dirty) => dirty,
+            None => return,
+        };
+        let size = self.pixels.size;
+        let width = max[0] - min[0];
+        let mut region = ColorImage::new([width, max[1] - min[1]], Color32::TRANSPARENT);
+        for y in min[1]..max[1] {
+            let row = y * size[0];
+            region.pixels[(y - min[1]) * width..(y - min[1] + 1) * width]
+                .copy_from_slice(&self.pixels.pixels[row + min[0]..row + max[0]]);
+        }
+        self.texture.set_partial(min, region);
+    }
+}
+
+/// Atlas of glyphs rasterized by a [`TextShaper`], growing up to [`MAX_GLYPH_ATLAS_PAGES`] pages before starting
+/// over on the next frame
+pub(crate) struct GlyphAtlas {
+    shaper: Arc<dyn TextShaper>,
+    page_size: [usize; 2],
+    pages: Vec<AtlasPage>,
+    /// Page & uv rect of rasterized glyphs by glyph key & pixel size. `None` for glyphs without a bitmap.
+    glyphs: AHashMap<(u64, u32), Option<(usize, Rect)>>,
+    /// Set when the pages filled up, they're evicted on the next frame
+    evict: bool,
+}
+
+impl GlyphAtlas {
+    pub fn new(shaper: Arc<dyn TextShaper>, side: usize) -> GlyphAtlas {
+        GlyphAtlas {
+            shaper,
+            page_size: [side, side],
+            pages: vec![],
+            glyphs: AHashMap::default(),
+            evict: false,
+        }
+    }
+
+    /// Number of texture pages in use
+    pub fn num_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Shapes `text` & builds meshes of its glyphs (one per atlas page) with the start of the baseline at `pos`:
+    /// the left end for left-to-right text & the right end for right-to-left text
+    pub fn shape_text(
+        &mut self,
+        ctx: &egui::Context,
+        pos: Pos2,
+        text: &str,
+        font_size: f32,
+        direction: TextDirection,
+        color: Color32,
+    ) -> Shape {
+        let pixels_per_point = ctx.pixels_per_point();
+        let direction = direction.resolve(text);
+        let glyphs = self.shaper.shape(text, font_size, direction);
+        let offset = match direction {
+            TextDirection::RightToLeft => {
+                let width = glyphs.iter().map(|glyph| glyph.rect.max.x).fold(0.0, f32::max);
+                pos.to_vec2() - egui::vec2(width, 0.0)
+            }
+            _ => pos.to_vec2(),
+        };
+        let mut meshes: Vec<Mesh> = vec![];
+        for glyph in glyphs {
+            if let Some((page, uv)) = self.glyph_uv(ctx, glyph.key, font_size, pixels_per_point) {
+                let texture_id = self.pages[page].texture.id();
+                let mesh_index = match meshes.iter().position(|mesh| mesh.texture_id == texture_id)
+                {
+                    Some(index) => index,
+                    None => {
+                        meshes.push(Mesh::with_texture(texture_id));
+                        meshes.len() - 1
+                    }
+                };
+                meshes[mesh_index].add_rect_with_uv(glyph.rect.translate(offset), uv, color);
+            }
+        }
+        Shape::Vec(meshes.into_iter().map(Shape::mesh).collect())
+    }
+
+    fn glyph_uv(
+        &mut self,
+        ctx: &egui::Context,
+        key: u64,
+        font_size: f32,
+        pixels_per_point: f32,
+    ) -> Option<(usize, Rect)> {
+        let cache_key = (key, (font_size * pixels_per_point).to_bits());
+        if let Some(uv) = self.glyphs.get(&cache_key) {
+            return *uv;
+        }
+        let uv = self
+            .shaper
+            .rasterize(key, font_size, pixels_per_point)
+            .and_then(|image| self.insert(ctx, &image));
+        self.glyphs.insert(cache_key, uv);
+        uv
+    }
+
+    /// Inserts the glyph on the last page, adding a page when full. When out of pages, the frame keeps adding them &
+    /// the atlas starts over on the next one.
+    fn insert(&mut self, ctx: &egui::Context, image: &ColorImage) -> Option<(usize, Rect)> {
+        let [width, height] = image.size;
+        if width + GLYPH_PADDING > self.page_size[0] || height + GLYPH_PADDING > self.page_size[1] {
+            return None;
+        }
+        if let Some(uv) = self.pages.last_mut().and_then(|page| page.insert(image)) {
+            return Some((self.pages.len() - 1, uv));
+        }
+        if self.pages.len() >= MAX_GLYPH_ATLAS_PAGES {
+            // Meshes built earlier this frame still sample the pages
+            self.evict = true;
+        }
+        self.pages.push(AtlasPage::new(ctx, self.page_size));
+        let uv = self.pages.last_mut().unwrap().insert(image)?;
+        Some((self.pages.len() - 1, uv))
+    }
+
+    /// Evicts all glyphs if the pages filled up last frame. Must be called after beginning egui's frame, so that
+    /// the pages are freed with its textures delta, after the last frame's draw.
+    pub fn begin_frame(&mut self) {
+        if std::mem::take(&mut self.evict) {
+            self.glyphs.clear();
+            self.pages.clear();
+        }
+    }
+
+    /// Uploads glyphs inserted this frame. Must be called before ending egui's frame.
+    pub fn flush(&mut self) {
+        for page in &mut self.pages {
+            page.flush();
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use ab_glyph::{Font, FontRef};
+use egui::{FontData, FontDefinitions, FontFamily};
+
+/// Fonts installed on the system, to look native without bundling fonts. Load them in the loader of
+/// [`Gui::set_fonts_async`](crate::Gui::set_fonts_async), pick families by name with
+/// [`SystemFonts::add_family`] & cover the scripts the ui shows with [`SystemFonts::add_fallbacks`].
+pub struct SystemFonts {
+    db: fontdb::Database,
+}
+
+impl SystemFonts {
+    /// Scans the system's font directories. It takes a while, so call it off the main thread, e.g. in the
+    /// loader of [`Gui::set_fonts_async`](crate::Gui::set_fonts_async).
+    pub fn load() -> SystemFonts {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        SystemFonts { db }
+    }
+
+    /// Names of the installed font families, sorted
+    pub fn families(&self) -> Vec<String> {
+        let mut families: Vec<String> =
+            self.db.faces().iter().map(|face| face.family.clone()).collect();
+        families.sort_unstable();
+        families.dedup();
+        families
+    }
+
+    /// Regular face of an installed family, e.g. "Noto Sans CJK JP"
+    pub fn font_data(&self, family: &str) -> Option<FontData> {
+        let id = self.db.query(&fontdb::Query {
+            families: &[fontdb::Family::Name(family)],
+            ..Default::default()
+        })?;
+        self.face_data(id)
+    }
+
+    /// Adds the regular face of an installed family as the first choice of `egui_family`. Returns false if the
+    /// family isn't installed.
+    pub fn add_family(
+        &self,
+        definitions: &mut FontDefinitions,
+        family: &str,
+        egui_family: FontFamily,
+    ) -> bool {
+        let font_data = match self.font_data(family) {
+            Some(font_data) => font_data,
+            None => return false,
+        };
+        definitions.font_data.insert(family.to_string(), font_data);
+        let fonts = definitions.families.entry(egui_family).or_default();
+        fonts.retain(|name| name != family);
+        fonts.insert(0, family.to_string());
+        true
+    }
+
+    /// Adds installed fonts as the last choices of `egui_family` until it covers the characters of `text`, e.g.
+    /// a few words in each script the ui shows. Fonts covering the most missing characters are picked first.
+    /// Returns the added families.
+    pub fn add_fallbacks(
+        &self,
+        definitions: &mut FontDefinitions,
+        egui_family: FontFamily,
+        text: &str,
+    ) -> Vec<String> {
+        let fonts = definitions.families.get(&egui_family).cloned().unwrap_or_default();
+        let mut missing: Vec<char> = text
+            .chars()
+            .filter(|c| !c.is_whitespace() && !c.is_control())
+            .filter(|&c| {
+                !fonts.iter().filter_map(|name| definitions.font_data.get(name)).any(|font_data| {
+                    FontRef::try_from_slice_and_index(&font_data.font, font_data.index)
+                        .map_or(false, |font| font.glyph_id(c).0 != 0)
+                })
+            })
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+
+        if missing.is_empty() {
+            return vec![];
+        }
+
+        // Characters each regular face covers, bold or italic fallbacks would look out of place
+        let mut candidates: Vec<(&str, fontdb::ID, Vec<char>)> = self
+            .db
+            .faces()
+            .iter()
+            .filter(|face| {
+                face.style == fontdb::Style::Normal && face.weight == fontdb::Weight::NORMAL
+            })
+            .filter_map(|face| {
+                let covered = self.db.with_face_data(face.id, |data, index| {
+                    FontRef::try_from_slice_and_index(data, index).map_or(vec![], |font| {
+                        missing.iter().copied().filter(|&c| font.glyph_id(c).0 != 0).collect()
+                    })
+                })?;
+                Some((face.family.as_str(), face.id, covered))
+            })
+            .collect();
+        candidates.sort_unstable_by_key(|(family, ..)| *family);
+        candidates.dedup_by_key(|(family, ..)| *family);
+
+        let mut added = vec![];
+        while !missing.is_empty() {
+            let best = candidates
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, (_, _, covered))| {
+                    covered.iter().filter(|c| missing.contains(c)).count()
+                })
+                .map(|(i, _)| i);
+            let (family, id, covered) = match best {
+                Some(i) if candidates[i].2.iter().any(|c| missing.contains(c)) => {
+                    candidates.swap_remove(i)
+                }
+                _ => break,
+            };
+            if let Some(font_data) = self.face_data(id) {
+                definitions.font_data.insert(family.to_string(), font_data);
+                let fonts = definitions.families.entry(egui_family.clone()).or_default();
+                if !fonts.iter().any(|name| name == family) {
+                    fonts.push(family.to_string());
+                }
+                added.push(family.to_string());
+            }
+            missing.retain(|c| !covered.contains(c));
+        }
+        added
+    }
+
+    fn face_data(&self, id: fontdb::ID) -> Option<FontData> {
+        self.db.with_face_data(id, |data, index| {
+            let mut font_data = FontData::from_owned(data.to_vec());
+            font_data.index = index;
+            font_data
+        })
+    }
+}
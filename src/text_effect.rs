@@ -0,0 +1,101 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use ahash::AHashMap;
+use egui::{
+    epaint::{Mesh, Primitive, Vertex, WHITE_UV},
+    ClippedPrimitive, Color32, TextureId, Vec2,
+};
+
+/// Drawn beneath all text to keep it readable over busy backgrounds, see
+/// [`Gui::set_text_effect`](crate::Gui::set_text_effect)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextEffect {
+    /// Copy of the glyphs offset by `offset` points
+    Shadow { offset: Vec2, color: Color32 },
+    /// Copies of the glyphs offset by `width` points in eight directions
+    Outline { width: f32, color: Color32 },
+}
+
+impl TextEffect {
+    fn offsets(&self) -> Vec<Vec2> {
+        match *self {
+            TextEffect::Shadow { offset, .. } => vec![offset],
+            TextEffect::Outline { width, .. } => (0..8)
+                .map(|i| Vec2::angled(i as f32 * std::f32::consts::TAU / 8.0) * width)
+                .collect(),
+        }
+    }
+
+    fn color(&self) -> Color32 {
+        match *self {
+            TextEffect::Shadow { color, .. } | TextEffect::Outline { color, .. } => color,
+        }
+    }
+}
+
+/// Adds the effect's copies of glyphs in egui's font texture right before the glyphs themselves, so they cover
+/// whatever was drawn beneath the text but not other text
+pub(crate) fn apply_text_effect(primitives: &mut [ClippedPrimitive], effect: TextEffect) {
+    let offsets = effect.offsets();
+    let color = effect.color();
+    for ClippedPrimitive { primitive, .. } in primitives {
+        if let Primitive::Mesh(mesh) = primitive {
+            if mesh.texture_id == TextureId::default() {
+                add_glyph_copies(mesh, &offsets, color);
+            }
+        }
+    }
+}
+
+fn add_glyph_copies(mesh: &mut Mesh, offsets: &[Vec2], color: Color32) {
+    let mut vertices = mesh.vertices.clone();
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    // Consecutive glyph triangles, untextured ones (e.g. a button's background) go in between
+    let mut run: Vec<u32> = vec![];
+    for triangle in mesh.indices.chunks_exact(3) {
+        if triangle.iter().all(|&i| mesh.vertices[i as usize].uv != WHITE_UV) {
+            run.extend_from_slice(triangle);
+        } else {
+            push_run(&mesh.vertices, &mut vertices, &mut indices, &run, offsets, color);
+            run.clear();
+            indices.extend_from_slice(triangle);
+        }
+    }
+    push_run(&mesh.vertices, &mut vertices, &mut indices, &run, offsets, color);
+    mesh.vertices = vertices;
+    mesh.indices = indices;
+}
+
+/// Pushes offset copies of the run & then the run itself
+fn push_run(
+    glyph_vertices: &[Vertex],
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    run: &[u32],
+    offsets: &[Vec2],
+    color: Color32,
+) {
+    for &offset in offsets {
+        let mut copies = AHashMap::<u32, u32>::default();
+        for &i in run {
+            let copy = *copies.entry(i).or_insert_with(|| {
+                let glyph = glyph_vertices[i as usize];
+                vertices.push(Vertex {
+                    pos: glyph.pos + offset,
+                    uv: glyph.uv,
+                    color: color.linear_multiply(glyph.color.a() as f32 / 255.0),
+                });
+                vertices.len() as u32 - 1
+            });
+            indices.push(copy);
+        }
+    }
+    indices.extend_from_slice(run);
+}
@@ -7,60 +7,361 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
-use std::sync::Arc;
+use std::{convert::TryFrom, sync::Arc};
 
-use image::RgbaImage;
 use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{
+        AutoCommandBufferBuilder, BufferImageCopy, CommandBufferUsage, CopyBufferToImageInfo,
+        PrimaryCommandBuffer,
+    },
     device::Queue,
+    format::Format,
     image::{
-        immutable::ImmutableImageCreationError, view::ImageView, ImageDimensions,
-        ImageViewAbstract, ImmutableImage, MipmapsCount,
+        immutable::ImmutableImageCreationError, view::ImageView, ImageAspects, ImageDimensions,
+        ImageLayout, ImageSubresourceLayers, ImageUsage, ImageViewAbstract, ImmutableImage,
+        MipmapsCount,
     },
+    sync::{FenceSignalFuture, GpuFuture},
+    DeviceSize,
 };
 
+use crate::GuiError;
+
 pub fn immutable_texture_from_bytes(
     queue: Arc<Queue>,
     byte_data: &[u8],
     dimensions: [u32; 2],
     format: vulkano::format::Format,
+) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, ImmutableImageCreationError> {
+    texture_from_bytes(queue, byte_data, dimensions, format, MipmapsCount::One)
+}
+
+/// Same as `immutable_texture_from_bytes`, but with a full mip chain blitted from the image, so that it doesn't
+/// shimmer when shown smaller than its size. `format` must support linear filtering blits. Block-compressed formats
+/// can't be blitted to, so they get a single level; load their pre-generated levels with
+/// [`immutable_texture_from_ktx2`] or [`immutable_texture_from_dds`] instead.
+pub fn immutable_texture_from_bytes_with_mipmaps(
+    queue: Arc<Queue>,
+    byte_data: &[u8],
+    dimensions: [u32; 2],
+    format: vulkano::format::Format,
+) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, ImmutableImageCreationError> {
+    texture_from_bytes(queue, byte_data, dimensions, format, MipmapsCount::Log2)
+}
+
+pub fn immutable_texture_from_file(
+    queue: Arc<Queue>,
+    file_bytes: &[u8],
+    format: vulkano::format::Format,
+) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, GuiError> {
+    let rgba = image::load_from_memory(file_bytes)?.to_rgba8();
+    let dimensions = rgba.dimensions();
+    Ok(immutable_texture_from_bytes(queue, &rgba, [dimensions.0, dimensions.1], format)?)
+}
+
+/// Same as `immutable_texture_from_file`, but with a full mip chain, see
+/// [`immutable_texture_from_bytes_with_mipmaps`]
+pub fn immutable_texture_from_file_with_mipmaps(
+    queue: Arc<Queue>,
+    file_bytes: &[u8],
+    format: vulkano::format::Format,
+) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, GuiError> {
+    let rgba = image::load_from_memory(file_bytes)?.to_rgba8();
+    let dimensions = rgba.dimensions();
+    Ok(immutable_texture_from_bytes_with_mipmaps(
+        queue,
+        &rgba,
+        [dimensions.0, dimensions.1],
+        format,
+    )?)
+}
+
+fn texture_from_bytes(
+    queue: Arc<Queue>,
+    byte_data: &[u8],
+    dimensions: [u32; 2],
+    format: vulkano::format::Format,
+    mip_levels: MipmapsCount,
 ) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, ImmutableImageCreationError> {
     let vko_dims =
         ImageDimensions::Dim2d { width: dimensions[0], height: dimensions[1], array_layers: 1 };
 
-    let (texture, _tex_fut) = ImmutableImage::from_iter(
+    let mip_levels = if format.compression().is_some() { MipmapsCount::One } else { mip_levels };
+    // Mip levels are generated with blits on the queue before the image's first use
+    let (texture, _tex_fut) =
+        ImmutableImage::from_iter(byte_data.iter().cloned(), vko_dims, mip_levels, format, queue)?;
+
+    Ok(ImageView::new_default(texture).unwrap())
+}
+
+/// Texture being uploaded & the future signaled once it can be sampled
+pub(crate) type TextureUpload = (
+    Arc<dyn ImageViewAbstract + Send + Sync + 'static>,
+    FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>,
+);
+
+/// Same as `immutable_texture_from_bytes`, but the upload isn't waited for. The image can be used on any queue
+/// family once the returned future has signaled.
+pub(crate) fn immutable_texture_from_bytes_async(
+    queue: Arc<Queue>,
+    byte_data: &[u8],
+    dimensions: [u32; 2],
+    format: Format,
+) -> Result<TextureUpload, GuiError> {
+    let vko_dims =
+        ImageDimensions::Dim2d { width: dimensions[0], height: dimensions[1], array_layers: 1 };
+    // Shared concurrently by the device's active queue families, so no ownership transfer is needed
+    let (texture, upload) = ImmutableImage::from_iter(
         byte_data.iter().cloned(),
         vko_dims,
         MipmapsCount::One,
         format,
         queue,
     )?;
-
-    Ok(ImageView::new_default(texture).unwrap())
+    let upload = upload.boxed_send_sync().then_signal_fence_and_flush()?;
+    Ok((ImageView::new_default(texture).unwrap(), upload))
 }
 
-pub fn immutable_texture_from_file(
+/// Loads a KTX2 container with its mip levels, in the Vulkan format it was written in. Supercompressed files &
+/// arrays, cubemaps & 3D textures aren't supported.
+pub fn immutable_texture_from_ktx2(
     queue: Arc<Queue>,
     file_bytes: &[u8],
-    format: vulkano::format::Format,
-) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, ImmutableImageCreationError> {
-    use image::GenericImageView;
+) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, GuiError> {
+    const IDENTIFIER: [u8; 12] =
+        [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+    if file_bytes.len() < 80 || file_bytes[..12] != IDENTIFIER {
+        return Err(GuiError::InvalidTextureFile("not a KTX2 file"));
+    }
+    let vk_format = read_u32(file_bytes, 12);
+    let [width, height, depth, layers, faces, level_count, supercompression] =
+        [20, 24, 28, 32, 36, 40, 44].map(|offset| read_u32(file_bytes, offset));
+    if supercompression != 0 {
+        return Err(GuiError::InvalidTextureFile("supercompressed KTX2 files aren't supported"));
+    }
+    if depth > 1 || layers > 1 || faces > 1 {
+        return Err(GuiError::InvalidTextureFile("only 2D KTX2 textures are supported"));
+    }
+    let format = Format::try_from(ash::vk::Format::from_raw(vk_format as i32))
+        .map_err(|_| GuiError::InvalidTextureFile("unknown KTX2 format"))?;
+    let level_count = clamp_level_count([width, height], level_count)?;
+    // Level index follows the 80 byte header & index, largest level first
+    let levels = (0..level_count as usize)
+        .map(|level| {
+            let entry = 80 + level * 24;
+            if file_bytes.len() < entry + 16 {
+                return Err(GuiError::InvalidTextureFile("truncated KTX2 level index"));
+            }
+            let offset = usize::try_from(read_u64(file_bytes, entry)).ok();
+            let length = usize::try_from(read_u64(file_bytes, entry + 8)).ok();
+            offset
+                .zip(length)
+                .and_then(|(offset, length)| file_bytes.get(offset..offset.checked_add(length)?))
+                .ok_or(GuiError::InvalidTextureFile("truncated KTX2 level data"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    immutable_texture_from_levels(queue, &levels, [width, height], format)
+}
 
-    let img = image::load_from_memory(file_bytes).expect("Failed to load image from bytes");
-    let rgba = if let Some(rgba) = img.as_rgba8() {
-        rgba.to_owned().to_vec()
-    } else {
-        // Convert rgb to rgba
-        let rgb = img.as_rgb8().unwrap().to_owned();
-        let mut raw_data = vec![];
-        for val in rgb.chunks(3) {
-            raw_data.push(val[0]);
-            raw_data.push(val[1]);
-            raw_data.push(val[2]);
-            raw_data.push(255);
+/// Loads a DDS file with its mip levels. BC1-BC7 & 8 bit RGBA/BGRA formats are supported, arrays, cubemaps & 3D
+/// textures aren't. Files without a DX10 header don't say whether they're sRGB, so they're loaded as UNORM; register
+/// them with [`SamplerOptions::srgb`](crate::SamplerOptions::srgb) if they are.
+pub fn immutable_texture_from_dds(
+    queue: Arc<Queue>,
+    file_bytes: &[u8],
+) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, GuiError> {
+    if file_bytes.len() < 128 || &file_bytes[..4] != b"DDS " {
+        return Err(GuiError::InvalidTextureFile("not a DDS file"));
+    }
+    const CAPS2_CUBEMAP: u32 = 0x200;
+    const CAPS2_VOLUME: u32 = 0x200000;
+    const PF_FOURCC: u32 = 0x4;
+    const PF_RGB: u32 = 0x40;
+    let height = read_u32(file_bytes, 12);
+    let width = read_u32(file_bytes, 16);
+    let level_count = clamp_level_count([width, height], read_u32(file_bytes, 28))?;
+    let pixel_format_flags = read_u32(file_bytes, 80);
+    let four_cc = &file_bytes[84..88];
+    if read_u32(file_bytes, 112) & (CAPS2_CUBEMAP | CAPS2_VOLUME) != 0 {
+        return Err(GuiError::InvalidTextureFile("only 2D DDS textures are supported"));
+    }
+    let (format, data_offset) = if pixel_format_flags & PF_FOURCC != 0 && four_cc == b"DX10" {
+        if file_bytes.len() < 148 {
+            return Err(GuiError::InvalidTextureFile("truncated DDS DX10 header"));
+        }
+        if read_u32(file_bytes, 140) > 1 {
+            return Err(GuiError::InvalidTextureFile("only 2D DDS textures are supported"));
         }
-        let new_rgba = RgbaImage::from_raw(rgb.width(), rgb.height(), raw_data).unwrap();
-        new_rgba.to_vec()
+        let format = match read_u32(file_bytes, 128) {
+            2 => Format::R32G32B32A32_SFLOAT,
+            10 => Format::R16G16B16A16_SFLOAT,
+            28 => Format::R8G8B8A8_UNORM,
+            29 => Format::R8G8B8A8_SRGB,
+            71 => Format::BC1_RGBA_UNORM_BLOCK,
+            72 => Format::BC1_RGBA_SRGB_BLOCK,
+            74 => Format::BC2_UNORM_BLOCK,
+            75 => Format::BC2_SRGB_BLOCK,
+            77 => Format::BC3_UNORM_BLOCK,
+            78 => Format::BC3_SRGB_BLOCK,
+            80 => Format::BC4_UNORM_BLOCK,
+            81 => Format::BC4_SNORM_BLOCK,
+            83 => Format::BC5_UNORM_BLOCK,
+            84 => Format::BC5_SNORM_BLOCK,
+            87 => Format::B8G8R8A8_UNORM,
+            91 => Format::B8G8R8A8_SRGB,
+            95 => Format::BC6H_UFLOAT_BLOCK,
+            96 => Format::BC6H_SFLOAT_BLOCK,
+            98 => Format::BC7_UNORM_BLOCK,
+            99 => Format::BC7_SRGB_BLOCK,
+            _ => return Err(GuiError::InvalidTextureFile("unsupported DDS DXGI format")),
+        };
+        (format, 148)
+    } else if pixel_format_flags & PF_FOURCC != 0 {
+        let format = match four_cc {
+            b"DXT1" => Format::BC1_RGBA_UNORM_BLOCK,
+            b"DXT2" | b"DXT3" => Format::BC2_UNORM_BLOCK,
+            b"DXT4" | b"DXT5" => Format::BC3_UNORM_BLOCK,
+            b"ATI1" | b"BC4U" => Format::BC4_UNORM_BLOCK,
+            b"ATI2" | b"BC5U" => Format::BC5_UNORM_BLOCK,
+            _ => return Err(GuiError::InvalidTextureFile("unsupported DDS four cc")),
+        };
+        (format, 128)
+    } else if pixel_format_flags & PF_RGB != 0 && read_u32(file_bytes, 88) == 32 {
+        let format = match read_u32(file_bytes, 92) {
+            0xFF => Format::R8G8B8A8_UNORM,
+            0xFF0000 => Format::B8G8R8A8_UNORM,
+            _ => return Err(GuiError::InvalidTextureFile("unsupported DDS channel masks")),
+        };
+        (format, 128)
+    } else {
+        return Err(GuiError::InvalidTextureFile("unsupported DDS pixel format"));
     };
-    let dimensions = img.dimensions();
-    immutable_texture_from_bytes(queue, &rgba, [dimensions.0, dimensions.1], format)
+    // Levels are stored back to back, largest first
+    let mut offset: usize = data_offset;
+    let levels = (0..level_count)
+        .map(|level| {
+            let data = usize::try_from(level_size(format, [width, height], level))
+                .ok()
+                .and_then(|length| file_bytes.get(offset..offset.checked_add(length)?))
+                .ok_or(GuiError::InvalidTextureFile("truncated DDS level data"))?;
+            offset += data.len();
+            Ok(data)
+        })
+        .collect::<Result<Vec<_>, GuiError>>()?;
+    immutable_texture_from_levels(queue, &levels, [width, height], format)
+}
+
+/// Checks that `format` can be sampled on the device & that `byte_data` holds exactly the first mip level of an
+/// image of `dimensions`, counted in whole blocks for block-compressed formats
+pub(crate) fn check_texture_data(
+    queue: &Queue,
+    byte_data: &[u8],
+    dimensions: [u32; 2],
+    format: Format,
+) -> Result<(), GuiError> {
+    let physical_device = queue.device().physical_device();
+    if !physical_device.format_properties(format).optimal_tiling_features.sampled_image {
+        return Err(GuiError::UnsupportedTextureFormat(format));
+    }
+    let expected = level_size(format, dimensions, 0) as usize;
+    if byte_data.len() != expected {
+        return Err(GuiError::WrongDataSize { found: byte_data.len(), expected });
+    }
+    Ok(())
+}
+
+/// Creates an image from the tightly packed data of each of its mip levels, largest first
+fn immutable_texture_from_levels(
+    queue: Arc<Queue>,
+    levels: &[&[u8]],
+    dimensions: [u32; 2],
+    format: Format,
+) -> Result<Arc<dyn ImageViewAbstract + Send + Sync + 'static>, GuiError> {
+    check_texture_data(&queue, levels[0], dimensions, format)?;
+    let (image, init) = ImmutableImage::uninitialized(
+        queue.device().clone(),
+        ImageDimensions::Dim2d { width: dimensions[0], height: dimensions[1], array_layers: 1 },
+        format,
+        MipmapsCount::Specific(levels.len() as u32),
+        ImageUsage { transfer_dst: true, sampled: true, ..ImageUsage::none() },
+        Default::default(),
+        ImageLayout::ShaderReadOnlyOptimal,
+        Some(queue.family()),
+    )?;
+    let buffer = CpuAccessibleBuffer::from_iter(
+        queue.device().clone(),
+        BufferUsage::transfer_src(),
+        false,
+        levels.concat(),
+    )
+    .unwrap();
+    let mut buffer_offset = 0;
+    let regions = levels
+        .iter()
+        .enumerate()
+        .map(|(level, data)| {
+            let region = BufferImageCopy {
+                buffer_offset,
+                image_subresource: ImageSubresourceLayers {
+                    aspects: ImageAspects { color: true, ..ImageAspects::none() },
+                    mip_level: level as u32,
+                    array_layers: 0..1,
+                },
+                image_extent: [
+                    level_extent(dimensions[0], level as u32),
+                    level_extent(dimensions[1], level as u32),
+                    1,
+                ],
+                ..Default::default()
+            };
+            buffer_offset += data.len() as DeviceSize;
+            region
+        })
+        .collect();
+    let mut cbb = AutoCommandBufferBuilder::primary(
+        queue.device().clone(),
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+    cbb.copy_buffer_to_image(CopyBufferToImageInfo {
+        regions,
+        ..CopyBufferToImageInfo::buffer_image(buffer, init)
+    })?;
+    let command_buffer = cbb.build().unwrap();
+    let _fut = command_buffer.execute(queue)?.then_signal_fence_and_flush()?;
+    Ok(ImageView::new_default(image).unwrap())
+}
+
+/// Level count of a texture file, at least 1 & at most a full mip chain, as files may claim any
+fn clamp_level_count(dimensions: [u32; 2], level_count: u32) -> Result<u32, GuiError> {
+    if dimensions[0] == 0 || dimensions[1] == 0 {
+        return Err(GuiError::InvalidTextureFile("texture has no texels"));
+    }
+    let largest = dimensions[0].max(dimensions[1]);
+    Ok(level_count.clamp(1, 32 - largest.leading_zeros()))
+}
+
+/// Size of a mip level along one dimension
+fn level_extent(size: u32, level: u32) -> u32 {
+    size.checked_shr(level).unwrap_or(0).max(1)
+}
+
+/// Size of a mip level's tightly packed data, in whole blocks for compressed formats
+fn level_size(format: Format, dimensions: [u32; 2], level: u32) -> DeviceSize {
+    let block_extent = format.block_extent();
+    // Rounded up without overflowing, levels are at least 1 texel
+    let blocks = |size: u32, block: u32| (level_extent(size, level) - 1) / block + 1;
+    (blocks(dimensions[0], block_extent[0]) as DeviceSize)
+        .saturating_mul(blocks(dimensions[1], block_extent[1]) as DeviceSize)
+        .saturating_mul(format.block_size().unwrap_or(0))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    read_u32(bytes, offset) as u64 | (read_u32(bytes, offset + 4) as u64) << 32
 }
@@ -0,0 +1,81 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+
+use egui::{ColorImage, Id, Vec2};
+use winit::{
+    dpi::LogicalSize,
+    window::{Fullscreen, Icon, Window},
+};
+
+/// Change to the window egui is drawn in, requested from ui code with [`send_window_command`]. Egui 0.18 has no
+/// viewport commands of its own, so this stands in for them.
+#[derive(Clone, PartialEq)]
+pub enum WindowCommand {
+    Title(String),
+    /// Inner size in points
+    InnerSize(Vec2),
+    Minimized(bool),
+    Maximized(bool),
+    /// Borderless fullscreen on the window's current monitor
+    Fullscreen(bool),
+    /// `None` removes the icon
+    Icon(Option<Arc<ColorImage>>),
+    Focus,
+}
+
+fn window_commands_id() -> Id {
+    Id::new("egui_winit_vulkano::window_commands")
+}
+
+/// Requests a change to the window egui is drawn in, e.g. from a custom title bar. Commands are carried out in
+/// order at the end of the frame.
+pub fn send_window_command(ctx: &egui::Context, command: WindowCommand) {
+    ctx.data().get_temp_mut_or_default::<Vec<WindowCommand>>(window_commands_id()).push(command);
+}
+
+/// Carries out the commands sent during the frame
+pub(crate) fn apply_window_commands(ctx: &egui::Context, window: &Window, pixels_per_point: f32) {
+    let commands: Vec<WindowCommand> = {
+        let mut data = ctx.data();
+        let commands = data.get_temp(window_commands_id()).unwrap_or_default();
+        data.remove::<Vec<WindowCommand>>(window_commands_id());
+        commands
+    };
+    for command in commands {
+        match command {
+            WindowCommand::Title(title) => window.set_title(&title),
+            WindowCommand::InnerSize(size) => {
+                // Winit's logical size is in points scaled by the window's scale factor, egui's may differ
+                let scale = pixels_per_point / window.scale_factor() as f32;
+                window.set_inner_size(LogicalSize::new(size.x * scale, size.y * scale));
+            }
+            WindowCommand::Minimized(minimized) => window.set_minimized(minimized),
+            WindowCommand::Maximized(maximized) => window.set_maximized(maximized),
+            WindowCommand::Fullscreen(fullscreen) => window.set_fullscreen(if fullscreen {
+                Some(Fullscreen::Borderless(None))
+            } else {
+                None
+            }),
+            WindowCommand::Icon(icon) => {
+                let icon = icon.and_then(|image| {
+                    let rgba = image
+                        .pixels
+                        .iter()
+                        .flat_map(|color| color.to_srgba_unmultiplied())
+                        .collect();
+                    Icon::from_rgba(rgba, image.size[0] as u32, image.size[1] as u32).ok()
+                });
+                window.set_window_icon(icon);
+            }
+            WindowCommand::Focus => window.focus_window(),
+        }
+    }
+}
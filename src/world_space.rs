@@ -0,0 +1,70 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+/// Rectangle in your scene a ui texture is mapped onto, e.g. a screen in a 3D world drawn with
+/// [`HeadlessGui::draw_to_texture`](crate::HeadlessGui::draw_to_texture). `origin` is the corner of the ui's top
+/// left, `right` & `down` its perpendicular edges towards the top right & bottom left corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldQuad {
+    pub origin: [f32; 3],
+    pub right: [f32; 3],
+    pub down: [f32; 3],
+}
+
+impl WorldQuad {
+    /// Texture coordinates where a ray (e.g. from the camera through the cursor, or of a VR controller) hits the
+    /// quad from either side, `None` if it misses it. Convert them to egui's coordinates with
+    /// [`HeadlessGui::pos_from_uv`](crate::HeadlessGui::pos_from_uv). For curved meshes, take the coordinates of
+    /// your own hit test instead.
+    pub fn ray_uv(&self, ray_origin: [f32; 3], ray_direction: [f32; 3]) -> Option<[f32; 2]> {
+        let normal = cross(self.right, self.down);
+        let denominator = dot(ray_direction, normal);
+        if denominator.abs() <= f32::EPSILON {
+            return None;
+        }
+        let distance = dot(sub(self.origin, ray_origin), normal) / denominator;
+        if distance < 0.0 {
+            return None;
+        }
+        let hit = [
+            ray_origin[0] + ray_direction[0] * distance,
+            ray_origin[1] + ray_direction[1] * distance,
+            ray_origin[2] + ray_direction[2] * distance,
+        ];
+        let relative = sub(hit, self.origin);
+        let uv = [
+            dot(relative, self.right) / dot(self.right, self.right),
+            dot(relative, self.down) / dot(self.down, self.down),
+        ];
+        let inside = uv.iter().all(|coordinate| (0.0..=1.0).contains(coordinate));
+        if inside {
+            Some(uv)
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[cfg(feature = "xr")]
+pub(crate) fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+    [a[0] / length, a[1] / length, a[2] / length]
+}
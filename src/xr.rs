@@ -0,0 +1,287 @@
+// Copyright (c) 2021 Okko Hakola
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+
+use egui::{epaint::Primitive, ClippedPrimitive, Color32, Rect, TexturesDelta};
+use vulkano::{
+    device::Queue,
+    format::Format,
+    image::{view::ImageView, AttachmentImage, ImageViewAbstract},
+    sync::GpuFuture,
+};
+
+use crate::{
+    error::GuiError,
+    renderer::Renderer,
+    world_space::{cross, dot, normalize},
+    DrawnTexture, HeadlessGui, WorldQuad,
+};
+
+/// Camera of one eye, as located by `xrLocateViews`. Both matrices are column major: `view` maps from the reference
+/// space the ui's [`WorldQuad`] is placed in to the eye's space, `projection` from there to clip space (e.g. built
+/// from the view's `fov` with Vulkan's depth range).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XrEye {
+    pub projection: [[f32; 4]; 4],
+    pub view: [[f32; 4]; 4],
+}
+
+/// Pose & size for an `XrCompositionLayerQuad` showing the ui texture where its [`WorldQuad`] is, letting the
+/// runtime reproject it for each eye. `orientation` is a quaternion as `[x, y, z, w]`, `size` is in the units of the
+/// quad, meters in OpenXR.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XrQuadLayer {
+    pub position: [f32; 3],
+    pub orientation: [f32; 4],
+    pub size: [f32; 2],
+}
+
+/// Egui on a panel in a VR or AR scene. The ui is drawn into a texture, which is either given to the runtime as a
+/// quad layer or drawn into each eye's image with [`XrGui::draw_into_eyes`], & pointed at with controller rays.
+/// Session management, swapchains & input actions stay with your OpenXR bindings.
+///
+/// vulkano 0.30 can't wrap images it didn't create, so images of OpenXR swapchains can't be drawn on directly. Copy
+/// the texture of [`XrGui::draw_ui`] into the acquired swapchain image, or draw your scene into vulkano images you
+/// copy from.
+pub struct XrGui {
+    gui: HeadlessGui,
+    quad: WorldQuad,
+    /// Draws the ui texture into eye images, created for the format of the first eye image
+    compositor: Option<Renderer>,
+    /// The ui texture & its id in `compositor`
+    registered: Option<(Arc<ImageView<AttachmentImage>>, egui::TextureId)>,
+    events: Vec<egui::Event>,
+    /// Where the last ray hit the ui, in points
+    pointer_pos: Option<egui::Pos2>,
+    pressed: bool,
+}
+
+impl XrGui {
+    /// Creates a gui laid out on a texture of `format` sized `dimensions` in pixels, placed at `quad` in your
+    /// reference space
+    pub fn new(
+        gfx_queue: Arc<Queue>,
+        format: Format,
+        dimensions: [u32; 2],
+        quad: WorldQuad,
+    ) -> Result<XrGui, GuiError> {
+        Ok(XrGui {
+            gui: HeadlessGui::new(gfx_queue, format, dimensions)?,
+            quad,
+            compositor: None,
+            registered: None,
+            events: vec![],
+            pointer_pos: None,
+            pressed: false,
+        })
+    }
+
+    pub fn context(&self) -> egui::Context {
+        self.gui.context()
+    }
+
+    /// The gui drawing the ui texture, e.g. to register user images or change its dimensions
+    pub fn headless(&mut self) -> &mut HeadlessGui {
+        &mut self.gui
+    }
+
+    pub fn quad(&self) -> WorldQuad {
+        self.quad
+    }
+
+    /// Moves the ui, e.g. to keep it attached to a controller
+    pub fn set_quad(&mut self, quad: WorldQuad) {
+        self.quad = quad;
+    }
+
+    /// Points a controller's aim ray (in the quad's reference space) at the ui, `pressed` being e.g. the state of its
+    /// trigger. Queues the pointer events for the next [`XrGui::run`] & returns whether the ray hits the ui, so that
+    /// the press can be kept from your scene. Presses starting off the ui are ignored, releases aren't.
+    pub fn handle_ray(
+        &mut self,
+        ray_origin: [f32; 3],
+        ray_direction: [f32; 3],
+        pressed: bool,
+    ) -> bool {
+        let pos = self.quad.ray_uv(ray_origin, ray_direction).map(|uv| self.gui.pos_from_uv(uv));
+        if let Some(pos) = pos {
+            self.events.push(egui::Event::PointerMoved(pos));
+            self.pointer_pos = Some(pos);
+        }
+        if pressed != self.pressed && (!pressed || pos.is_some()) {
+            if let Some(pointer_pos) = self.pointer_pos {
+                self.events.push(egui::Event::PointerButton {
+                    pos: pointer_pos,
+                    button: egui::PointerButton::Primary,
+                    pressed,
+                    modifiers: Default::default(),
+                });
+                self.pressed = pressed;
+            }
+        }
+        // Drags continue off the ui until released
+        if pos.is_none() && !self.pressed && self.pointer_pos.take().is_some() {
+            self.events.push(egui::Event::PointerGone);
+        }
+        pos.is_some()
+    }
+
+    /// Runs a frame with `raw_input` & the pointer events of the rays handled since the last frame, see
+    /// [`HeadlessGui::run`]. Give the time of the frame (e.g. its predicted display time) for animations.
+    pub fn run(
+        &mut self,
+        mut raw_input: egui::RawInput,
+        run_ui: impl FnOnce(&egui::Context),
+    ) -> egui::PlatformOutput {
+        raw_input.events.splice(0..0, self.events.drain(..));
+        self.gui.run(raw_input, run_ui)
+    }
+
+    /// Draws the last frame run on the ui texture & returns it with the future of the draw, e.g. to copy into the
+    /// image of a quad layer's swapchain. See [`HeadlessGui::draw_to_texture`].
+    pub fn draw_ui<F>(&mut self, before_future: F) -> Result<DrawnTexture, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        self.gui.draw_to_texture(before_future)
+    }
+
+    /// Pose & size to submit the ui texture with as a quad layer
+    pub fn quad_layer(&self) -> XrQuadLayer {
+        let WorldQuad { origin, right, down } = self.quad;
+        let position = [
+            origin[0] + (right[0] + down[0]) * 0.5,
+            origin[1] + (right[1] + down[1]) * 0.5,
+            origin[2] + (right[2] + down[2]) * 0.5,
+        ];
+        // Quad layers face +Z with +Y up
+        let x = normalize(right);
+        let y = normalize([-down[0], -down[1], -down[2]]);
+        let z = cross(x, y);
+        XrQuadLayer {
+            position,
+            orientation: quaternion_from_axes(x, y, z),
+            size: [dot(right, right).sqrt(), dot(down, down).sqrt()],
+        }
+    }
+
+    /// Draws the ui texture of [`XrGui::draw_ui`] where its quad is into the image of each eye, over what's already
+    /// in it (there's no depth test), e.g. when the runtime has no quad layers or the ui must be in a projection
+    /// layer. Each image is drawn with the eye of the same index, so there must be one per eye, & all must have the
+    /// same format. Draw the ui first & chain its future into `before_future`.
+    pub fn draw_into_eyes<F>(
+        &mut self,
+        before_future: F,
+        eyes: &[XrEye],
+        images: &[Arc<dyn ImageViewAbstract + 'static>],
+    ) -> Result<Box<dyn GpuFuture>, GuiError>
+    where
+        F: GpuFuture + 'static,
+    {
+        if eyes.len() != images.len() {
+            return Err(GuiError::WrongEyeImageCount { found: images.len(), expected: eyes.len() });
+        }
+        let texture = match self.gui.texture() {
+            Some(texture) => texture,
+            None => return Err(GuiError::NothingDrawn),
+        };
+        let mut future: Box<dyn GpuFuture> = Box::new(before_future);
+        let format = match images.first() {
+            Some(image) => image.format(),
+            None => return Ok(future),
+        };
+
+        let compositor = match &mut self.compositor {
+            Some(compositor) if Some(compositor.format()) == format => compositor,
+            compositor => {
+                let format = format.ok_or(GuiError::WrongRenderTargetFormat {
+                    found: None,
+                    expected: self.gui.format(),
+                })?;
+                self.registered = None;
+                compositor.insert(Renderer::new_with_render_pass(self.gui.queue(), format, true)?)
+            }
+        };
+        let texture_id = match &self.registered {
+            Some((registered, texture_id)) if Arc::ptr_eq(registered, &texture) => *texture_id,
+            registered => {
+                if let Some((_, texture_id)) = registered {
+                    compositor.unregister_image(*texture_id);
+                }
+                let texture_id = compositor.register_image(texture.clone())?;
+                self.registered = Some((texture, texture_id));
+                texture_id
+            }
+        };
+
+        let mut mesh = egui::Mesh::with_texture(texture_id);
+        mesh.add_rect_with_uv(
+            Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)),
+            Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        let clipped_meshes =
+            [ClippedPrimitive { clip_rect: Rect::EVERYTHING, primitive: Primitive::Mesh(mesh) }];
+        let model = quad_model(self.quad);
+        for (eye, image) in eyes.iter().zip(images) {
+            compositor.check_render_target_format(image)?;
+            compositor.set_projection(Some(multiply(eye.projection, multiply(eye.view, model))));
+            future = compositor.draw_on_image(
+                &clipped_meshes,
+                &TexturesDelta::default(),
+                1.0,
+                future,
+                image.clone(),
+            )?;
+        }
+        Ok(future)
+    }
+}
+
+/// Column major matrix from the unit square the composited mesh spans to the quad
+fn quad_model(quad: WorldQuad) -> [[f32; 4]; 4] {
+    let WorldQuad { origin, right, down } = quad;
+    let normal = cross(right, down);
+    [
+        [right[0], right[1], right[2], 0.0],
+        [down[0], down[1], down[2], 0.0],
+        [normal[0], normal[1], normal[2], 0.0],
+        [origin[0], origin[1], origin[2], 1.0],
+    ]
+}
+
+/// Product `a * b` of column major matrices
+fn multiply(a: [[f32; 4]; 4], b: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut product = [[0.0; 4]; 4];
+    for (column, b_column) in product.iter_mut().zip(b.iter()) {
+        for (row, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+        }
+    }
+    product
+}
+
+/// Quaternion `[x, y, z, w]` rotating the basis axes onto the orthonormal `x`, `y` & `z`
+fn quaternion_from_axes(x: [f32; 3], y: [f32; 3], z: [f32; 3]) -> [f32; 4] {
+    let trace = x[0] + y[1] + z[2];
+    if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [(y[2] - z[1]) / s, (z[0] - x[2]) / s, (x[1] - y[0]) / s, 0.25 * s]
+    } else if x[0] > y[1] && x[0] > z[2] {
+        let s = (1.0 + x[0] - y[1] - z[2]).sqrt() * 2.0;
+        [0.25 * s, (y[0] + x[1]) / s, (z[0] + x[2]) / s, (y[2] - z[1]) / s]
+    } else if y[1] > z[2] {
+        let s = (1.0 + y[1] - x[0] - z[2]).sqrt() * 2.0;
+        [(y[0] + x[1]) / s, 0.25 * s, (z[1] + y[2]) / s, (z[0] - x[2]) / s]
+    } else {
+        let s = (1.0 + z[2] - x[0] - y[1]).sqrt() * 2.0;
+        [(z[0] + x[2]) / s, (z[1] + y[2]) / s, 0.25 * s, (x[1] - y[0]) / s]
+    }
+}